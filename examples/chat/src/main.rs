@@ -29,6 +29,7 @@ impl Actor for Server {
 
 #[derive(Message)]
 #[rtype(result = "()")]
+#[allow(dead_code)]
 struct TcpConnect(pub TcpStream, pub net::SocketAddr);
 
 /// Handle stream of TcpStream's
@@ -105,14 +105,14 @@ impl Handler<Connect> for ChatServer {
         println!("Someone joined");
 
         // notify all users in same room
-        self.send_message(&"Main".to_owned(), "Someone joined", 0);
+        self.send_message("Main", "Someone joined", 0);
 
         // register session with random id
         let id = rand::thread_rng().gen::<usize>();
         self.sessions.insert(id, msg.addr);
 
         // auto join session to Main room
-        self.rooms.get_mut(&"Main".to_owned()).unwrap().insert(id);
+        self.rooms.get_mut("Main").unwrap().insert(id);
 
         // send id back
         id
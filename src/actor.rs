@@ -1,16 +1,19 @@
+use std::any::Any;
 use std::time::Duration;
 
 use actix_rt::Arbiter;
 use futures_util::stream::Stream;
 use log::error;
 
-use crate::address::{channel, Addr};
+use crate::address::{
+    channel, ActorId, Addr, Terminated, ToEnvelope, WatchHandle, Watchable,
+};
 use crate::context::Context;
 use crate::contextitems::{
     ActorDelayedMessageItem, ActorMessageItem, ActorMessageStreamItem,
 };
 use crate::fut::{ActorFuture, ActorStream};
-use crate::handler::{Handler, Message};
+use crate::handler::{Handler, Message, ResponseActFuture};
 use crate::mailbox::DEFAULT_CAPACITY;
 use crate::stream::StreamHandler;
 use crate::utils::{IntervalFunc, TimerFunc};
@@ -80,6 +83,18 @@ pub trait Actor: Sized + Unpin + 'static {
     /// Called when an actor gets polled the first time.
     fn started(&mut self, ctx: &mut Self::Context) {}
 
+    /// Called after `started()`, returned future runs before the mailbox
+    /// gets polled.
+    ///
+    /// The future runs again once a supervised actor gets restarted.
+    /// Async lifecycle hooks are not supported by `SyncContext`.
+    fn started_async(
+        &mut self,
+        ctx: &mut Self::Context,
+    ) -> Option<ResponseActFuture<Self, ()>> {
+        None
+    }
+
     /// Called after an actor is in `Actor::Stopping` state.
     ///
     /// There can be several reasons for stopping:
@@ -88,12 +103,27 @@ pub trait Actor: Sized + Unpin + 'static {
     /// - All addresses to the current actor get dropped and no more
     ///   evented objects are left in the context.
     ///
+    /// The reason is available via `ActorContext::stop_reason()`.
+    ///
     /// An actor can return from the stopping state to the running
     /// state by returning `Running::Continue`.
     fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
         Running::Stop
     }
 
+    /// Called once `stopping()` returns `Running::Stop`, returned future
+    /// runs before `stopped()` gets called.
+    ///
+    /// Neither mailbox nor spawned futures are polled while the future
+    /// runs. The future is dropped once stopping deadline elapses, see
+    /// `Context::set_stopping_deadline()`.
+    fn stopping_async(
+        &mut self,
+        ctx: &mut Self::Context,
+    ) -> Option<ResponseActFuture<Self, ()>> {
+        None
+    }
+
     /// Called after an actor is stopped.
     ///
     /// This method can be used to perform any needed cleanup work or
@@ -234,6 +264,38 @@ pub enum Running {
     Continue,
 }
 
+/// The reason why an actor stopped.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StopReason {
+    /// Actor got stopped with `ActorContext::stop()` or
+    /// `ActorContext::terminate()`.
+    Normal,
+    /// All addresses to the actor got dropped and no evented objects
+    /// are left in its context.
+    MailboxClosed,
+    /// Message handler panicked.
+    Panic(String),
+    /// Actor got stopped by its supervisor or by arbiter shutdown.
+    Shutdown,
+    /// Linked actor stopped.
+    Linked(ActorId),
+    /// Actor stopped because of an error.
+    Error(String),
+}
+
+impl StopReason {
+    /// Create reason from a payload of caught panic.
+    pub(crate) fn from_panic(err: &(dyn Any + Send)) -> StopReason {
+        if let Some(msg) = err.downcast_ref::<&str>() {
+            StopReason::Panic((*msg).to_owned())
+        } else if let Some(msg) = err.downcast_ref::<String>() {
+            StopReason::Panic(msg.clone())
+        } else {
+            StopReason::Panic("Box<Any>".to_owned())
+        }
+    }
+}
+
 impl ActorState {
     /// Indicates whether the actor is alive.
     pub fn alive(self) -> bool {
@@ -253,6 +315,7 @@ impl ActorState {
 ///
 /// The execution context defines the type of execution, and the
 /// actor's communication channels (message handling).
+#[allow(unused_variables)]
 pub trait ActorContext: Sized {
     /// Immediately stop processing incoming messages and switch to a
     /// `stopping` state. This only affects actors that are currently
@@ -266,6 +329,26 @@ pub trait ActorContext: Sized {
 
     /// Retrieve the current Actor execution state.
     fn state(&self) -> ActorState;
+
+    /// Same as `stop`, but records the reason of stopping.
+    ///
+    /// If actor is already stopping, the first reason is kept.
+    fn stop_with(&mut self, reason: StopReason) {
+        self.stop()
+    }
+
+    /// Same as `terminate`, but records the reason of stopping.
+    ///
+    /// If actor is already stopping, the first reason is kept.
+    fn terminate_with(&mut self, reason: StopReason) {
+        self.terminate()
+    }
+
+    /// Returns the reason why the actor is stopping, `None` if the
+    /// actor is running.
+    fn stop_reason(&self) -> Option<&StopReason> {
+        None
+    }
 }
 
 /// Asynchronous execution context.
@@ -461,12 +544,99 @@ where
     {
         self.spawn(IntervalFunc::new(dur, f).finish())
     }
+
+    /// Watches another actor.
+    ///
+    /// `Terminated` message with the stop reason gets delivered to this actor once the
+    /// watched actor stops. If the watched actor is already stopped,
+    /// message gets delivered immediately. Watching does not keep this
+    /// actor alive, once this actor stops its watch is eventually dropped.
+    ///
+    /// Returned handle can be used to stop watching.
+    ///
+    /// ```rust
+    /// use actix::prelude::*;
+    ///
+    /// struct Worker;
+    ///
+    /// impl Actor for Worker {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// struct Watcher;
+    ///
+    /// impl Actor for Watcher {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Context<Self>) {
+    ///         let worker = Worker.start();
+    ///         ctx.watch(&worker);
+    ///     }
+    /// }
+    ///
+    /// impl Handler<Terminated> for Watcher {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, msg: Terminated, ctx: &mut Context<Self>) {
+    ///         println!("{} stopped", msg.id);
+    ///         System::current().stop();
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let sys = System::new("example");
+    ///     let addr = Watcher.start();
+    ///     sys.run();
+    /// }
+    /// ```
+    fn watch<W>(&mut self, actor: &W) -> WatchHandle
+    where
+        W: Watchable,
+        A: Handler<Terminated>,
+        A::Context: ToEnvelope<A, Terminated>,
+    {
+        let addr = self.address();
+        let alive = addr.alive_handle();
+        let addr = addr.downgrade();
+        actor.on_terminated(
+            Box::new(move |msg| {
+                if let Some(addr) = addr.upgrade() {
+                    addr.do_send(msg);
+                }
+            }),
+            alive,
+        )
+    }
+
+    /// Links this actor with another actor.
+    ///
+    /// Link is bidirectional, once either of the linked actors stops,
+    /// the other one gets stopped as well with `StopReason::Linked`.
+    /// Link does not keep actors alive.
+    fn link<W>(&mut self, actor: &W)
+    where
+        W: Watchable,
+    {
+        let addr = self.address();
+
+        let stop = addr.stop_handle();
+        actor.on_terminated(
+            Box::new(move |msg| stop(StopReason::Linked(msg.id))),
+            addr.alive_handle(),
+        );
+
+        let stop = actor.stop_handle();
+        addr.on_terminated(
+            Box::new(move |msg| stop(StopReason::Linked(msg.id))),
+            actor.alive_handle(),
+        );
+    }
 }
 
 /// A handle to a spawned future.
 ///
 /// Can be used to cancel the future.
-#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash, Default)]
 pub struct SpawnHandle(usize);
 
 impl SpawnHandle {
@@ -479,9 +649,3 @@ impl SpawnHandle {
         self.0
     }
 }
-
-impl Default for SpawnHandle {
-    fn default() -> SpawnHandle {
-        SpawnHandle(0)
-    }
-}
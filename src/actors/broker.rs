@@ -0,0 +1,282 @@
+//! Publish/subscribe broker.
+//!
+//! [`Broker`](struct.Broker.html) is a system service which delivers
+//! published messages to all subscribers of the message type. Messages can
+//! also be published to a named topic, such messages are delivered only to
+//! subscribers of the topic. Subscribers are `Recipient`s, the ones which
+//! are disconnected are removed once next message is published.
+//!
+//! Actors subscribe with [`BrokerSubscribe`](trait.BrokerSubscribe.html)
+//! and publish with [`BrokerIssue`](trait.BrokerIssue.html). Published
+//! messages must be `Clone` and have `()` result, see
+//! [`BrokerMsg`](trait.BrokerMsg.html).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::actors::broker::{BrokerIssue, BrokerSubscribe};
+//!
+//! #[derive(Clone, Message)]
+//! #[rtype(result = "()")]
+//! struct Joined(String);
+//!
+//! struct Room;
+//!
+//! impl Actor for Room {
+//!     type Context = Context<Self>;
+//!
+//!     fn started(&mut self, ctx: &mut Context<Self>) {
+//!         self.subscribe::<Joined>(ctx);
+//!     }
+//! }
+//!
+//! impl Handler<Joined> for Room {
+//!     type Result = ();
+//!
+//!     fn handle(&mut self, msg: Joined, _: &mut Context<Self>) {
+//!         println!("{} joined", msg.0);
+//!         System::current().stop();
+//!     }
+//! }
+//!
+//! struct Lobby;
+//!
+//! impl Actor for Lobby {
+//!     type Context = Context<Self>;
+//!
+//!     fn started(&mut self, _: &mut Context<Self>) {
+//!         self.issue_async(Joined("alice".to_owned()));
+//!     }
+//! }
+//!
+//! System::run(|| {
+//!     Room.start();
+//!     Lobby.start();
+//! })
+//! .unwrap();
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::address::SendError;
+use crate::prelude::*;
+
+/// Message which can be published by the broker.
+pub trait BrokerMsg: Message<Result = ()> + Clone + Send + 'static {}
+
+impl<M: Message<Result = ()> + Clone + Send + 'static> BrokerMsg for M {}
+
+type Key = (TypeId, Option<String>);
+
+/// Publish/subscribe broker service.
+#[derive(Default)]
+pub struct Broker {
+    subscribers: HashMap<Key, Box<dyn Any>>,
+}
+
+impl fmt::Debug for Broker {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Broker")
+            .field("subscriptions", &self.subscribers.len())
+            .finish()
+    }
+}
+
+impl Actor for Broker {
+    type Context = Context<Self>;
+}
+
+impl Supervised for Broker {}
+impl SystemService for Broker {}
+
+impl Broker {
+    /// Subscribes recipient to messages of type `M`.
+    pub fn subscribe<M: BrokerMsg>(recipient: Recipient<M>) {
+        Broker::from_registry().do_send(Subscribe::new(recipient));
+    }
+
+    /// Publishes message to subscribers of type `M`.
+    pub fn issue_async<M: BrokerMsg>(msg: M) {
+        Broker::from_registry().do_send(Publish::new(msg));
+    }
+
+    fn recipients<M: BrokerMsg>(
+        &mut self,
+        topic: Option<String>,
+    ) -> &mut Vec<Recipient<M>> {
+        self.subscribers
+            .entry((TypeId::of::<M>(), topic))
+            .or_insert_with(|| Box::new(Vec::<Recipient<M>>::new()))
+            .downcast_mut()
+            .expect("recipients are stored by message type")
+    }
+}
+
+/// Subscribes recipient to messages of type `M` published by the broker.
+pub struct Subscribe<M: BrokerMsg> {
+    topic: Option<String>,
+    recipient: Recipient<M>,
+}
+
+impl<M: BrokerMsg> Subscribe<M> {
+    /// Subscribes to messages published without topic.
+    pub fn new(recipient: Recipient<M>) -> Self {
+        Subscribe {
+            topic: None,
+            recipient,
+        }
+    }
+
+    /// Subscribes to messages published to `topic`.
+    pub fn topic<T: Into<String>>(topic: T, recipient: Recipient<M>) -> Self {
+        Subscribe {
+            topic: Some(topic.into()),
+            recipient,
+        }
+    }
+}
+
+impl<M: BrokerMsg> Message for Subscribe<M> {
+    type Result = ();
+}
+
+impl<M: BrokerMsg> Handler<Subscribe<M>> for Broker {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe<M>, _: &mut Context<Self>) {
+        let recipients = self.recipients::<M>(msg.topic);
+        recipients.retain(|rcp| rcp.connected());
+        if !recipients.contains(&msg.recipient) {
+            recipients.push(msg.recipient);
+        }
+    }
+}
+
+/// Publishes message to subscribers.
+pub struct Publish<M: BrokerMsg> {
+    topic: Option<String>,
+    msg: M,
+}
+
+impl<M: BrokerMsg> Publish<M> {
+    /// Publishes message to subscribers without topic.
+    pub fn new(msg: M) -> Self {
+        Publish { topic: None, msg }
+    }
+
+    /// Publishes message to subscribers of `topic`.
+    pub fn topic<T: Into<String>>(topic: T, msg: M) -> Self {
+        Publish {
+            topic: Some(topic.into()),
+            msg,
+        }
+    }
+}
+
+impl<M: BrokerMsg> Message for Publish<M> {
+    type Result = ();
+}
+
+impl<M: BrokerMsg> Handler<Publish<M>> for Broker {
+    type Result = ();
+
+    fn handle(&mut self, msg: Publish<M>, _: &mut Context<Self>) {
+        let key = (TypeId::of::<M>(), msg.topic);
+        let recipients = match self.subscribers.get_mut(&key) {
+            Some(recipients) => recipients,
+            None => return,
+        };
+        let recipients = recipients
+            .downcast_mut::<Vec<Recipient<M>>>()
+            .expect("recipients are stored by message type");
+
+        let item = msg.msg;
+        recipients.retain(|rcp| match rcp.do_send(item.clone()) {
+            Err(SendError::Closed(_)) => false,
+            _ => true,
+        });
+        if recipients.is_empty() {
+            self.subscribers.remove(&key);
+        }
+    }
+}
+
+/// Helper trait to subscribe actor to the broker.
+pub trait BrokerSubscribe: Actor<Context = Context<Self>> {
+    /// Subscribes to messages of type `M`.
+    ///
+    /// Actor does not handle other messages until the subscription is
+    /// registered.
+    fn subscribe<M>(&self, ctx: &mut Context<Self>)
+    where
+        Self: Handler<M>,
+        M: BrokerMsg,
+    {
+        let msg = Subscribe::new(ctx.address().recipient());
+        wait(ctx, Broker::from_registry().send(msg));
+    }
+
+    /// Subscribes to messages of type `M` published to `topic`.
+    fn subscribe_topic<M, T>(&self, topic: T, ctx: &mut Context<Self>)
+    where
+        Self: Handler<M>,
+        M: BrokerMsg,
+        T: Into<String>,
+    {
+        let msg = Subscribe::topic(topic, ctx.address().recipient());
+        wait(ctx, Broker::from_registry().send(msg));
+    }
+}
+
+impl<A: Actor<Context = Context<A>>> BrokerSubscribe for A {}
+
+/// Helper trait to publish messages from an actor.
+pub trait BrokerIssue: Actor<Context = Context<Self>> {
+    /// Publishes message without waiting for its delivery.
+    fn issue_async<M: BrokerMsg>(&self, msg: M) {
+        Broker::from_registry().do_send(Publish::new(msg));
+    }
+
+    /// Publishes message, actor does not handle other messages until the
+    /// broker delivers it to subscribers.
+    fn issue_sync<M: BrokerMsg>(&self, msg: M, ctx: &mut Context<Self>) {
+        wait(ctx, Broker::from_registry().send(Publish::new(msg)));
+    }
+
+    /// Publishes message to `topic` without waiting for its delivery.
+    fn issue_topic_async<M, T>(&self, topic: T, msg: M)
+    where
+        M: BrokerMsg,
+        T: Into<String>,
+    {
+        Broker::from_registry().do_send(Publish::topic(topic, msg));
+    }
+
+    /// Publishes message to `topic`, actor does not handle other messages
+    /// until the broker delivers it to subscribers.
+    fn issue_topic_sync<M, T>(&self, topic: T, msg: M, ctx: &mut Context<Self>)
+    where
+        M: BrokerMsg,
+        T: Into<String>,
+    {
+        wait(
+            ctx,
+            Broker::from_registry().send(Publish::topic(topic, msg)),
+        );
+    }
+}
+
+impl<A: Actor<Context = Context<A>>> BrokerIssue for A {}
+
+/// Blocks actor's mailbox until the broker handles the request.
+fn wait<A, M>(ctx: &mut Context<A>, req: Request<Broker, M>)
+where
+    A: Actor<Context = Context<A>>,
+    M: Message<Result = ()> + Send + 'static,
+    Broker: Handler<M>,
+{
+    ctx.wait(fut::wrap_future::<_, A>(req).map(|_, _, _| ()));
+}
@@ -13,76 +13,391 @@
 //! ```
 //! Then, the actor should be used as a system service (or arbiter service, but
 //! take care that all the places which will use the mocked actor are on the
-//! same arbiter). Thus, in a test, it will retrieve the mocker from the
-//! registry instead of the actual actor.
+//! same arbiter). In a test, the mocker is built with handlers for every
+//! message type it expects and installed into the registry with
+//! `Mocker::install_system_service()` or `Mocker::install_arbiter_service()`,
+//! so code under test retrieves the mocker instead of the actual actor.
 //!
-//! To set the mock function in the actor, the `init_actor` function
-//! is used, which allows the state of an actor to be set when it is
-//! started as an arbiter or system service. A closure which takes
-//! `Box<Any>` is evaluated for every message, and must return
-//! `Box<Any>`, specifically the return type for the message type
-//! send.
+//! Every received message is recorded in [`Calls`](struct.Calls.html).
+//! Expected number of calls and their order are verified with
+//! `Calls::verify()`, tests should call it before the system stops. Mocker
+//! which is dropped with unmet expectations that are not verified yet
+//! panics, unless the thread is already panicking. The panic of a mocker
+//! dropped by a running system does not fail the test though. Message
+//! without a handler panics.
 //!
-//! See the mock example to see how it can be used.
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::actors::mocker::Mocker;
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Query(String);
+//!
+//! impl Message for Query {
+//!     type Result = Option<u64>;
+//! }
+//!
+//! #[derive(Default)]
+//! struct Database;
+//!
+//! impl Actor for Database {
+//!     type Context = Context<Self>;
+//! }
+//!
+//! impl Supervised for Database {}
+//! impl SystemService for Database {}
+//!
+//! System::run(|| {
+//!     let mocker = Mocker::<Database>::new()
+//!         .on(|msg: &Query| if msg.0 == "answer" { Some(42) } else { None })
+//!         .times(2);
+//!     let calls = mocker.calls();
+//!     mocker.install_system_service();
+//!
+//!     actix::spawn(async move {
+//!         let db = Mocker::<Database>::from_registry();
+//!         assert_eq!(db.send(Query("answer".into())).await.unwrap(), Some(42));
+//!         assert_eq!(db.send(Query("question".into())).await.unwrap(), None);
+//!
+//!         assert_eq!(calls.messages::<Query>()[1], Query("question".into()));
+//!         calls.verify();
+//!         System::current().stop();
+//!     });
+//! })
+//! .unwrap();
+//! ```
 
-use std::any::Any;
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::rc::Rc;
 
 use crate::handler::MessageResponse;
 use crate::prelude::*;
+use crate::registry::{Registry, SystemRegistry};
+
+type MockHandler<M> = Box<dyn FnMut(&M) -> <M as Message>::Result>;
+
+type MockFn<T> = Box<dyn FnMut(Box<dyn Any>, &mut Context<Mocker<T>>) -> Box<dyn Any>>;
 
 /// This actor is able to wrap another actor and accept all the messages the
-/// wrapped actor can, passing it to a closure which can mock the response of
+/// wrapped actor can, passing them to handlers which mock the responses of
 /// the actor.
-#[allow(clippy::type_complexity)]
-pub struct Mocker<T: Sized + Unpin + 'static> {
-    phantom: PhantomData<T>,
-    mock: Box<dyn FnMut(Box<dyn Any>, &mut Context<Mocker<T>>) -> Box<dyn Any>>,
-}
-
-impl<T: Unpin> Mocker<T> {
-    #[allow(clippy::type_complexity)]
-    pub fn mock(
-        mock: Box<dyn FnMut(Box<dyn Any>, &mut Context<Mocker<T>>) -> Box<dyn Any>>,
-    ) -> Mocker<T> {
-        Mocker::<T> {
+pub struct Mocker<T: 'static> {
+    phantom: PhantomData<fn() -> T>,
+    handlers: HashMap<TypeId, Box<dyn Any>>,
+    /// Handles messages without typed handler, set by `Mocker::mock()`.
+    fallback: Option<MockFn<T>>,
+    last: Option<(TypeId, &'static str)>,
+    state: Rc<RefCell<State>>,
+}
+
+struct Call {
+    id: TypeId,
+    name: &'static str,
+    /// Message handled by a typed handler.
+    msg: Option<Box<dyn Any>>,
+}
+
+struct Expected {
+    id: TypeId,
+    name: &'static str,
+    times: usize,
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<Call>,
+    expected: Vec<Expected>,
+    ordered: bool,
+    verified: bool,
+}
+
+impl<T> Mocker<T> {
+    /// Creates mocker without handlers.
+    pub fn new() -> Self {
+        Mocker {
             phantom: PhantomData,
-            mock,
+            handlers: HashMap::new(),
+            fallback: None,
+            last: None,
+            state: Rc::new(RefCell::new(State::default())),
+        }
+    }
+
+    /// Creates mocker which passes every message to `mock`.
+    ///
+    /// The closure gets the boxed message and returns boxed
+    /// `Option<M::Result>`.
+    #[deprecated(since = "0.10.0", note = "Use `Mocker::new()` with `Mocker::on()`")]
+    pub fn mock(mock: MockFn<T>) -> Self {
+        let mut mocker = Self::new();
+        mocker.fallback = Some(mock);
+        mocker
+    }
+
+    /// Registers handler of `M` messages.
+    ///
+    /// Handler gets reference to the message and returns response to the
+    /// sender, the message is recorded afterwards. Registering another
+    /// handler for the same message type replaces previous one.
+    pub fn on<M, F>(mut self, f: F) -> Self
+    where
+        M: Message + 'static,
+        F: FnMut(&M) -> M::Result + 'static,
+    {
+        let id = TypeId::of::<M>();
+        let handler: MockHandler<M> = Box::new(f);
+        self.handlers.insert(id, Box::new(handler));
+        self.last = Some((id, type_name::<M>()));
+        self
+    }
+
+    /// Expects handler registered by the last `on()` call to be called
+    /// exactly `times` times.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no handler is registered yet.
+    pub fn times(self, times: usize) -> Self {
+        let (id, name) = self
+            .last
+            .expect("Mocker::times() is called before Mocker::on()");
+        {
+            let mut state = self.state.borrow_mut();
+            state.expected.retain(|exp| exp.id != id);
+            state.expected.push(Expected { id, name, times });
+        }
+        self
+    }
+
+    /// Expects messages with expected number of calls to be received in
+    /// the order their expectations are registered.
+    ///
+    /// Messages without expectations are not checked.
+    pub fn in_order(self) -> Self {
+        self.state.borrow_mut().ordered = true;
+        self
+    }
+
+    /// Returns handle to messages received by the mocker.
+    pub fn calls(&self) -> Calls {
+        Calls {
+            state: Rc::clone(&self.state),
         }
     }
 }
 
-impl<T: SystemService> SystemService for Mocker<T> {}
-impl<T: ArbiterService> ArbiterService for Mocker<T> {}
-impl<T: Unpin> Supervised for Mocker<T> {}
+impl<T: SystemService> Mocker<T> {
+    /// Starts mocker and installs it into `SystemRegistry`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the service is already running.
+    pub fn install_system_service(self) -> Addr<Self> {
+        let addr = self.start();
+        SystemRegistry::set(addr.clone());
+        addr
+    }
+}
 
-impl<T: Unpin> Default for Mocker<T> {
+impl<T: ArbiterService> Mocker<T> {
+    /// Starts mocker and installs it into `Registry` of the current arbiter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the service is already running.
+    pub fn install_arbiter_service(self) -> Addr<Self> {
+        let addr = self.start();
+        Registry::set(addr.clone());
+        addr
+    }
+}
+
+impl<T> fmt::Debug for Mocker<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Mocker")
+            .field("actor", &type_name::<T>())
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+/// Empty mocker, every received message panics.
+impl<T> Default for Mocker<T> {
     fn default() -> Self {
-        panic!("Mocker actor used before set")
+        Self::new()
     }
 }
 
-impl<T: Sized + Unpin + 'static> Actor for Mocker<T> {
+impl<T: SystemService> SystemService for Mocker<T> {}
+impl<T: ArbiterService> ArbiterService for Mocker<T> {}
+impl<T> Supervised for Mocker<T> {}
+
+impl<T> Actor for Mocker<T> {
     type Context = Context<Self>;
 }
 
-impl<M: 'static, T: Sized + Unpin + 'static> Handler<M> for Mocker<T>
+impl<T> Drop for Mocker<T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let res = self.state.borrow_mut().verify();
+        if let Err(err) = res {
+            panic!("{}: {}", type_name::<Self>(), err);
+        }
+    }
+}
+
+impl<M, T> Handler<M> for Mocker<T>
 where
-    M: Message,
-    <M as Message>::Result: MessageResponse<Mocker<T>, M>,
+    M: Message + 'static,
+    M::Result: MessageResponse<Mocker<T>, M>,
 {
     type Result = M::Result;
+
     fn handle(&mut self, msg: M, ctx: &mut Self::Context) -> M::Result {
-        let mut ret = (self.mock)(Box::new(msg), ctx);
-        let out = mem::replace(
-            ret.downcast_mut::<Option<M::Result>>()
-                .expect("wrong return type for message"),
-            None,
-        );
-        match out {
-            Some(a) => a,
-            _ => panic!(),
+        let handler = self
+            .handlers
+            .get_mut(&TypeId::of::<M>())
+            .and_then(|handler| handler.downcast_mut::<MockHandler<M>>());
+        let (res, msg): (_, Option<Box<dyn Any>>) =
+            match (handler, self.fallback.as_mut()) {
+                (Some(handler), _) => (handler(&msg), Some(Box::new(msg))),
+                (None, Some(mock)) => {
+                    let res = mock(Box::new(msg), ctx)
+                        .downcast::<Option<M::Result>>()
+                        .expect("wrong return type for message");
+                    (res.expect("mock returned no response"), None)
+                }
+                (None, None) => panic!(
+                    "{}: unexpected message {}",
+                    type_name::<Self>(),
+                    type_name::<M>()
+                ),
+            };
+
+        self.state.borrow_mut().calls.push(Call {
+            id: TypeId::of::<M>(),
+            name: type_name::<M>(),
+            msg,
+        });
+        res
+    }
+}
+
+impl State {
+    fn verify(&mut self) -> Result<(), String> {
+        if self.verified {
+            return Ok(());
+        }
+        self.verified = true;
+
+        for exp in &self.expected {
+            let calls = self.calls.iter().filter(|call| call.id == exp.id).count();
+            if calls != exp.times {
+                return Err(format!(
+                    "{} is expected to be received {} times, received {} times",
+                    exp.name, exp.times, calls
+                ));
+            }
+        }
+
+        if self.ordered {
+            let received: Vec<_> = self
+                .calls
+                .iter()
+                .filter(|call| self.expected.iter().any(|exp| exp.id == call.id))
+                .map(|call| call.name)
+                .collect();
+            let expected: Vec<_> = self
+                .expected
+                .iter()
+                .flat_map(|exp| (0..exp.times).map(move |_| exp.name))
+                .collect();
+            if received != expected {
+                return Err(format!(
+                    "messages are expected in order {:?}, received {:?}",
+                    expected, received
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Messages received by a [`Mocker`](struct.Mocker.html).
+#[derive(Clone)]
+pub struct Calls {
+    state: Rc<RefCell<State>>,
+}
+
+impl fmt::Debug for Calls {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_list().entries(self.types()).finish()
+    }
+}
+
+impl Calls {
+    /// Returns number of received messages.
+    pub fn len(&self) -> usize {
+        self.state.borrow_mut().calls.len()
+    }
+
+    /// Returns `true` if no message is received yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns number of received `M` messages.
+    pub fn count<M: 'static>(&self) -> usize {
+        let id = TypeId::of::<M>();
+        self.state
+            .borrow_mut()
+            .calls
+            .iter()
+            .filter(|call| call.id == id)
+            .count()
+    }
+
+    /// Returns copies of received `M` messages in the order they were
+    /// received.
+    pub fn messages<M: Clone + 'static>(&self) -> Vec<M> {
+        self.state
+            .borrow_mut()
+            .calls
+            .iter()
+            .filter_map(|call| call.msg.as_ref()?.downcast_ref::<M>())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns type names of all received messages in the order they were
+    /// received.
+    pub fn types(&self) -> Vec<&'static str> {
+        self.state
+            .borrow_mut()
+            .calls
+            .iter()
+            .map(|call| call.name)
+            .collect()
+    }
+
+    /// Verifies expected number of calls and their order.
+    ///
+    /// Expectations are verified only once, mocker does not verify them
+    /// again on drop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if expectations are not met.
+    pub fn verify(&self) {
+        let res = self.state.borrow_mut().verify();
+        if let Err(err) = res {
+            panic!("{}", err);
         }
     }
 }
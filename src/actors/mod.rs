@@ -1,6 +1,9 @@
 //! Helper actors
 
+pub mod broker;
 pub mod mocker;
+pub mod probe;
+pub mod router;
 
 #[cfg(feature = "resolver")]
 pub mod resolver;
@@ -0,0 +1,292 @@
+//! Test probe actor.
+//!
+//! [`TestProbe`](struct.TestProbe.html) can be turned into a `Recipient` of
+//! any message type. Received messages are queued and can be asserted on
+//! from the test with `expect_msg()`, `expect_no_msg()` and `receive_n()`.
+//!
+//! Waiting for messages is limited by the probe's timeout, it is driven by
+//! the [`clock`](../../clock/index.html) of the current arbiter, so paused
+//! clock makes timeouts deterministic.
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::actors::probe::TestProbe;
+//!
+//! struct Ping(usize);
+//!
+//! impl Message for Ping {
+//!     type Result = usize;
+//! }
+//!
+//! System::run(|| {
+//!     let probe = TestProbe::new();
+//!     probe.reply(|msg: &Ping| msg.0 + 1);
+//!     let recipient = probe.recipient::<Ping>();
+//!
+//!     actix::spawn(async move {
+//!         assert_eq!(recipient.send(Ping(1)).await.unwrap(), 2);
+//!         assert_eq!(probe.expect_msg::<Ping>().await.0, 1);
+//!         System::current().stop();
+//!     });
+//! })
+//! .unwrap();
+//! ```
+
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{self, Poll, Waker};
+use std::time::Duration;
+
+use crate::clock::{self, Delay};
+use crate::handler::{MessageResponse, ResponseChannel};
+use crate::prelude::*;
+
+/// Default time to wait for a message.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+type Reply<M> = Box<dyn FnMut(&M) -> <M as Message>::Result>;
+
+struct Received {
+    name: &'static str,
+    msg: Box<dyn Any>,
+}
+
+#[derive(Default)]
+struct Inner {
+    queue: VecDeque<Received>,
+    replies: HashMap<TypeId, Box<dyn Any>>,
+    waker: Option<Waker>,
+}
+
+/// Actor which queues received messages for assertions in tests.
+///
+/// Probe actor runs in the arbiter where the probe is created and stops
+/// once all probe's recipients and the probe itself are dropped.
+pub struct TestProbe {
+    addr: Addr<Probe>,
+    inner: Rc<RefCell<Inner>>,
+    timeout: Duration,
+}
+
+impl fmt::Debug for TestProbe {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("TestProbe")
+            .field("queue", &self.inner.borrow().queue.len())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Default for TestProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestProbe {
+    /// Starts probe actor in the current arbiter.
+    pub fn new() -> Self {
+        let inner = Rc::new(RefCell::new(Inner::default()));
+        let addr = Probe {
+            inner: Rc::clone(&inner),
+        }
+        .start();
+        TestProbe {
+            addr,
+            inner,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets time to wait for a message, default is 3 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns recipient of `M` messages delivered to the probe.
+    pub fn recipient<M>(&self) -> Recipient<M>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.addr.clone().recipient()
+    }
+
+    /// Replies to every received `M` message with result of `f`.
+    ///
+    /// Without a reply, response channel of the message is dropped and
+    /// the sender gets `MailboxError::Closed`.
+    pub fn reply<M, F>(&self, f: F)
+    where
+        M: Message + 'static,
+        F: FnMut(&M) -> M::Result + 'static,
+    {
+        let reply: Reply<M> = Box::new(f);
+        self.inner
+            .borrow_mut()
+            .replies
+            .insert(TypeId::of::<M>(), Box::new(reply));
+    }
+
+    /// Returns number of queued messages.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().queue.len()
+    }
+
+    /// Returns `true` if no message is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits for the next message and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no message is received within the timeout or the next
+    /// message is not `M`.
+    pub async fn expect_msg<M: 'static>(&self) -> M {
+        match self.next(self.timeout).await {
+            Some(received) => downcast(received),
+            None => panic!(
+                "timeout ({:?}) while waiting for {}",
+                self.timeout,
+                type_name::<M>()
+            ),
+        }
+    }
+
+    /// Waits for `within` and asserts no message is received meanwhile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a message is received.
+    pub async fn expect_no_msg(&self, within: Duration) {
+        if let Some(received) = self.next(within).await {
+            panic!("received unexpected message {}", received.name);
+        }
+    }
+
+    /// Waits for `n` messages of type `M` and returns them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all messages are not received within the timeout or a
+    /// message is not `M`.
+    pub async fn receive_n<M: 'static>(&self, n: usize) -> Vec<M> {
+        let deadline = clock::Instant::now() + self.timeout;
+        let mut msgs = Vec::with_capacity(n);
+        while msgs.len() < n {
+            let within = deadline.saturating_duration_since(clock::Instant::now());
+            match self.next(within).await {
+                Some(received) => msgs.push(downcast(received)),
+                None => panic!(
+                    "timeout ({:?}) while waiting for {} messages {}, received {}",
+                    self.timeout,
+                    n,
+                    type_name::<M>(),
+                    msgs.len()
+                ),
+            }
+        }
+        msgs
+    }
+
+    fn next(&self, within: Duration) -> Next<'_> {
+        Next {
+            inner: &self.inner,
+            delay: clock::delay_for(within),
+        }
+    }
+}
+
+fn downcast<M: 'static>(received: Received) -> M {
+    match received.msg.downcast::<M>() {
+        Ok(msg) => *msg,
+        Err(_) => panic!(
+            "expected message {}, received {}",
+            type_name::<M>(),
+            received.name
+        ),
+    }
+}
+
+/// Future for the next received message.
+struct Next<'a> {
+    inner: &'a Rc<RefCell<Inner>>,
+    delay: Delay,
+}
+
+impl<'a> Future for Next<'a> {
+    type Output = Option<Received>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            if let Some(received) = inner.queue.pop_front() {
+                return Poll::Ready(Some(received));
+            }
+            inner.waker = Some(cx.waker().clone());
+        }
+        match Pin::new(&mut self.delay).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+struct Probe {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Actor for Probe {
+    type Context = Context<Self>;
+}
+
+impl<M> Handler<M> for Probe
+where
+    M: Message + 'static,
+{
+    type Result = ProbeReply<M>;
+
+    fn handle(&mut self, msg: M, _: &mut Context<Self>) -> ProbeReply<M> {
+        // reply is called without borrow of the probe, it may inspect the
+        // probe or replace the reply
+        let reply = self.inner.borrow_mut().replies.remove(&TypeId::of::<M>());
+        let res = reply.and_then(|mut reply| {
+            let res = reply.downcast_mut::<Reply<M>>().map(|reply| reply(&msg));
+            self.inner
+                .borrow_mut()
+                .replies
+                .entry(TypeId::of::<M>())
+                .or_insert(reply);
+            res
+        });
+
+        let mut inner = self.inner.borrow_mut();
+        inner.queue.push_back(Received {
+            name: type_name::<M>(),
+            msg: Box::new(msg),
+        });
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+        ProbeReply(res)
+    }
+}
+
+/// Response of the probe actor, response channel is dropped if there is
+/// no reply.
+struct ProbeReply<M: Message>(Option<M::Result>);
+
+impl<M: Message + 'static> MessageResponse<Probe, M> for ProbeReply<M> {
+    fn handle<R: ResponseChannel<M>>(self, _: &mut Context<Probe>, tx: Option<R>) {
+        if let (Some(res), Some(tx)) = (self.0, tx) {
+            tx.send(res)
+        }
+    }
+}
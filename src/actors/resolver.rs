@@ -41,7 +41,7 @@ use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
 use trust_dns_resolver::TokioAsyncResolver as AsyncResolver;
 use trust_dns_resolver::{error::ResolveError, lookup_ip::LookupIp};
 
-use crate::clock::Delay;
+use crate::clock::{self, Delay};
 use crate::fut::ActorFuture;
 use crate::fut::Either;
 use crate::prelude::*;
@@ -141,6 +141,7 @@ pub enum ResolverError {
     IoError(io::Error),
 }
 
+#[derive(Default)]
 pub struct Resolver {
     resolver: Option<AsyncResolver>,
     cfg: Option<(ResolverConfig, ResolverOpts)>,
@@ -159,9 +160,13 @@ impl Resolver {
 
 impl Actor for Resolver {
     type Context = Context<Self>;
-    fn started(&mut self, ctx: &mut Self::Context) {
+
+    fn started_async(
+        &mut self,
+        _: &mut Self::Context,
+    ) -> Option<ResponseActFuture<Self, ()>> {
         let cfg = self.cfg.take();
-        ctx.wait(
+        Some(Box::pin(
             async move { cfg }
                 .into_actor(self)
                 .then(
@@ -200,7 +205,7 @@ impl Actor for Resolver {
                     // Keep the resolver itself.
                     this.resolver = Some(resolver_res.unwrap());
                 }),
-        );
+        ))
     }
 }
 
@@ -208,16 +213,6 @@ impl Supervised for Resolver {}
 
 impl SystemService for Resolver {}
 
-impl Default for Resolver {
-    fn default() -> Resolver {
-        Resolver {
-            resolver: None,
-            cfg: None,
-            err: None,
-        }
-    }
-}
-
 impl Handler<Resolve> for Resolver {
     type Result = ResponseActFuture<Self, Result<VecDeque<SocketAddr>, ResolverError>>;
 
@@ -407,7 +402,7 @@ impl TcpConnector {
         TcpConnector {
             addrs,
             stream: None,
-            timeout: tokio::time::delay_for(timeout),
+            timeout: clock::delay_for(timeout),
         }
     }
 }
@@ -422,10 +417,10 @@ impl ActorFuture for TcpConnector {
         _: &mut Context<Resolver>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Self::Output> {
-        let mut this = self.get_mut();
+        let this = self.get_mut();
 
         // timeout
-        if let Poll::Ready(_) = Pin::new(&mut this.timeout).poll(cx) {
+        if Pin::new(&mut this.timeout).poll(cx).is_ready() {
             return Poll::Ready(Err(ResolverError::Timeout));
         }
 
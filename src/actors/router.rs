@@ -0,0 +1,455 @@
+//! Router over a pool of actors.
+//!
+//! [`Router`](struct.Router.html) starts a number of instances of an actor,
+//! possibly on different `Arbiter`s, and forwards every message it receives
+//! to one of them. The router's address accepts all messages the routee
+//! handles, responses are sent back to the requester.
+//!
+//! Messages are routed according to [`Routing`](enum.Routing.html):
+//! round-robin, random or to the routee with the smallest mailbox. Message
+//! types registered with `Router::consistent_hash()` are routed by a key
+//! instead, messages with the same key always go to the same routee as long
+//! as the pool is not resized. Message types registered with
+//! `Router::broadcast()` are delivered to all routees.
+//!
+//! Routees which stop are replaced with new instances as soon as they stop.
+//! The pool can be resized dynamically with a [`Resizer`](struct.Resizer.html).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::actors::router::{Router, Routing};
+//!
+//! struct Job(u32);
+//!
+//! impl Message for Job {
+//!     type Result = u32;
+//! }
+//!
+//! struct Worker;
+//!
+//! impl Actor for Worker {
+//!     type Context = Context<Self>;
+//! }
+//!
+//! impl Handler<Job> for Worker {
+//!     type Result = u32;
+//!
+//!     fn handle(&mut self, msg: Job, _: &mut Context<Self>) -> u32 {
+//!         msg.0 * 2
+//!     }
+//! }
+//!
+//! #[actix_rt::main]
+//! async fn main() {
+//!     let router = Router::new(4, || Worker)
+//!         .routing(Routing::SmallestMailbox)
+//!         .start();
+//!
+//!     assert_eq!(router.send(Job(21)).await.unwrap(), 42);
+//! }
+//! ```
+use std::any::{Any, TypeId};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::stream;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::handler::{MessageResponse, ResponseChannel};
+use crate::prelude::*;
+
+/// Number of points of each routee on the consistent hashing ring.
+const VIRTUAL_NODES: usize = 32;
+
+/// Default interval between checks whether the pool should shrink.
+const DEFAULT_RESIZE_INTERVAL: Duration = Duration::from_secs(1);
+
+type Factory<A> = Arc<dyn Fn() -> A + Send + Sync>;
+type KeyFn<M> = Box<dyn Fn(&M) -> u64>;
+type BroadcastFn<A, M> = Box<dyn Fn(&M, &[Addr<A>]) -> <M as Message>::Result>;
+
+/// How messages are routed to routees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Routing {
+    /// Routees receive messages in turn.
+    RoundRobin,
+    /// Routee is chosen randomly.
+    Random,
+    /// Routee with the smallest number of messages in its mailbox.
+    SmallestMailbox,
+}
+
+impl Default for Routing {
+    fn default() -> Self {
+        Routing::RoundRobin
+    }
+}
+
+/// Resizes the pool of routees depending on the pressure.
+///
+/// The pool grows by one routee when a message is routed while all
+/// routees have messages in their mailboxes, and shrinks by one routee
+/// every interval while more than one routee has an empty mailbox. The
+/// number of routees stays within `lower` and `upper` bounds.
+#[derive(Clone, Copy, Debug)]
+pub struct Resizer {
+    lower: usize,
+    upper: usize,
+    interval: Duration,
+}
+
+impl Resizer {
+    /// Creates resizer which keeps between `lower` and `upper` routees.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lower` is zero or greater than `upper`.
+    pub fn new(lower: usize, upper: usize) -> Self {
+        assert!(lower > 0, "Router requires at least one routee");
+        assert!(lower <= upper, "lower bound is greater than upper bound");
+        Resizer {
+            lower,
+            upper,
+            interval: DEFAULT_RESIZE_INTERVAL,
+        }
+    }
+
+    /// Sets interval between checks whether the pool should shrink,
+    /// default is 1 second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// Actor which routes messages to a pool of actors.
+pub struct Router<A: Actor<Context = Context<A>>> {
+    factory: Factory<A>,
+    size: usize,
+    routing: Routing,
+    resizer: Option<Resizer>,
+    arbiters: Vec<Arbiter>,
+    keys: HashMap<TypeId, Box<dyn Any>>,
+    broadcasts: HashMap<TypeId, Box<dyn Any>>,
+    routees: Vec<Addr<A>>,
+    ring: BTreeMap<u64, usize>,
+    next: usize,
+    placed: usize,
+    seed: u64,
+    terminated: Option<UnboundedSender<ActorId>>,
+}
+
+impl<A: Actor<Context = Context<A>>> fmt::Debug for Router<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Router")
+            .field("routing", &self.routing)
+            .field("resizer", &self.resizer)
+            .field("routees", &self.routees.len())
+            .finish()
+    }
+}
+
+impl<A: Actor<Context = Context<A>>> Router<A> {
+    /// Creates router with `size` routees created by `factory`.
+    ///
+    /// Routees are started once the router is started.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new<F>(size: usize, factory: F) -> Self
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        assert!(size > 0, "Router requires at least one routee");
+        Router {
+            factory: Arc::new(factory),
+            size,
+            routing: Routing::default(),
+            resizer: None,
+            arbiters: Vec::new(),
+            keys: HashMap::new(),
+            broadcasts: HashMap::new(),
+            routees: Vec::new(),
+            ring: BTreeMap::new(),
+            next: 0,
+            placed: 0,
+            seed: RandomState::new().build_hasher().finish() | 1,
+            terminated: None,
+        }
+    }
+
+    /// Sets how messages are routed, default is round-robin.
+    pub fn routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Resizes the pool dynamically, the initial size is clamped to
+    /// resizer's bounds.
+    pub fn resizer(mut self, resizer: Resizer) -> Self {
+        self.resizer = Some(resizer);
+        self
+    }
+
+    /// Starts routees on the arbiters in turn instead of the router's
+    /// arbiter.
+    pub fn arbiters(mut self, arbiters: Vec<Arbiter>) -> Self {
+        self.arbiters = arbiters;
+        self
+    }
+
+    /// Routes messages of type `M` by the key returned by `f`.
+    pub fn consistent_hash<M, K, F>(mut self, f: F) -> Self
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+        K: Hash,
+        F: Fn(&M) -> K + 'static,
+    {
+        let key: KeyFn<M> = Box::new(move |msg| hash(&f(msg)));
+        self.keys.insert(TypeId::of::<M>(), Box::new(key));
+        self
+    }
+
+    /// Delivers messages of type `M` to all routees.
+    pub fn broadcast<M>(mut self) -> Self
+    where
+        A: Handler<M>,
+        M: Message<Result = ()> + Clone + Send + 'static,
+    {
+        let f: BroadcastFn<A, M> = Box::new(|msg, routees| {
+            for addr in routees {
+                addr.do_send(msg.clone());
+            }
+        });
+        self.broadcasts.insert(TypeId::of::<M>(), Box::new(f));
+        self
+    }
+
+    fn start_routee(&mut self) -> Addr<A> {
+        let addr = if self.arbiters.is_empty() {
+            (self.factory)().start()
+        } else {
+            let arb = &self.arbiters[self.placed % self.arbiters.len()];
+            self.placed = self.placed.wrapping_add(1);
+
+            let factory = Arc::clone(&self.factory);
+            A::start_in_arbiter(arb, move |_| factory())
+        };
+
+        if let Some(tx) = self.terminated.clone() {
+            // routee is watched only by its router
+            addr.on_terminated(
+                Box::new(move |msg| {
+                    let _ = tx.send(msg.id);
+                }),
+                Box::new(|| true),
+            );
+        }
+        addr
+    }
+
+    /// Replaces routee which stopped, routees removed by the resizer are
+    /// not in the pool anymore.
+    fn replace_routee(&mut self, id: ActorId) {
+        if let Some(idx) = self.routees.iter().position(|addr| addr.id() == id) {
+            self.routees[idx] = self.start_routee();
+        }
+    }
+
+    fn add_routee(&mut self) {
+        let idx = self.routees.len();
+        let addr = self.start_routee();
+        self.routees.push(addr);
+        for node in 0..VIRTUAL_NODES {
+            self.ring.insert(hash(&(idx, node)), idx);
+        }
+    }
+
+    fn remove_routee(&mut self) {
+        let idx = self.routees.len() - 1;
+        self.routees.pop();
+        for node in 0..VIRTUAL_NODES {
+            self.ring.remove(&hash(&(idx, node)));
+        }
+    }
+
+    /// Replaces stopped routees and grows the pool under pressure.
+    fn maintain(&mut self) {
+        for idx in 0..self.routees.len() {
+            if !self.routees[idx].connected() {
+                self.routees[idx] = self.start_routee();
+            }
+        }
+
+        if let Some(resizer) = self.resizer {
+            if self.routees.len() < resizer.upper
+                && self.routees.iter().all(|addr| addr.len() > 0)
+            {
+                self.add_routee();
+            }
+        }
+    }
+
+    fn shrink(&mut self) {
+        let lower = match self.resizer {
+            Some(resizer) => resizer.lower,
+            None => return,
+        };
+        let idle = self.routees.iter().filter(|addr| addr.len() == 0).count();
+        let last_idle = self.routees.last().map(Addr::len) == Some(0);
+        if self.routees.len() > lower && idle > 1 && last_idle {
+            self.remove_routee();
+        }
+    }
+
+    fn select<M: 'static>(&mut self, msg: &M) -> usize {
+        if let Some(key) = self
+            .keys
+            .get(&TypeId::of::<M>())
+            .and_then(|key| key.downcast_ref::<KeyFn<M>>())
+        {
+            let key = key(msg);
+            return *self
+                .ring
+                .range(key..)
+                .chain(self.ring.iter())
+                .next()
+                .expect("ring contains all routees")
+                .1;
+        }
+
+        match self.routing {
+            Routing::RoundRobin => {
+                let idx = self.next % self.routees.len();
+                self.next = self.next.wrapping_add(1);
+                idx
+            }
+            Routing::Random => {
+                // xorshift64*
+                self.seed ^= self.seed >> 12;
+                self.seed ^= self.seed << 25;
+                self.seed ^= self.seed >> 27;
+                let rnd = self.seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+                (rnd % self.routees.len() as u64) as usize
+            }
+            Routing::SmallestMailbox => self
+                .routees
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, addr)| addr.len())
+                .map(|(idx, _)| idx)
+                .expect("router has routees"),
+        }
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<A: Actor<Context = Context<A>>> Actor for Router<A> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.terminated = Some(tx);
+        let terminated =
+            stream::unfold(rx, |mut rx| async { rx.recv().await.map(|id| (id, rx)) });
+        ctx.spawn(
+            Box::pin(terminated)
+                .into_actor(self)
+                .map(|id, act, _| act.replace_routee(id))
+                .finish(),
+        );
+
+        let size = match self.resizer {
+            Some(resizer) => self.size.max(resizer.lower).min(resizer.upper),
+            None => self.size,
+        };
+        for _ in 0..size {
+            self.add_routee();
+        }
+
+        if let Some(resizer) = self.resizer {
+            ctx.run_interval(resizer.interval, |act, _| act.shrink());
+        }
+    }
+}
+
+impl<A, M> Handler<M> for Router<A>
+where
+    A: Actor<Context = Context<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Result = Routed<A, M>;
+
+    fn handle(&mut self, msg: M, _: &mut Context<Self>) -> Routed<A, M> {
+        self.maintain();
+
+        if let Some(f) = self
+            .broadcasts
+            .get(&TypeId::of::<M>())
+            .and_then(|f| f.downcast_ref::<BroadcastFn<A, M>>())
+        {
+            return Routed(Route::Done(f(&msg, &self.routees)));
+        }
+
+        let idx = self.select(&msg);
+        Routed(Route::Forward(self.routees[idx].clone(), msg))
+    }
+}
+
+/// Response of the router, the message is forwarded to a routee once
+/// the router knows whether the sender waits for a response.
+pub struct Routed<A: Actor, M: Message>(Route<A, M>);
+
+enum Route<A: Actor, M: Message> {
+    Forward(Addr<A>, M),
+    Done(M::Result),
+}
+
+impl<A: Actor, M: Message> fmt::Debug for Routed<A, M> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Route::Forward(ref addr, _) => {
+                fmt.debug_tuple("Forward").field(&addr.id()).finish()
+            }
+            Route::Done(_) => fmt.debug_tuple("Done").finish(),
+        }
+    }
+}
+
+impl<A, M> MessageResponse<Router<A>, M> for Routed<A, M>
+where
+    A: Actor<Context = Context<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    fn handle<R: ResponseChannel<M>>(self, _: &mut Context<Router<A>>, tx: Option<R>) {
+        match (self.0, tx) {
+            (Route::Forward(addr, msg), Some(tx)) => {
+                let req = addr.send(msg);
+                actix_rt::spawn(async move {
+                    match req.await {
+                        Ok(res) => tx.send(res),
+                        Err(err) => tx.fail(err),
+                    }
+                })
+            }
+            (Route::Forward(addr, msg), None) => addr.do_send(msg),
+            (Route::Done(res), Some(tx)) => tx.send(res),
+            (Route::Done(_), None) => (),
+        }
+    }
+}
@@ -1,23 +1,28 @@
 //! This is copy of [sync/mpsc/](https://github.com/alexcrichton/futures-rs)
 use std::hash::{Hash, Hasher};
+use std::iter;
 use std::pin::Pin;
 use std::sync::atomic::Ordering::{Relaxed, SeqCst};
-use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize};
 use std::sync::{Arc, Weak};
 use std::task::Poll;
+use std::thread;
 use std::{fmt, task};
-use std::{thread, usize};
 
-use futures_channel::oneshot::{channel as sync_channel, Receiver};
+use futures_channel::oneshot::Receiver;
 use futures_util::stream::Stream;
+use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 
-use crate::actor::Actor;
-use crate::handler::{Handler, Message};
+use crate::actor::{Actor, StopReason};
+use crate::handler::{Handler, Message, Priority};
+use crate::mailbox::OverflowPolicy;
+use crate::metrics;
 
-use super::envelope::{Envelope, ToEnvelope};
+use super::envelope::{pack_request, Envelope, ResponseReceiver, ToEnvelope};
 use super::queue::{PopResult, Queue};
-use super::SendError;
+use super::watch::{ActorId, StopProxy, Terminated, WatchHandle, Watcher, Watchers};
+use super::{MailboxError, SendError};
 
 pub trait Sender<M>: Send
 where
@@ -28,13 +33,21 @@ where
 
     fn try_send(&self, msg: M) -> Result<(), SendError<M>>;
 
-    fn send(&self, msg: M) -> Result<Receiver<M::Result>, SendError<M>>;
+    fn send(&self, msg: M) -> Result<ResponseReceiver<M>, SendError<M>>;
 
     fn boxed(&self) -> Box<dyn Sender<M>>;
 
     fn hash(&self) -> usize;
 
     fn connected(&self) -> bool;
+
+    fn id(&self) -> ActorId;
+
+    fn watch(&self, watcher: Watcher) -> WatchHandle;
+
+    fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send>;
+
+    fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send>;
 }
 
 /// The transmission end of a channel which is used to send values.
@@ -76,6 +89,7 @@ impl<A: Actor> fmt::Debug for WeakAddressSender<A> {
     }
 }
 
+#[allow(dead_code)]
 trait AssertKinds: Send + Sync + Clone {}
 
 /// The receiving end of a channel which implements the `Stream` trait.
@@ -100,9 +114,29 @@ struct Inner<A: Actor> {
     // channel as well as a flag signalling that the channel is closed.
     state: AtomicUsize,
 
-    // Atomic, FIFO queue used to send messages to the receiver.
+    // Atomic, FIFO queue used to send messages with `Normal` priority to
+    // the receiver.
     message_queue: Queue<Envelope<A>>,
 
+    // Queues of messages with `Low` and `High` priority, created once such
+    // message is sent.
+    priority_queues: OnceCell<[Queue<Envelope<A>>; 2]>,
+
+    // Queues are single consumer, senders pop messages with `DropOldest`
+    // overflow policy, so every pop has to hold this lock once the policy
+    // is used.
+    pop_lock: Mutex<()>,
+
+    // Behavior of the channel once it is full, `OverflowPolicy` as `u8`.
+    overflow: AtomicU8,
+
+    // Set once `DropOldest` policy is used, stays set as senders could
+    // still be evicting messages after the policy changes.
+    evicting: AtomicBool,
+
+    // Number of messages dropped or rejected because the channel was full.
+    dropped: AtomicUsize,
+
     // Atomic, FIFO queue used to send parked task handles to the receiver.
     parked_queue: Queue<Arc<Mutex<SenderTask>>>,
 
@@ -111,6 +145,15 @@ struct Inner<A: Actor> {
 
     // Handle to the receiver's task.
     recv_task: Mutex<ReceiverTask>,
+
+    // Unique identifier of the receiving actor.
+    id: ActorId,
+
+    // Callbacks to call once receiver is dropped.
+    watchers: Arc<Watchers>,
+
+    // The reason why the receiving actor stopped.
+    stop_reason: Mutex<Option<StopReason>>,
 }
 
 // Struct representation of `Inner::state`.
@@ -123,6 +166,16 @@ struct State {
     num_messages: usize,
 }
 
+// Returned from AddressSender::reserve()
+enum Slot {
+    // Message can be queued, sender has to park if `true`
+    Queued(bool),
+    // Channel is full, message has to be dropped
+    Full,
+    // Channel is closed
+    Closed,
+}
+
 #[derive(Debug)]
 struct ReceiverTask {
     unparked: bool,
@@ -136,7 +189,7 @@ enum TryPark {
 }
 
 // The `is_open` flag is stored in the left-most bit of `Inner::state`
-const OPEN_MASK: usize = usize::MAX - (usize::MAX >> 1);
+const OPEN_MASK: usize = !(!0 >> 1);
 
 // When a new channel is created, it is created in the open state with no
 // pending messages.
@@ -198,12 +251,20 @@ pub fn channel<A: Actor>(buffer: usize) -> (AddressSender<A>, AddressReceiver<A>
         buffer: AtomicUsize::new(buffer),
         state: AtomicUsize::new(INIT_STATE),
         message_queue: Queue::new(),
+        priority_queues: OnceCell::new(),
+        pop_lock: Mutex::new(()),
+        overflow: AtomicU8::new(OverflowPolicy::Backpressure as u8),
+        evicting: AtomicBool::new(false),
+        dropped: AtomicUsize::new(0),
         parked_queue: Queue::new(),
         num_senders: AtomicUsize::new(1),
         recv_task: Mutex::new(ReceiverTask {
             unparked: false,
             task: None,
         }),
+        id: ActorId::next(),
+        watchers: Watchers::new(),
+        stop_reason: Mutex::new(None),
     });
 
     let tx = AddressSender {
@@ -231,10 +292,47 @@ impl<A: Actor> AddressSender<A> {
         state.is_open
     }
 
+    /// Returns the number of messages in the channel.
+    pub(crate) fn len(&self) -> usize {
+        decode_state(self.inner.state.load(SeqCst)).num_messages
+    }
+
     /// Attempts to send a message on this `Sender<A>` with blocking.
     ///
     /// This function must be called from inside of a task.
     pub fn send<M>(&self, msg: M) -> Result<Receiver<M::Result>, SendError<M>>
+    where
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+        M::Result: Send,
+        M: Message + Send,
+    {
+        self.send_with_priority(msg, M::PRIORITY)
+    }
+
+    /// Same as `send`, but overrides priority of the message.
+    pub fn send_with_priority<M>(
+        &self,
+        msg: M,
+        priority: Priority,
+    ) -> Result<Receiver<M::Result>, SendError<M>>
+    where
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+        M::Result: Send,
+        M: Message + Send,
+    {
+        self.request(msg, priority)
+            .map(ResponseReceiver::into_receiver)
+    }
+
+    /// Same as `send_with_priority`, but the response reports why the
+    /// message is not handled.
+    pub(crate) fn request<M>(
+        &self,
+        msg: M,
+        priority: Priority,
+    ) -> Result<ResponseReceiver<M>, SendError<M>>
     where
         A: Handler<M>,
         A::Context: ToEnvelope<A, M>,
@@ -250,12 +348,17 @@ impl<A: Actor> AddressSender<A> {
         // This operation will also atomically determine if the sender task
         // should be parked.
         //
-        // None is returned in the case that the channel has been closed by the
+        // Closed is returned in the case that the channel has been closed by the
         // receiver. This happens when `Receiver::close` is called or the
         // receiver is dropped.
-        let park_self = match self.inc_num_messages() {
-            Some(park_self) => park_self,
-            None => return Err(SendError::Closed(msg)),
+        let park_self = match self.reserve() {
+            Slot::Queued(park_self) => park_self,
+            Slot::Full => {
+                // fail request right away
+                self.inner.dropped.fetch_add(1, Relaxed);
+                return Ok(ResponseReceiver::Failed(MailboxError::Full));
+            }
+            Slot::Closed => return Err(SendError::Closed(msg)),
         };
 
         // If the channel has reached capacity, then the sender task needs to
@@ -263,9 +366,8 @@ impl<A: Actor> AddressSender<A> {
         if park_self {
             self.park();
         }
-        let (tx, rx) = sync_channel();
-        let env = <A::Context as ToEnvelope<A, M>>::pack(msg, Some(tx));
-        self.queue_push_and_signal(env);
+        let (env, rx) = pack_request(msg);
+        self.queue_push_and_signal(env, priority);
         Ok(rx)
     }
 
@@ -282,16 +384,17 @@ impl<A: Actor> AddressSender<A> {
             return Err(SendError::Full(msg));
         }
 
-        let park_self = match self.inc_num_messages() {
-            Some(park_self) => park_self,
-            None => return Err(SendError::Closed(msg)),
+        let park_self = match self.reserve() {
+            Slot::Queued(park_self) => park_self,
+            Slot::Full => return self.overflow(msg),
+            Slot::Closed => return Err(SendError::Closed(msg)),
         };
 
         if park_self && park {
             self.park();
         }
         let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
-        self.queue_push_and_signal(env);
+        self.queue_push_and_signal(env, M::PRIORITY);
         Ok(())
     }
 
@@ -305,15 +408,17 @@ impl<A: Actor> AddressSender<A> {
         M::Result: Send,
         M: Message + Send,
     {
-        if self.inc_num_messages().is_none() {
-            Err(SendError::Closed(msg))
-        } else {
-            // If inc_num_messages returned Some(park_self), then the mailbox is still active.
-            // We ignore the boolean (indicating to park and wait) in the Some, and queue the
+        match self.reserve() {
+            Slot::Closed => Err(SendError::Closed(msg)),
+            Slot::Full => self.overflow(msg),
+            // If reserve returned Queued(park_self), then the mailbox is still active.
+            // We ignore the boolean (indicating to park and wait), and queue the
             // message regardless.
-            let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
-            self.queue_push_and_signal(env);
-            Ok(())
+            Slot::Queued(_) => {
+                let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
+                self.queue_push_and_signal(env, M::PRIORITY);
+                Ok(())
+            }
         }
     }
 
@@ -324,16 +429,122 @@ impl<A: Actor> AddressSender<A> {
         }
     }
 
+    /// Returns identifier of the receiving actor.
+    pub fn id(&self) -> ActorId {
+        self.inner.id
+    }
+
+    /// Register callback which is called once receiver is dropped.
+    ///
+    /// Callback is called immediately if receiver is already dropped.
+    pub(crate) fn watch(&self, watcher: Watcher) -> WatchHandle {
+        match Watchers::add(&self.inner.watchers, watcher) {
+            Ok(handle) => handle,
+            Err(watcher) => {
+                watcher.notify(self.inner.terminated());
+                WatchHandle::empty()
+            }
+        }
+    }
+
+    /// Returns function which checks if receiver is still open, does not
+    /// prevent channel from being disconnected.
+    pub(crate) fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send> {
+        let inner = Arc::downgrade(&self.inner);
+        Box::new(move || {
+            inner.upgrade().map_or(false, |inner| {
+                decode_state(inner.state.load(SeqCst)).is_open
+            })
+        })
+    }
+
+    /// Returns function which queues stop envelope, does not prevent
+    /// channel from being disconnected.
+    pub(crate) fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send> {
+        let tx = self.downgrade();
+        Box::new(move |reason| {
+            if let Some(tx) = tx.upgrade() {
+                if tx.inc_num_messages().is_some() {
+                    tx.queue_push_and_signal(
+                        Envelope::with_proxy(Box::new(StopProxy::new(reason))),
+                        Priority::High,
+                    );
+                }
+            }
+        })
+    }
+
     // Push message to the queue and signal to the receiver
-    fn queue_push_and_signal(&self, msg: Envelope<A>) {
+    fn queue_push_and_signal(&self, msg: Envelope<A>, priority: Priority) {
         // Push the message onto the message queue
-        self.inner.message_queue.push(msg);
+        self.inner.queue(priority).push(msg);
 
         // Signal to the receiver that a message has been enqueued. If the
         // receiver is parked, this will unpark the task.
         self.signal();
     }
 
+    // Reserve space for a new message according to the overflow policy.
+    fn reserve(&self) -> Slot {
+        match self.inner.overflow_policy() {
+            OverflowPolicy::Backpressure => match self.inc_num_messages() {
+                Some(park_self) => Slot::Queued(park_self),
+                None => Slot::Closed,
+            },
+            OverflowPolicy::DropOldest => self.inc_num_messages_bounded(true),
+            OverflowPolicy::DropNewest | OverflowPolicy::Reject => {
+                self.inc_num_messages_bounded(false)
+            }
+        }
+    }
+
+    // Count message which did not fit into the channel.
+    fn overflow<M>(&self, msg: M) -> Result<(), SendError<M>> {
+        self.inner.dropped.fetch_add(1, Relaxed);
+        match self.inner.overflow_policy() {
+            OverflowPolicy::Reject => Err(SendError::Full(msg)),
+            _ => Ok(()),
+        }
+    }
+
+    // Increment the number of queued messages unless the channel is full.
+    // If `evict` is set, oldest message is dropped to make space instead.
+    fn inc_num_messages_bounded(&self, evict: bool) -> Slot {
+        let mut curr = self.inner.state.load(SeqCst);
+        loop {
+            let mut state = decode_state(curr);
+            if !state.is_open {
+                return Slot::Closed;
+            }
+
+            let buffer = self.inner.buffer.load(Relaxed);
+            if buffer != 0 && state.num_messages >= buffer {
+                if !evict {
+                    return Slot::Full;
+                }
+                // If there is nothing to evict, the receiver just took a
+                // message or another sender has not pushed its message yet.
+                // Both are about to update the state, check it again.
+                if !self.inner.evict_oldest() {
+                    thread::yield_now();
+                }
+                curr = self.inner.state.load(SeqCst);
+                continue;
+            }
+            state.num_messages += 1;
+
+            let next = encode_state(&state);
+            match self
+                .inner
+                .state
+                .compare_exchange(curr, next, SeqCst, SeqCst)
+            {
+                Ok(_) => return Slot::Queued(false),
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+
     // Increment the number of queued messages. Returns if the sender should
     // block.
     fn inc_num_messages(&self) -> Option<bool> {
@@ -454,8 +665,8 @@ where
     fn try_send(&self, msg: M) -> Result<(), SendError<M>> {
         self.try_send(msg, true)
     }
-    fn send(&self, msg: M) -> Result<Receiver<M::Result>, SendError<M>> {
-        self.send(msg)
+    fn send(&self, msg: M) -> Result<ResponseReceiver<M>, SendError<M>> {
+        self.request(msg, M::PRIORITY)
     }
     fn boxed(&self) -> Box<dyn Sender<M>> {
         Box::new(self.clone())
@@ -469,6 +680,22 @@ where
     fn connected(&self) -> bool {
         self.connected()
     }
+
+    fn id(&self) -> ActorId {
+        self.id()
+    }
+
+    fn watch(&self, watcher: Watcher) -> WatchHandle {
+        self.watch(watcher)
+    }
+
+    fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send> {
+        self.stop_handle()
+    }
+
+    fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send> {
+        self.alive_handle()
+    }
 }
 
 impl<A: Actor> Clone for AddressSender<A> {
@@ -487,7 +714,11 @@ impl<A: Actor> Clone for AddressSender<A> {
             debug_assert!(curr < self.inner.max_senders());
 
             let next = curr + 1;
-            let actual = self.inner.num_senders.compare_and_swap(curr, next, SeqCst);
+            let actual = self
+                .inner
+                .num_senders
+                .compare_exchange(curr, next, SeqCst, SeqCst)
+                .unwrap_or_else(|actual| actual);
 
             // The ABA problem doesn't matter here. We only care that the
             // number of senders never exceeds the maximum.
@@ -540,10 +771,7 @@ impl<A: Actor> WeakAddressSender<A> {
     ///
     /// Returns [`None`] if the actor has since been dropped.
     pub fn upgrade(&self) -> Option<AddressSender<A>> {
-        match Weak::upgrade(&self.inner) {
-            Some(inner) => Some(AddressSenderProducer { inner }.sender()),
-            None => None,
-        }
+        Weak::upgrade(&self.inner).map(|inner| AddressSenderProducer { inner }.sender())
     }
 }
 
@@ -552,7 +780,25 @@ impl<A: Actor> WeakAddressSender<A> {
 // ===== impl SenderProducer =====
 //
 //
+impl<A: Actor> Clone for AddressSenderProducer<A> {
+    fn clone(&self) -> Self {
+        AddressSenderProducer {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
 impl<A: Actor> AddressSenderProducer<A> {
+    /// Returns identifier of the receiving actor.
+    pub fn id(&self) -> ActorId {
+        self.inner.id
+    }
+
+    /// Returns the number of messages in the channel.
+    pub(crate) fn len(&self) -> usize {
+        decode_state(self.inner.state.load(SeqCst)).num_messages
+    }
+
     /// Are any senders connected
     pub fn connected(&self) -> bool {
         self.inner.num_senders.load(SeqCst) != 0
@@ -573,24 +819,25 @@ impl<A: Actor> AddressSenderProducer<A> {
 
         // wake up all
         if cap > buffer {
-            loop {
-                match unsafe { self.inner.parked_queue.pop() } {
-                    PopResult::Data(task) => {
-                        task.lock().notify();
-                    }
-                    PopResult::Empty => {
-                        // Queue empty, no task to wake up.
-                        return;
-                    }
-                    PopResult::Inconsistent => {
-                        // Same as above
-                        thread::yield_now();
-                    }
-                }
-            }
+            self.inner.unpark_all();
         }
     }
 
+    /// Get channel overflow policy
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.inner.overflow_policy()
+    }
+
+    /// Set channel overflow policy
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.inner.set_overflow_policy(policy)
+    }
+
+    /// Number of messages dropped or rejected because channel was full
+    pub fn dropped(&self) -> usize {
+        self.inner.dropped.load(Relaxed)
+    }
+
     /// Get sender side of the channel
     pub fn sender(&self) -> AddressSender<A> {
         // this code same as Sender::clone
@@ -603,7 +850,11 @@ impl<A: Actor> AddressSenderProducer<A> {
             }
 
             let next = curr + 1;
-            let actual = self.inner.num_senders.compare_and_swap(curr, next, SeqCst);
+            let actual = self
+                .inner
+                .num_senders
+                .compare_exchange(curr, next, SeqCst, SeqCst)
+                .unwrap_or_else(|actual| actual);
 
             // The ABA problem doesn't matter here. We only care that the
             // number of senders never exceeds the maximum.
@@ -626,11 +877,22 @@ impl<A: Actor> AddressSenderProducer<A> {
 //
 //
 impl<A: Actor> AddressReceiver<A> {
+    /// Returns identifier of the receiving actor.
+    pub fn id(&self) -> ActorId {
+        self.inner.id
+    }
+
     /// Returns whether any senders are still connected.
     pub fn connected(&self) -> bool {
         self.inner.num_senders.load(SeqCst) != 0
     }
 
+    /// Records the reason why the receiving actor stopped, it gets
+    /// reported to watchers once receiver is dropped.
+    pub fn set_stop_reason(&mut self, reason: StopReason) {
+        *self.inner.stop_reason.lock() = Some(reason);
+    }
+
     /// Returns the channel capacity.
     pub fn capacity(&self) -> usize {
         self.inner.buffer.load(Relaxed)
@@ -646,24 +908,36 @@ impl<A: Actor> AddressReceiver<A> {
 
         // wake up all
         if cap > buffer {
-            loop {
-                match unsafe { self.inner.parked_queue.pop() } {
-                    PopResult::Data(task) => {
-                        task.lock().notify();
-                    }
-                    PopResult::Empty => {
-                        // Queue empty, no task to wake up.
-                        return;
-                    }
-                    PopResult::Inconsistent => {
-                        // Same as above
-                        thread::yield_now();
-                    }
-                }
-            }
+            self.inner.unpark_all();
         }
     }
 
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        decode_state(self.inner.state.load(SeqCst)).num_messages
+    }
+
+    /// Returns `true` if there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the channel overflow policy.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.inner.overflow_policy()
+    }
+
+    /// Sets the channel overflow policy.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.inner.set_overflow_policy(policy)
+    }
+
+    /// Returns the number of messages dropped or rejected because the
+    /// channel was full.
+    pub fn dropped(&self) -> usize {
+        self.inner.dropped.load(Relaxed)
+    }
+
     /// Returns the sender side of the channel.
     pub fn sender(&self) -> AddressSender<A> {
         // this code same as Sender::clone
@@ -676,7 +950,11 @@ impl<A: Actor> AddressReceiver<A> {
             }
 
             let next = curr + 1;
-            let actual = self.inner.num_senders.compare_and_swap(curr, next, SeqCst);
+            let actual = self
+                .inner
+                .num_senders
+                .compare_exchange(curr, next, SeqCst, SeqCst)
+                .unwrap_or_else(|actual| actual);
 
             // The ABA problem doesn't matter here. We only care that the
             // number of senders never exceeds the maximum.
@@ -699,33 +977,57 @@ impl<A: Actor> AddressReceiver<A> {
         }
     }
 
-    fn next_message(&mut self) -> Poll<Option<Envelope<A>>> {
-        // Pop off a message
+    /// Closes the channel, senders get `SendError::Closed` while queued
+    /// messages can still be received.
+    pub(crate) fn close(&mut self) {
+        let mut curr = self.inner.state.load(SeqCst);
         loop {
-            match unsafe { self.inner.message_queue.pop() } {
-                PopResult::Data(msg) => {
-                    return Poll::Ready(Some(msg));
-                }
-                PopResult::Empty => {
-                    // The queue is empty, return NotReady
-                    return Poll::Pending;
-                }
-                PopResult::Inconsistent => {
-                    // Inconsistent means that there will be a message to pop
-                    // in a short time. This branch can only be reached if
-                    // values are being produced from another thread, so there
-                    // are a few ways that we can deal with this:
-                    //
-                    // 1) Spin
-                    // 2) thread::yield_now()
-                    // 3) task::current().unwrap() & return NotReady
-                    //
-                    // For now, thread::yield_now() is used, but it would
-                    // probably be better to spin a few times then yield.
-                    thread::yield_now();
+            let mut state = decode_state(curr);
+            if !state.is_open {
+                break;
+            }
+            state.is_open = false;
+
+            let next = encode_state(&state);
+            match self
+                .inner
+                .state
+                .compare_exchange(curr, next, SeqCst, SeqCst)
+            {
+                Ok(_) => break,
+                Err(actual) => curr = actual,
+            }
+        }
+
+        // Wake up any threads waiting as they'll see that we've closed the
+        // channel and will continue on their merry way.
+        loop {
+            match unsafe { self.inner.parked_queue.pop() } {
+                PopResult::Data(task) => {
+                    task.lock().notify();
                 }
+                PopResult::Empty => break,
+                PopResult::Inconsistent => thread::yield_now(),
+            }
+        }
+    }
+
+    fn next_message(&mut self) -> Poll<Option<Envelope<A>>> {
+        // policy is only changed by the receiver's side
+        let _guard = if self.inner.evicting.load(Relaxed) {
+            Some(self.inner.pop_lock.lock())
+        } else {
+            None
+        };
+
+        // Pop off a message, highest priority first
+        for queue in self.inner.queues().rev() {
+            if let Poll::Ready(msg) = pop_message(queue) {
+                return Poll::Ready(msg);
             }
         }
+        // All queues are empty, return NotReady
+        Poll::Pending
     }
 
     // Unpark a single task handle if there is one pending in the parked queue
@@ -763,26 +1065,6 @@ impl<A: Actor> AddressReceiver<A> {
         recv_task.task = Some(cx.waker().clone());
         TryPark::Parked
     }
-
-    fn dec_num_messages(&self) {
-        let mut curr = self.inner.state.load(SeqCst);
-
-        loop {
-            let mut state = decode_state(curr);
-
-            state.num_messages -= 1;
-
-            let next = encode_state(&state);
-            match self
-                .inner
-                .state
-                .compare_exchange(curr, next, SeqCst, SeqCst)
-            {
-                Ok(_) => break,
-                Err(actual) => curr = actual,
-            }
-        }
-    }
 }
 
 impl<A: Actor> Stream for AddressReceiver<A> {
@@ -822,7 +1104,7 @@ impl<A: Actor> Stream for AddressReceiver<A> {
             this.unpark_one();
 
             // Decrement number of messages
-            this.dec_num_messages();
+            this.inner.dec_num_messages();
 
             // Return the message
             return Poll::Ready(msg);
@@ -832,41 +1114,21 @@ impl<A: Actor> Stream for AddressReceiver<A> {
 
 impl<A: Actor> Drop for AddressReceiver<A> {
     fn drop(&mut self) {
-        // close
-        let mut curr = self.inner.state.load(SeqCst);
-        loop {
-            let mut state = decode_state(curr);
-            if !state.is_open {
-                break;
-            }
-            state.is_open = false;
+        self.close();
 
-            let next = encode_state(&state);
-            match self
-                .inner
-                .state
-                .compare_exchange(curr, next, SeqCst, SeqCst)
-            {
-                Ok(_) => break,
-                Err(actual) => curr = actual,
-            }
+        // Drain the channel of all pending messages
+        while self.next_message().is_ready() {
+            // ...
         }
 
-        // Wake up any threads waiting as they'll see that we've closed the
-        // channel and will continue on their merry way.
-        loop {
-            match unsafe { self.inner.parked_queue.pop() } {
-                PopResult::Data(task) => {
-                    task.lock().notify();
-                }
-                PopResult::Empty => break,
-                PopResult::Inconsistent => thread::yield_now(),
-            }
+        // Notify watchers
+        for watcher in self.inner.watchers.take() {
+            watcher.notify(self.inner.terminated())
         }
 
-        // Drain the channel of all pending messages
-        while self.next_message().is_ready() {
-            // ...
+        if metrics::enabled() {
+            let actor = std::any::type_name::<A>();
+            metrics::report(|sink| sink.stopped(self.inner.id, actor));
         }
     }
 }
@@ -882,6 +1144,146 @@ impl<A: Actor> Inner<A> {
     fn max_senders(&self) -> usize {
         MAX_CAPACITY - self.buffer.load(Relaxed)
     }
+
+    fn overflow_policy(&self) -> OverflowPolicy {
+        match self.overflow.load(Relaxed) {
+            1 => OverflowPolicy::DropOldest,
+            2 => OverflowPolicy::DropNewest,
+            3 => OverflowPolicy::Reject,
+            _ => OverflowPolicy::Backpressure,
+        }
+    }
+
+    fn set_overflow_policy(&self, policy: OverflowPolicy) {
+        if policy == OverflowPolicy::DropOldest {
+            self.evicting.store(true, Relaxed);
+        }
+        self.overflow.store(policy as u8, Relaxed);
+
+        // parked senders are not woken up by other policies
+        if policy != OverflowPolicy::Backpressure {
+            self.unpark_all();
+        }
+    }
+
+    fn queue(&self, priority: Priority) -> &Queue<Envelope<A>> {
+        if priority == Priority::Normal {
+            return &self.message_queue;
+        }
+        let queues = self
+            .priority_queues
+            .get_or_init(|| [Queue::new(), Queue::new()]);
+        match priority {
+            Priority::High => &queues[1],
+            _ => &queues[0],
+        }
+    }
+
+    // Message queues in ascending order of priority.
+    fn queues(&self) -> impl DoubleEndedIterator<Item = &Queue<Envelope<A>>> {
+        let (low, high) = match self.priority_queues.get() {
+            Some([low, high]) => (Some(low), Some(high)),
+            None => (None, None),
+        };
+        low.into_iter()
+            .chain(iter::once(&self.message_queue))
+            .chain(high)
+    }
+
+    // Drop the oldest message with the lowest priority. Returns `false`
+    // if channel is empty.
+    fn evict_oldest(&self) -> bool {
+        let msg = {
+            let _guard = self.pop_lock.lock();
+            self.queues().find_map(|queue| match pop_message(queue) {
+                Poll::Ready(msg) => msg,
+                Poll::Pending => None,
+            })
+        };
+
+        match msg {
+            Some(_) => {
+                self.dec_num_messages();
+                self.dropped.fetch_add(1, Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn unpark_all(&self) {
+        loop {
+            match unsafe { self.parked_queue.pop() } {
+                PopResult::Data(task) => {
+                    task.lock().notify();
+                }
+                PopResult::Empty => {
+                    // Queue empty, no task to wake up.
+                    return;
+                }
+                PopResult::Inconsistent => {
+                    // Same as above
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+
+    fn dec_num_messages(&self) {
+        let mut curr = self.state.load(SeqCst);
+
+        loop {
+            let mut state = decode_state(curr);
+
+            state.num_messages -= 1;
+
+            let next = encode_state(&state);
+            match self.state.compare_exchange(curr, next, SeqCst, SeqCst) {
+                Ok(_) => break,
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+
+    fn terminated(&self) -> Terminated {
+        Terminated {
+            id: self.id,
+            reason: self
+                .stop_reason
+                .lock()
+                .clone()
+                .unwrap_or(StopReason::Normal),
+        }
+    }
+}
+
+// Pop a message off of the queue, caller has to hold `Inner::pop_lock`.
+fn pop_message<A: Actor>(queue: &Queue<Envelope<A>>) -> Poll<Option<Envelope<A>>> {
+    loop {
+        match unsafe { queue.pop() } {
+            PopResult::Data(msg) => {
+                return Poll::Ready(Some(msg));
+            }
+            PopResult::Empty => {
+                // The queue is empty, return NotReady
+                return Poll::Pending;
+            }
+            PopResult::Inconsistent => {
+                // Inconsistent means that there will be a message to pop
+                // in a short time. This branch can only be reached if
+                // values are being produced from another thread, so there
+                // are a few ways that we can deal with this:
+                //
+                // 1) Spin
+                // 2) thread::yield_now()
+                // 3) task::current().unwrap() & return NotReady
+                //
+                // For now, thread::yield_now() is used, but it would
+                // probably be better to spin a few times then yield.
+                thread::yield_now();
+            }
+        }
+    }
 }
 
 unsafe impl<A: Actor> Send for Inner<A> {}
@@ -913,6 +1315,7 @@ fn encode_state(state: &State) -> usize {
 mod tests {
     use super::*;
     use crate::prelude::*;
+    use std::sync::mpsc;
     use std::{thread, time};
 
     struct Act;
@@ -981,4 +1384,34 @@ mod tests {
         })
         .unwrap();
     }
+
+    #[test]
+    fn test_drop_oldest_cap() {
+        let (s1, mut recv) = channel::<Act>(1);
+        recv.set_overflow_policy(OverflowPolicy::DropOldest);
+        let s2 = recv.sender();
+
+        // space is reserved, message is not pushed yet
+        match s1.reserve() {
+            Slot::Queued(_) => (),
+            _ => panic!("space should be reserved"),
+        }
+
+        // other sender either waits for the reserved message to be pushed
+        // or evicts it right away, outcome is the same
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            tx.send(()).unwrap();
+            s2.do_send(Ping).is_ok()
+        });
+        rx.recv().unwrap();
+        let env = <Context<Act> as ToEnvelope<Act, Ping>>::pack(Ping, None);
+        s1.queue_push_and_signal(env, Priority::Normal);
+        assert!(handle.join().unwrap());
+
+        // pushed message is evicted by the other sender
+        let state = decode_state(recv.inner.state.load(SeqCst));
+        assert_eq!(state.num_messages, 1);
+        assert_eq!(recv.dropped(), 1);
+    }
 }
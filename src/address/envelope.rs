@@ -1,10 +1,22 @@
+use std::any::{type_name, Any};
+use std::future::Future;
 use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::task::{self, Poll};
 
-use futures_channel::oneshot::Sender;
+use futures_channel::oneshot::{self, Receiver, Sender};
 
-use crate::actor::{Actor, AsyncContext};
+use crate::actor::{Actor, ActorContext, AsyncContext, StopReason};
+use crate::clock::{self, Instant};
 use crate::context::Context;
-use crate::handler::{Handler, Message, MessageResponse};
+use crate::contextimpl::AsyncContextParts;
+use crate::handler::{Handler, Message, MessageResponse, ResponseChannel};
+use crate::metrics;
+
+use super::MailboxError;
 
 /// Converter trait, packs message into a suitable envelope.
 pub trait ToEnvelope<A, M: Message>
@@ -16,6 +28,146 @@ where
     fn pack(msg: M, tx: Option<Sender<M::Result>>) -> Envelope<A>;
 }
 
+/// Pack message along with response channel which reports failure of the
+/// request.
+pub(crate) fn pack_request<A, M>(msg: M) -> (Envelope<A>, ResponseReceiver<M>)
+where
+    A: Handler<M>,
+    A::Context: ToEnvelope<A, M>,
+    M: Message,
+{
+    let (tx, rx) = oneshot::channel();
+    let failure = Arc::new(Failure::default());
+    let mut env = <A::Context as ToEnvelope<A, M>>::pack(msg, Some(tx));
+    env.set_failure(Arc::clone(&failure));
+    let rx = ResponseReceiver::Channel {
+        rx,
+        failure: Some(failure),
+    };
+    (env, rx)
+}
+
+/// Error of the request, set before its response channel is dropped.
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct Failure(AtomicU8);
+
+impl Failure {
+    fn set(&self, err: MailboxError) {
+        let code = match err {
+            MailboxError::Closed => 1,
+            MailboxError::Timeout => 2,
+            MailboxError::Panicked => 3,
+            MailboxError::Full => 4,
+        };
+        self.0.store(code, Ordering::SeqCst);
+    }
+
+    fn get(&self) -> Option<MailboxError> {
+        match self.0.load(Ordering::SeqCst) {
+            1 => Some(MailboxError::Closed),
+            2 => Some(MailboxError::Timeout),
+            3 => Some(MailboxError::Panicked),
+            4 => Some(MailboxError::Full),
+            _ => None,
+        }
+    }
+}
+
+/// Receiver half of the message response channel.
+pub enum ResponseReceiver<M: Message> {
+    Channel {
+        rx: Receiver<M::Result>,
+        /// Set before the response channel is dropped by failed request.
+        failure: Option<Arc<Failure>>,
+    },
+    /// Message is not delivered.
+    Failed(MailboxError),
+}
+
+impl<M: Message> ResponseReceiver<M> {
+    /// Receiver of the plain response channel, its errors are reported as
+    /// dropped channel.
+    pub(crate) fn into_receiver(self) -> Receiver<M::Result> {
+        match self {
+            ResponseReceiver::Channel { rx, .. } => rx,
+            ResponseReceiver::Failed(_) => oneshot::channel().1,
+        }
+    }
+}
+
+impl<M: Message> From<Receiver<M::Result>> for ResponseReceiver<M> {
+    fn from(rx: Receiver<M::Result>) -> Self {
+        ResponseReceiver::Channel { rx, failure: None }
+    }
+}
+
+impl<M: Message> Future for ResponseReceiver<M> {
+    type Output = Result<M::Result, MailboxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        match self.get_mut() {
+            ResponseReceiver::Channel { rx, failure } => match Pin::new(rx).poll(cx) {
+                Poll::Ready(Ok(res)) => Poll::Ready(Ok(res)),
+                Poll::Ready(Err(_)) => Poll::Ready(Err(failure
+                    .as_ref()
+                    .and_then(|failure| failure.get())
+                    .unwrap_or(MailboxError::Closed))),
+                Poll::Pending => Poll::Pending,
+            },
+            ResponseReceiver::Failed(err) => Poll::Ready(Err(*err)),
+        }
+    }
+}
+
+/// Response channel passed to the message handler, reports failure of the
+/// request.
+pub(crate) struct Responder<M: Message> {
+    tx: Sender<M::Result>,
+    failure: Option<Arc<Failure>>,
+}
+
+impl<M: Message> Responder<M> {
+    pub(crate) fn new(
+        tx: Option<Sender<M::Result>>,
+        failure: Option<Arc<Failure>>,
+    ) -> Option<Self> {
+        tx.map(|tx| Responder { tx, failure })
+    }
+
+    /// Pack message which takes over the response channel.
+    pub(crate) fn pack<A>(self, msg: M) -> Envelope<A>
+    where
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+    {
+        let mut env = <A::Context as ToEnvelope<A, M>>::pack(msg, Some(self.tx));
+        if let Some(failure) = self.failure {
+            env.set_failure(failure);
+        }
+        env
+    }
+}
+
+impl<M: Message + 'static> ResponseChannel<M> for Responder<M>
+where
+    M::Result: Send,
+{
+    fn is_canceled(&self) -> bool {
+        self.tx.is_canceled()
+    }
+
+    fn send(self, response: M::Result) {
+        let _ = self.tx.send(response);
+    }
+
+    fn fail(self, err: MailboxError) {
+        if let Some(failure) = self.failure {
+            failure.set(err);
+        }
+    }
+}
+
 pub trait EnvelopeProxy {
     type Actor: Actor;
 
@@ -25,6 +177,21 @@ pub trait EnvelopeProxy {
         act: &mut Self::Actor,
         ctx: &mut <Self::Actor as Actor>::Context,
     );
+
+    /// Type name of the message, used for metrics
+    fn message_type(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// The message, if it is not handled yet
+    fn message(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Set error which is reported if the request fails, before the
+    /// response channel is dropped.
+    #[doc(hidden)]
+    fn set_failure(&mut self, _: Arc<Failure>) {}
 }
 
 impl<A, M> ToEnvelope<A, M> for Context<A>
@@ -34,11 +201,22 @@ where
     M::Result: Send,
 {
     fn pack(msg: M, tx: Option<Sender<M::Result>>) -> Envelope<A> {
-        Envelope::new(msg, tx)
+        Envelope::with_proxy(Box::new(ContextEnvelopeProxy {
+            tx,
+            failure: None,
+            msg: Some(msg),
+            act: PhantomData,
+        }))
     }
 }
 
-pub struct Envelope<A: Actor>(Box<dyn EnvelopeProxy<Actor = A> + Send>);
+pub struct Envelope<A: Actor> {
+    proxy: Box<dyn EnvelopeProxy<Actor = A> + Send>,
+    sent: Option<Instant>,
+    /// Span of the sender, parent of the handler's span
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
 
 impl<A: Actor> Envelope<A> {
     pub fn new<M>(msg: M, tx: Option<Sender<M::Result>>) -> Self
@@ -48,15 +226,33 @@ impl<A: Actor> Envelope<A> {
         M: Message + Send + 'static,
         M::Result: Send,
     {
-        Envelope(Box::new(SyncEnvelopeProxy {
+        Envelope::with_proxy(Box::new(SyncEnvelopeProxy {
             tx,
+            failure: None,
             msg: Some(msg),
             act: PhantomData,
         }))
     }
 
     pub fn with_proxy(proxy: Box<dyn EnvelopeProxy<Actor = A> + Send>) -> Self {
-        Envelope(proxy)
+        let sent = if metrics::enabled() {
+            Some(clock::now())
+        } else {
+            None
+        };
+        Envelope {
+            proxy,
+            sent,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        }
+    }
+
+    /// Time passed since the message was sent, known only if metrics
+    /// were enabled at that time.
+    pub(crate) fn elapsed(&self) -> Option<std::time::Duration> {
+        self.sent
+            .map(|sent| clock::now().saturating_duration_since(sent))
     }
 }
 
@@ -68,7 +264,29 @@ impl<A: Actor> EnvelopeProxy for Envelope<A> {
         act: &mut Self::Actor,
         ctx: &mut <Self::Actor as Actor>::Context,
     ) {
-        self.0.handle(act, ctx)
+        #[cfg(feature = "tracing")]
+        let _enter = tracing::debug_span!(
+            parent: &self.span,
+            "handle",
+            actor = type_name::<A>(),
+            message = self.proxy.message_type(),
+            handle = tracing::field::Empty,
+        )
+        .entered();
+
+        self.proxy.handle(act, ctx)
+    }
+
+    fn message_type(&self) -> &'static str {
+        self.proxy.message_type()
+    }
+
+    fn message(&self) -> Option<&dyn Any> {
+        self.proxy.message()
+    }
+
+    fn set_failure(&mut self, failure: Arc<Failure>) {
+        self.proxy.set_failure(failure)
     }
 }
 
@@ -80,6 +298,7 @@ where
     act: PhantomData<A>,
     msg: Option<M>,
     tx: Option<Sender<M::Result>>,
+    failure: Option<Arc<Failure>>,
 }
 
 unsafe impl<A, M> Send for SyncEnvelopeProxy<A, M>
@@ -103,14 +322,152 @@ where
         act: &mut Self::Actor,
         ctx: &mut <Self::Actor as Actor>::Context,
     ) {
-        let tx = self.tx.take();
-        if tx.is_some() && tx.as_ref().unwrap().is_canceled() {
-            return;
-        }
+        let tx = Responder::new(self.tx.take(), self.failure.take());
+        handle_request(act, ctx, &mut self.msg, tx, &mut ())
+    }
+
+    fn message_type(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn set_failure(&mut self, failure: Arc<Failure>) {
+        self.failure = Some(failure);
+    }
+}
+
+/// Envelope of `Context` actor, passes response channel to the message
+/// stashed by the handler.
+pub(crate) struct ContextEnvelopeProxy<A, M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    act: PhantomData<A>,
+    msg: Option<M>,
+    tx: Option<Sender<M::Result>>,
+    failure: Option<Arc<Failure>>,
+}
+
+unsafe impl<A, M> Send for ContextEnvelopeProxy<A, M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+}
+
+impl<A, M> EnvelopeProxy for ContextEnvelopeProxy<A, M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+    A: Actor<Context = Context<A>> + Handler<M>,
+{
+    type Actor = A;
+
+    fn handle(&mut self, act: &mut A, ctx: &mut Context<A>) {
+        let tx = Responder::new(self.tx.take(), self.failure.take());
+        handle_request(act, ctx, &mut self.msg, tx, &mut StashHooks)
+    }
+
+    fn message_type(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn message(&self) -> Option<&dyn Any> {
+        let msg: &M = self.msg.as_ref()?;
+        Some(msg)
+    }
+
+    fn set_failure(&mut self, failure: Arc<Failure>) {
+        self.failure = Some(failure);
+    }
+}
+
+/// Context hooks called around the message handler by `handle_request`.
+pub(crate) trait HandlerHooks<C> {
+    /// Handler is about to be called.
+    fn before(&mut self, _: &mut C) {}
+
+    /// Handler returned, `tx` is the response channel of the request or
+    /// `()` if the handler panicked.
+    fn after(&mut self, _: &mut C, _tx: &mut dyn Any) {}
+}
+
+impl<C> HandlerHooks<C> for () {}
+
+/// Hooks of `Context`, a message stashed by the handler takes over the
+/// response channel.
+struct StashHooks;
 
-        if let Some(msg) = self.msg.take() {
-            let fut = <Self::Actor as Handler<M>>::handle(act, msg, ctx);
-            fut.handle(ctx, tx)
+impl<A> HandlerHooks<Context<A>> for StashHooks
+where
+    A: Actor<Context = Context<A>>,
+{
+    fn before(&mut self, ctx: &mut Context<A>) {
+        ctx.parts().set_handling(true);
+    }
+
+    fn after(&mut self, ctx: &mut Context<A>, tx: &mut dyn Any) {
+        ctx.parts().set_handling(false);
+        ctx.parts().stash_response(tx);
+    }
+}
+
+/// Handle message of the envelope and respond to the request.
+///
+/// Canceled request is dropped without calling the handler. Panic of the
+/// handler fails the request with `MailboxError::Panicked` and terminates
+/// the actor.
+pub(crate) fn handle_request<A, M, H>(
+    act: &mut A,
+    ctx: &mut A::Context,
+    msg: &mut Option<M>,
+    tx: Option<Responder<M>>,
+    hooks: &mut H,
+) where
+    A: Actor + Handler<M>,
+    A::Context: ActorContext,
+    M: Message + 'static,
+    M::Result: Send,
+    H: HandlerHooks<A::Context>,
+{
+    if tx.as_ref().map_or(false, |tx| tx.is_canceled()) {
+        return;
+    }
+
+    if let Some(msg) = msg.take() {
+        hooks.before(ctx);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            <A as Handler<M>>::handle(act, msg, ctx)
+        }));
+        match res {
+            Ok(fut) => {
+                let mut tx = tx;
+                hooks.after(ctx, &mut tx);
+                fut.handle(ctx, tx)
+            }
+            Err(err) => {
+                // message stashed by panicked handler has no response channel
+                hooks.after(ctx, &mut ());
+                handler_panicked::<M, _, _>(ctx, tx, &*err)
+            }
         }
     }
 }
+
+/// Fail pending request and terminate the actor after message handler panic.
+fn handler_panicked<M, C, R>(
+    ctx: &mut C,
+    tx: Option<R>,
+    err: &(dyn std::any::Any + Send),
+) where
+    M: Message,
+    C: ActorContext,
+    R: ResponseChannel<M>,
+{
+    let reason = StopReason::from_panic(err);
+    log::error!("Message handler panicked: {:?}", reason);
+    if let Some(tx) = tx {
+        tx.fail(MailboxError::Panicked);
+    }
+    ctx.terminate_with(reason);
+}
@@ -5,13 +5,13 @@ use std::task::{self, Poll};
 use std::time::Duration;
 
 use futures_channel::oneshot;
-
 use pin_project::pin_project;
 
-use crate::clock::Delay;
-use crate::handler::{Handler, Message};
+use crate::clock::{self, Delay};
+use crate::handler::{Handler, Message, Priority};
 
 use super::channel::{AddressSender, Sender};
+use super::envelope::ResponseReceiver;
 use super::{MailboxError, SendError, ToEnvelope};
 
 /// A `Future` which represents an asynchronous message sending
@@ -24,8 +24,8 @@ where
     A::Context: ToEnvelope<A, M>,
     M: Message,
 {
-    rx: Option<oneshot::Receiver<M::Result>>,
-    info: Option<(AddressSender<A>, M)>,
+    rx: Option<ResponseReceiver<M>>,
+    info: Option<(AddressSender<A>, M, Priority)>,
     timeout: Option<Delay>,
     act: PhantomData<A>,
 }
@@ -37,8 +37,8 @@ where
     M: Message,
 {
     pub(crate) fn new(
-        rx: Option<oneshot::Receiver<M::Result>>,
-        info: Option<(AddressSender<A>, M)>,
+        rx: Option<ResponseReceiver<M>>,
+        info: Option<(AddressSender<A>, M, Priority)>,
     ) -> Request<A, M> {
         Request {
             rx,
@@ -55,7 +55,7 @@ where
 
     /// Set message delivery timeout
     pub fn timeout(mut self, dur: Duration) -> Self {
-        self.timeout = Some(tokio::time::delay_for(dur));
+        self.timeout = Some(clock::delay_for(dur));
         self
     }
 
@@ -86,11 +86,11 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let this = self.as_mut().project();
 
-        if let Some((sender, msg)) = this.info.take() {
-            match sender.send(msg) {
+        if let Some((sender, msg, priority)) = this.info.take() {
+            match sender.request(msg, priority) {
                 Ok(rx) => *this.rx = Some(rx),
                 Err(SendError::Full(msg)) => {
-                    *this.info = Some((sender, msg));
+                    *this.info = Some((sender, msg, priority));
                     return Poll::Pending;
                 }
                 Err(SendError::Closed(_)) => {
@@ -100,9 +100,8 @@ where
         }
 
         if this.rx.is_some() {
-            match Pin::new(&mut this.rx.as_mut().unwrap()).poll(cx) {
-                Poll::Ready(Ok(i)) => Poll::Ready(Ok(i)),
-                Poll::Ready(Err(_)) => Poll::Ready(Err(MailboxError::Closed)),
+            match Pin::new(this.rx.as_mut().unwrap()).poll(cx) {
+                Poll::Ready(res) => Poll::Ready(res),
                 Poll::Pending => self.poll_timeout(cx),
             }
         } else {
@@ -119,7 +118,7 @@ where
     M: Message + Send + 'static,
     M::Result: Send,
 {
-    rx: Option<oneshot::Receiver<M::Result>>,
+    rx: Option<ResponseReceiver<M>>,
     info: Option<(Box<dyn Sender<M>>, M)>,
     timeout: Option<Delay>,
 }
@@ -132,6 +131,13 @@ where
     pub fn new(
         rx: Option<oneshot::Receiver<M::Result>>,
         info: Option<(Box<dyn Sender<M>>, M)>,
+    ) -> RecipientRequest<M> {
+        Self::with_response(rx.map(ResponseReceiver::from), info)
+    }
+
+    pub(crate) fn with_response(
+        rx: Option<ResponseReceiver<M>>,
+        info: Option<(Box<dyn Sender<M>>, M)>,
     ) -> RecipientRequest<M> {
         RecipientRequest {
             rx,
@@ -142,7 +148,7 @@ where
 
     /// Set message delivery timeout
     pub fn timeout(mut self, dur: Duration) -> Self {
-        self.timeout = Some(tokio::time::delay_for(dur));
+        self.timeout = Some(clock::delay_for(dur));
         self
     }
 
@@ -185,9 +191,8 @@ where
         }
 
         if this.rx.is_some() {
-            match Pin::new(&mut this.rx.as_mut().unwrap()).poll(cx) {
-                Poll::Ready(Ok(i)) => Poll::Ready(Ok(i)),
-                Poll::Ready(Err(_)) => Poll::Ready(Err(MailboxError::Closed)),
+            match Pin::new(this.rx.as_mut().unwrap()).poll(cx) {
+                Poll::Ready(res) => Poll::Ready(res),
                 Poll::Pending => self.poll_timeout(cx),
             }
         } else {
@@ -4,15 +4,17 @@ use std::{error, fmt};
 use derive_more::Display;
 
 pub(crate) mod channel;
-mod envelope;
+pub(crate) mod envelope;
 mod message;
 mod queue;
+mod watch;
 
 use crate::actor::Actor;
-use crate::handler::{Handler, Message};
+use crate::handler::{Handler, Message, Priority};
 
 pub use self::envelope::{Envelope, EnvelopeProxy, ToEnvelope};
 pub use self::message::{RecipientRequest, Request};
+pub use self::watch::{ActorId, Terminated, WatchHandle, Watchable};
 
 pub(crate) use self::channel::{AddressReceiver, AddressSenderProducer};
 use self::channel::{AddressSender, Sender, WeakAddressSender};
@@ -29,6 +31,10 @@ pub enum MailboxError {
     Closed,
     #[display(fmt = "Message delivery timed out")]
     Timeout,
+    #[display(fmt = "Message handler panicked")]
+    Panicked,
+    #[display(fmt = "Mailbox is full")]
+    Full,
 }
 
 impl error::Error for MailboxError {}
@@ -84,6 +90,11 @@ impl<A: Actor> Addr<A> {
         self.tx.connected()
     }
 
+    /// Returns the number of messages in the actor's mailbox.
+    pub(crate) fn len(&self) -> usize {
+        self.tx.len()
+    }
+
     #[inline]
     /// Sends a message unconditionally, ignoring any potential errors.
     ///
@@ -126,21 +137,35 @@ impl<A: Actor> Addr<A> {
         A: Handler<M>,
         A::Context: ToEnvelope<A, M>,
     {
-        match self.tx.send(msg) {
+        self.send_with_priority(msg, M::PRIORITY)
+    }
+
+    /// Sends an asynchronous message with specified priority and waits
+    /// for a response.
+    ///
+    /// Same as `send`, but overrides `Message::PRIORITY` for this message.
+    pub fn send_with_priority<M>(&self, msg: M, priority: Priority) -> Request<A, M>
+    where
+        M: Message + Send,
+        M::Result: Send,
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+    {
+        match self.tx.request(msg, priority) {
             Ok(rx) => Request::new(Some(rx), None),
             Err(SendError::Full(msg)) => {
-                Request::new(None, Some((self.tx.clone(), msg)))
+                Request::new(None, Some((self.tx.clone(), msg, priority)))
             }
             Err(SendError::Closed(_)) => Request::new(None, None),
         }
     }
 
     /// Returns the `Recipient` for a specific message type.
-    pub fn recipient<M: 'static>(self) -> Recipient<M>
+    pub fn recipient<M>(self) -> Recipient<M>
     where
         A: Handler<M>,
         A::Context: ToEnvelope<A, M>,
-        M: Message + Send,
+        M: Message + Send + 'static,
         M::Result: Send,
     {
         self.into()
@@ -248,11 +273,11 @@ where
     /// cancelled.
     pub fn send(&self, msg: M) -> RecipientRequest<M> {
         match self.tx.send(msg) {
-            Ok(rx) => RecipientRequest::new(Some(rx), None),
+            Ok(rx) => RecipientRequest::with_response(Some(rx), None),
             Err(SendError::Full(msg)) => {
-                RecipientRequest::new(None, Some((self.tx.boxed(), msg)))
+                RecipientRequest::with_response(None, Some((self.tx.boxed(), msg)))
             }
-            Err(SendError::Closed(_)) => RecipientRequest::new(None, None),
+            Err(SendError::Closed(_)) => RecipientRequest::with_response(None, None),
         }
     }
 
@@ -261,14 +286,14 @@ where
     }
 }
 
-impl<A: Actor, M: Message + Send + 'static> Into<Recipient<M>> for Addr<A>
+impl<A: Actor, M: Message + Send + 'static> From<Addr<A>> for Recipient<M>
 where
     A: Handler<M>,
     M::Result: Send,
     A::Context: ToEnvelope<A, M>,
 {
-    fn into(self) -> Recipient<M> {
-        Recipient::new(Box::new(self.tx))
+    fn from(addr: Addr<A>) -> Self {
+        Recipient::new(Box::new(addr.tx))
     }
 }
 
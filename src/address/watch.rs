@@ -0,0 +1,281 @@
+use std::cmp;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+
+use crate::actor::{Actor, ActorContext, StopReason};
+use crate::handler::Message;
+
+use super::envelope::EnvelopeProxy;
+use super::{Addr, Recipient};
+
+/// Unique identifier of an actor.
+///
+/// Identifier is assigned once actor's mailbox is created and stays the
+/// same across supervisor restarts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActorId(usize);
+
+impl ActorId {
+    pub(crate) fn next() -> ActorId {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ActorId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub(crate) fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for ActorId {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "actor#{}", self.0)
+    }
+}
+
+/// Notification which is delivered to watching actor once watched
+/// actor stops.
+///
+/// See `AsyncContext::watch()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terminated {
+    /// Identifier of the stopped actor.
+    pub id: ActorId,
+    /// The reason why the actor stopped.
+    pub reason: StopReason,
+}
+
+impl Message for Terminated {
+    type Result = ();
+}
+
+/// Callback registered with `Watchable::on_terminated()`.
+pub struct Watcher {
+    notify: Box<dyn FnOnce(Terminated) + Send>,
+    alive: Box<dyn Fn() -> bool + Send>,
+}
+
+impl Watcher {
+    pub(crate) fn new(
+        notify: Box<dyn FnOnce(Terminated) + Send>,
+        alive: Box<dyn Fn() -> bool + Send>,
+    ) -> Self {
+        Watcher { notify, alive }
+    }
+
+    pub(crate) fn notify(self, msg: Terminated) {
+        (self.notify)(msg)
+    }
+}
+
+/// Number of watchers which triggers first pruning of dead watchers.
+const PRUNE_THRESHOLD: usize = 8;
+
+struct Entries {
+    next_key: usize,
+    /// Watchers get pruned once there are this many of them.
+    prune_at: usize,
+    watchers: Vec<(usize, Watcher)>,
+}
+
+/// Watchers of an actor.
+pub(crate) struct Watchers {
+    /// `None` once the actor is stopped.
+    entries: Mutex<Option<Entries>>,
+}
+
+impl Watchers {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Watchers {
+            entries: Mutex::new(Some(Entries {
+                next_key: 0,
+                prune_at: PRUNE_THRESHOLD,
+                watchers: Vec::new(),
+            })),
+        })
+    }
+
+    /// Registers watcher, returns watcher back if the actor is stopped.
+    ///
+    /// Watchers which are not alive anymore are dropped once number of
+    /// watchers doubles, so dead watchers never outnumber live ones.
+    pub(crate) fn add(
+        this: &Arc<Self>,
+        watcher: Watcher,
+    ) -> Result<WatchHandle, Watcher> {
+        let mut entries = this.entries.lock();
+        let entries = match *entries {
+            Some(ref mut entries) => entries,
+            None => return Err(watcher),
+        };
+
+        if entries.watchers.len() >= entries.prune_at {
+            entries.watchers.retain(|(_, watcher)| (watcher.alive)());
+            entries.prune_at = cmp::max(entries.watchers.len() * 2, PRUNE_THRESHOLD);
+        }
+
+        let key = entries.next_key;
+        entries.next_key = entries.next_key.wrapping_add(1);
+        entries.watchers.push((key, watcher));
+
+        Ok(WatchHandle {
+            watchers: Arc::downgrade(this),
+            key,
+        })
+    }
+
+    /// Takes all watchers, watchers added later are notified immediately.
+    pub(crate) fn take(&self) -> Vec<Watcher> {
+        self.entries
+            .lock()
+            .take()
+            .map(|entries| entries.watchers.into_iter().map(|(_, w)| w).collect())
+            .unwrap_or_default()
+    }
+
+    fn remove(&self, key: usize) {
+        if let Some(ref mut entries) = *self.entries.lock() {
+            entries.watchers.retain(|(k, _)| *k != key);
+        }
+    }
+}
+
+/// Handle of a callback registered with `Watchable::on_terminated()`.
+///
+/// Dropping the handle does not remove the callback, use
+/// [`unwatch()`](#method.unwatch) for that.
+pub struct WatchHandle {
+    watchers: Weak<Watchers>,
+    key: usize,
+}
+
+impl WatchHandle {
+    /// Handle of a callback which is not registered.
+    pub(crate) fn empty() -> Self {
+        WatchHandle {
+            watchers: Weak::new(),
+            key: 0,
+        }
+    }
+
+    /// Removes the callback, so it never gets called.
+    ///
+    /// Does nothing if the watched actor is stopped already.
+    pub fn unwatch(self) {
+        if let Some(watchers) = self.watchers.upgrade() {
+            watchers.remove(self.key);
+        }
+    }
+}
+
+impl fmt::Debug for WatchHandle {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("WatchHandle")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// Actor handle which can be watched or linked with
+/// `AsyncContext::watch()` and `AsyncContext::link()`.
+pub trait Watchable {
+    /// Returns identifier of the actor.
+    fn id(&self) -> ActorId;
+
+    /// Registers callback which gets called once actor stops.
+    ///
+    /// If actor is already stopped callback gets called immediately.
+    /// Callback gets dropped without being called once `alive` returns
+    /// `false`, it is checked while other callbacks are registered.
+    fn on_terminated(
+        &self,
+        f: Box<dyn FnOnce(Terminated) + Send>,
+        alive: Box<dyn Fn() -> bool + Send>,
+    ) -> WatchHandle;
+
+    /// Returns function which asks actor to stop with the given reason.
+    ///
+    /// Returned function does not keep actor alive.
+    fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send>;
+
+    /// Returns function which checks if actor is still running.
+    ///
+    /// Returned function does not keep actor alive.
+    fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send>;
+}
+
+impl<A: Actor> Watchable for Addr<A> {
+    fn id(&self) -> ActorId {
+        self.tx.id()
+    }
+
+    fn on_terminated(
+        &self,
+        f: Box<dyn FnOnce(Terminated) + Send>,
+        alive: Box<dyn Fn() -> bool + Send>,
+    ) -> WatchHandle {
+        self.tx.watch(Watcher::new(f, alive))
+    }
+
+    fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send> {
+        self.tx.stop_handle()
+    }
+
+    fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send> {
+        self.tx.alive_handle()
+    }
+}
+
+impl<M> Watchable for Recipient<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    fn id(&self) -> ActorId {
+        self.tx.id()
+    }
+
+    fn on_terminated(
+        &self,
+        f: Box<dyn FnOnce(Terminated) + Send>,
+        alive: Box<dyn Fn() -> bool + Send>,
+    ) -> WatchHandle {
+        self.tx.watch(Watcher::new(f, alive))
+    }
+
+    fn stop_handle(&self) -> Box<dyn Fn(StopReason) + Send> {
+        self.tx.stop_handle()
+    }
+
+    fn alive_handle(&self) -> Box<dyn Fn() -> bool + Send> {
+        self.tx.alive_handle()
+    }
+}
+
+/// Envelope which stops receiving actor, used by actor links.
+pub(crate) struct StopProxy<A> {
+    reason: Option<StopReason>,
+    act: PhantomData<fn(A)>,
+}
+
+impl<A> StopProxy<A> {
+    pub(crate) fn new(reason: StopReason) -> Self {
+        StopProxy {
+            reason: Some(reason),
+            act: PhantomData,
+        }
+    }
+}
+
+impl<A: Actor> EnvelopeProxy for StopProxy<A> {
+    type Actor = A;
+
+    fn handle(&mut self, _: &mut A, ctx: &mut A::Context) {
+        if let Some(reason) = self.reason.take() {
+            ctx.stop_with(reason)
+        }
+    }
+}
@@ -1,13 +1,201 @@
-//! A configurable source of time.
+//! Time of actors
 //!
-//! This module provides an API to get the current instant in such a way that
-//! the source of time may be configured. This allows mocking out the source of
-//! time in tests.
+//! Timers of actors with `Context` run on the clock of their arbiter:
+//! `AsyncContext::run_later()`, `AsyncContext::run_interval()`,
+//! `AsyncContext::notify_later()`, `TimerFunc`, `IntervalFunc`,
+//! `Request::timeout()`, `ActorFuture::timeout()` and the backoff of
+//! `Supervisor`. The types of this module are the types of the `tokio`
+//! clock.
 //!
-//! See [Module `tokio_timer::clock`] for full documentation.
+//! Time outside of arbiters is read with [`now()`](fn.now.html), the clock of
+//! the current `System`. Sync actors use it for `SyncContext` timers, idle
+//! and shutdown timeouts of `SyncArbiter`, latency and handling time reported
+//! to `metrics` are measured with it as well.
 //!
-//! [Module `tokio_timer::clock`]: https://docs.rs/tokio-timer/latest/tokio_timer/clock/index.html
+//! With the `test-util` feature enabled, [`pause()`](fn.pause.html) stops the
+//! clock of the current `System` and of the current arbiter, they are moved
+//! forward together with [`advance()`](fn.advance.html). While time is paused
+//! and the arbiter has nothing to do, the arbiter's clock jumps to the next
+//! pending timer, so timeouts elapse immediately instead of waiting for wall
+//! time. The clock of the `System` only moves with `advance()`, sync actors
+//! see their timers elapse once the clock is advanced past them. Other
+//! arbiters of the system keep their own clocks.
+//!
+//! ```rust
+//! # #[cfg(feature = "test-util")]
+//! # fn main() {
+//! use actix::prelude::*;
+//! use actix::clock::{self, Duration, Instant};
+//!
+//! System::run(|| {
+//!     clock::pause();
+//!     let start = Instant::now();
+//!
+//!     actix::spawn(async move {
+//!         clock::advance(Duration::from_secs(60)).await;
+//!         assert!(Instant::now() - start >= Duration::from_secs(60));
+//!
+//!         // elapses without waiting
+//!         clock::delay_for(Duration::from_secs(3600)).await;
+//!         System::current().stop();
+//!     });
+//! })
+//! .unwrap();
+//! # }
+//! # #[cfg(not(feature = "test-util"))]
+//! # fn main() {}
+//! ```
+//!
+//! See [Module `tokio::time`] for full documentation.
+//!
+//! [Module `tokio::time`]: https://docs.rs/tokio/0.2/tokio/time/index.html
 
 pub use tokio::time::{
     delay_for, delay_until, interval_at, Delay, Duration, Instant, Interval,
 };
+
+/// Returns the current time of the current `System`.
+///
+/// It is the system time unless the clock got paused with `pause()`, or
+/// advanced while it was paused.
+pub fn now() -> Instant {
+    #[cfg(feature = "test-util")]
+    {
+        if let Some(now) = control::now() {
+            return now;
+        }
+    }
+    Instant::from_std(std::time::Instant::now())
+}
+
+/// Real time to wait for the `deadline` of the clock.
+///
+/// Waiting is limited while the clock is paused, so that the deadline is
+/// checked again once the clock is advanced.
+pub(crate) fn wait_time(deadline: Instant) -> Duration {
+    let timeout = deadline.saturating_duration_since(now());
+    #[cfg(feature = "test-util")]
+    {
+        if control::paused() {
+            return timeout.min(control::PAUSED_WAIT);
+        }
+    }
+    timeout
+}
+
+/// Pauses the clock of the current `System` and of the current arbiter.
+///
+/// # Panics
+///
+/// Panics if the clock of the arbiter is already paused or if called from
+/// outside of an arbiter.
+#[cfg(feature = "test-util")]
+pub fn pause() {
+    tokio::time::pause();
+    control::update(|state| state.pause());
+}
+
+/// Resumes the clock of the current `System` and of the current arbiter.
+///
+/// # Panics
+///
+/// Panics if the clock of the arbiter is not paused or if called from
+/// outside of an arbiter.
+#[cfg(feature = "test-util")]
+pub fn resume() {
+    tokio::time::resume();
+    control::update(|state| state.resume());
+}
+
+/// Advances the paused clock of the current `System` and of the current
+/// arbiter.
+///
+/// # Panics
+///
+/// Panics if the clock of the arbiter is not paused or if called from
+/// outside of an arbiter.
+#[cfg(feature = "test-util")]
+pub async fn advance(duration: Duration) {
+    control::update(|state| state.base += duration);
+    tokio::time::advance(duration).await
+}
+
+/// Clocks of systems which got paused.
+#[cfg(feature = "test-util")]
+mod control {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use actix_rt::System;
+    use once_cell::sync::Lazy;
+    use parking_lot::Mutex;
+
+    use super::{Duration, Instant};
+
+    /// Longest real time to wait for a deadline while the clock is paused.
+    pub(super) const PAUSED_WAIT: Duration = Duration::from_millis(5);
+
+    pub(super) struct State {
+        /// Time of the clock at `since`.
+        pub(super) base: Instant,
+        since: std::time::Instant,
+        paused: bool,
+    }
+
+    impl State {
+        fn now(&self) -> Instant {
+            if self.paused {
+                self.base
+            } else {
+                self.base + self.since.elapsed()
+            }
+        }
+
+        pub(super) fn pause(&mut self) {
+            self.base = self.now();
+            self.paused = true;
+        }
+
+        pub(super) fn resume(&mut self) {
+            self.base = self.now();
+            self.since = std::time::Instant::now();
+            self.paused = false;
+        }
+    }
+
+    /// Set once a clock got paused, other clocks follow the system time.
+    static USED: AtomicBool = AtomicBool::new(false);
+    static CLOCKS: Lazy<Mutex<HashMap<usize, State>>> =
+        Lazy::new(|| Mutex::new(HashMap::new()));
+
+    fn with<F: FnOnce(&State) -> R, R>(f: F) -> Option<R> {
+        if !USED.load(Ordering::Acquire) || !System::is_set() {
+            return None;
+        }
+        CLOCKS.lock().get(&System::current().id()).map(f)
+    }
+
+    pub(super) fn now() -> Option<Instant> {
+        with(State::now)
+    }
+
+    pub(super) fn paused() -> bool {
+        with(|state| state.paused).unwrap_or(false)
+    }
+
+    pub(super) fn update<F: FnOnce(&mut State)>(f: F) {
+        USED.store(true, Ordering::Release);
+        let id = System::current().id();
+        let mut clocks = CLOCKS.lock();
+        let state = clocks.entry(id).or_insert_with(|| {
+            // clock which was never paused follows the system time
+            let since = std::time::Instant::now();
+            State {
+                base: Instant::from_std(since),
+                since,
+                paused: false,
+            }
+        });
+        f(state)
+    }
+}
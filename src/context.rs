@@ -1,10 +1,14 @@
 use std::fmt;
+use std::time::Duration;
 
-use crate::actor::{Actor, ActorContext, ActorState, AsyncContext, SpawnHandle};
+use crate::actor::{
+    Actor, ActorContext, ActorState, AsyncContext, SpawnHandle, StopReason,
+};
 use crate::address::{Addr, AddressReceiver};
 use crate::contextimpl::{AsyncContextParts, ContextFut, ContextParts};
 use crate::fut::ActorFuture;
-use crate::mailbox::Mailbox;
+use crate::handler::{Handler, Message};
+use crate::mailbox::{Mailbox, OverflowPolicy};
 
 /// An actor execution context.
 pub struct Context<A>
@@ -40,6 +44,18 @@ where
     fn state(&self) -> ActorState {
         self.parts.state()
     }
+    #[inline]
+    fn stop_with(&mut self, reason: StopReason) {
+        self.parts.stop_with(reason)
+    }
+    #[inline]
+    fn terminate_with(&mut self, reason: StopReason) {
+        self.parts.terminate_with(reason)
+    }
+    #[inline]
+    fn stop_reason(&self) -> Option<&StopReason> {
+        self.parts.stop_reason()
+    }
 }
 
 impl<A> AsyncContext<A> for Context<A>
@@ -145,10 +161,143 @@ where
         self.parts.set_mailbox_capacity(cap)
     }
 
+    /// Sets behavior of the mailbox once it is full.
+    ///
+    /// The default policy is `OverflowPolicy::Backpressure`.
+    /// #Examples
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Telemetry;
+    /// impl Actor for Telemetry {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.set_mailbox_capacity(1024);
+    ///         ctx.set_mailbox_overflow(OverflowPolicy::DropOldest);
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// # System::new("test");
+    /// let addr = Telemetry.start();
+    /// # }
+    /// ```
+    pub fn set_mailbox_overflow(&mut self, policy: OverflowPolicy) {
+        self.parts.set_mailbox_overflow(policy)
+    }
+
+    /// Returns the number of messages dropped or rejected because the
+    /// mailbox was full.
+    pub fn dropped_messages(&self) -> usize {
+        self.parts.dropped_messages()
+    }
+
+    /// Sets time limit of the future returned by `Actor::stopping_async()`.
+    ///
+    /// The default deadline is 5 seconds. The future is dropped once the
+    /// deadline elapses and the actor gets stopped.
+    pub fn set_stopping_deadline(&mut self, deadline: Duration) {
+        self.parts.set_stopping_deadline(deadline)
+    }
+
     /// Returns whether any addresses are still connected.
     pub fn connected(&self) -> bool {
         self.parts.connected()
     }
+
+    /// Stashes the message which is being handled.
+    ///
+    /// Must be called from the handler of `msg`. The message is handled
+    /// again after `unstash_all()` is called and its response is sent to
+    /// the sender then, value returned by the current handler is dropped.
+    /// Stashed messages are dropped once the actor stops, including stop
+    /// followed by a restart of supervised actor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of message handler, e.g. from a future
+    /// spawned in actor's context.
+    ///
+    /// #Examples
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Query;
+    /// impl Message for Query {
+    ///     type Result = usize;
+    /// }
+    ///
+    /// struct Ready;
+    /// impl Message for Ready {
+    ///     type Result = ();
+    /// }
+    ///
+    /// #[derive(Default)]
+    /// struct Cache {
+    ///     ready: bool,
+    /// }
+    /// impl Actor for Cache {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// impl Handler<Query> for Cache {
+    ///     type Result = usize;
+    ///
+    ///     fn handle(&mut self, msg: Query, ctx: &mut Context<Self>) -> usize {
+    ///         if !self.ready {
+    ///             ctx.stash(msg);
+    ///         }
+    ///         0
+    ///     }
+    /// }
+    ///
+    /// impl Handler<Ready> for Cache {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, _: Ready, ctx: &mut Context<Self>) {
+    ///         self.ready = true;
+    ///         ctx.unstash_all();
+    ///     }
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn stash<M>(&mut self, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.parts.stash(msg)
+    }
+
+    /// Re-enqueues all stashed messages.
+    ///
+    /// Messages are handled in the order they were stashed, ahead of
+    /// messages in the mailbox.
+    pub fn unstash_all(&mut self) {
+        self.parts.unstash_all()
+    }
+
+    /// Returns the number of stashed messages.
+    pub fn stashed(&self) -> usize {
+        self.parts.stashed()
+    }
+
+    pub(crate) fn postpone<M>(&mut self, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.parts.postpone(msg)
+    }
+
+    pub(crate) fn unpostpone_all(&mut self) {
+        self.parts.unpostpone_all()
+    }
+
+    pub(crate) fn postponed(&self) -> usize {
+        self.parts.postponed()
+    }
 }
 
 impl<A> AsyncContextParts<A> for Context<A>
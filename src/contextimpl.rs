@@ -1,18 +1,32 @@
+use std::any::{type_name, Any};
+use std::collections::VecDeque;
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
 
 use bitflags::bitflags;
 use futures_util::future::Future;
+use log::{error, warn};
 use smallvec::SmallVec;
 
 use crate::actor::{
-    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, StopReason,
+    Supervised,
 };
-use crate::address::{Addr, AddressSenderProducer};
+use crate::address::envelope::Responder;
+use crate::address::{Addr, AddressSenderProducer, Envelope, ToEnvelope};
+use crate::clock::{delay_for, Delay};
+#[cfg(feature = "tracing")]
+use crate::contextitems::ActorInstrumented;
 use crate::contextitems::ActorWaitItem;
 use crate::fut::ActorFuture;
-use crate::mailbox::Mailbox;
+use crate::handler::{Handler, Message, ResponseActFuture};
+use crate::introspection::Tracked;
+use crate::mailbox::{Mailbox, OverflowPolicy};
+use crate::metrics;
 
 bitflags! {
     /// internal context state
@@ -30,6 +44,12 @@ type Item<A> = (
     Pin<Box<dyn ActorFuture<Output = (), Actor = A>>>,
 );
 
+/// Message stashed by running handler, waits for the response channel.
+type Stashing<A> = Box<dyn FnOnce(&mut dyn Any) -> Envelope<A>>;
+
+/// Default time limit of `Actor::stopping_async()` future
+const DEFAULT_STOPPING_DEADLINE: Duration = Duration::from_secs(5);
+
 pub trait AsyncContextParts<A>: ActorContext + AsyncContext<A>
 where
     A: Actor<Context = Self>,
@@ -47,6 +67,17 @@ where
     wait: SmallVec<[ActorWaitItem<A>; 2]>,
     items: SmallVec<[Item<A>; 3]>,
     handles: SmallVec<[SpawnHandle; 2]>,
+    reason: Option<StopReason>,
+    stash: VecDeque<Envelope<A>>,
+    /// Messages postponed by `StateMachine::postpone()`, kept apart from
+    /// the stash.
+    postponed: VecDeque<Envelope<A>>,
+    unstashed: VecDeque<Envelope<A>>,
+    stashing: Option<Stashing<A>>,
+    postponing: bool,
+    /// Set while message handler runs.
+    handling: bool,
+    stopping_deadline: Duration,
 }
 
 impl<A> fmt::Debug for ContextParts<A>
@@ -57,6 +88,8 @@ where
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("ContextParts")
             .field("flags", &self.flags)
+            .field("reason", &self.reason)
+            .field("stashed", &self.stash.len())
             .finish()
     }
 }
@@ -78,6 +111,14 @@ where
                 SpawnHandle::default(),
                 SpawnHandle::default(),
             ]),
+            reason: None,
+            stash: VecDeque::new(),
+            postponed: VecDeque::new(),
+            unstashed: VecDeque::new(),
+            stashing: None,
+            postponing: false,
+            handling: false,
+            stopping_deadline: DEFAULT_STOPPING_DEADLINE,
         }
     }
 
@@ -87,16 +128,36 @@ where
     /// Actor could prevent stopping by returning `false` from
     /// `Actor::stopping()` method.
     pub fn stop(&mut self) {
+        self.stop_with(StopReason::Normal)
+    }
+
+    #[inline]
+    /// Initiate stop process for actor execution and record the reason
+    pub fn stop_with(&mut self, reason: StopReason) {
         if self.flags.contains(ContextFlags::RUNNING) {
             self.flags.remove(ContextFlags::RUNNING);
             self.flags.insert(ContextFlags::STOPPING);
+            self.reason.get_or_insert(reason);
         }
     }
 
     #[inline]
     /// Terminate actor execution
     pub fn terminate(&mut self) {
-        self.flags = ContextFlags::STOPPED;
+        self.terminate_with(StopReason::Normal)
+    }
+
+    #[inline]
+    /// Terminate actor execution and record the reason
+    pub fn terminate_with(&mut self, reason: StopReason) {
+        self.flags = ContextFlags::STOPPED | (self.flags & ContextFlags::STARTED);
+        self.reason.get_or_insert(reason);
+    }
+
+    #[inline]
+    /// The reason why actor is stopping
+    pub fn stop_reason(&self) -> Option<&StopReason> {
+        self.reason.as_ref()
     }
 
     #[inline]
@@ -117,6 +178,7 @@ where
     /// Is context waiting for future completion
     pub fn waiting(&self) -> bool {
         !self.wait.is_empty()
+            || !self.unstashed.is_empty()
             || self
                 .flags
                 .intersects(ContextFlags::STOPPING | ContextFlags::STOPPED)
@@ -136,6 +198,11 @@ where
     {
         let handle = self.handles[0].next();
         self.handles[0] = handle;
+        #[cfg(feature = "tracing")]
+        let fut = {
+            tracing::Span::current().record("handle", tracing::field::debug(handle));
+            ActorInstrumented::new(fut)
+        };
         let fut: Box<dyn ActorFuture<Output = (), Actor = A>> = Box::new(fut);
         self.items.push((handle, Pin::from(fut)));
         handle
@@ -149,6 +216,8 @@ where
     where
         F: ActorFuture<Output = (), Actor = A> + 'static,
     {
+        #[cfg(feature = "tracing")]
+        let f = ActorInstrumented::new(f);
         self.wait.push(ActorWaitItem::new(f));
     }
 
@@ -170,18 +239,136 @@ where
         self.addr.set_capacity(cap);
     }
 
+    #[inline]
+    pub fn set_mailbox_overflow(&mut self, policy: OverflowPolicy) {
+        self.addr.set_overflow_policy(policy);
+    }
+
+    #[inline]
+    pub fn dropped_messages(&self) -> usize {
+        self.addr.dropped()
+    }
+
+    #[inline]
+    pub fn set_stopping_deadline(&mut self, deadline: Duration) {
+        self.stopping_deadline = deadline;
+    }
+
     #[inline]
     pub fn address(&self) -> Addr<A> {
         Addr::new(self.addr.sender())
     }
 
+    /// Stash message which is being handled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of message handler.
+    pub fn stash<M>(&mut self, msg: M)
+    where
+        A: Actor<Context = crate::Context<A>> + Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        assert!(
+            self.handling,
+            "Context::stash() must be called from message handler"
+        );
+        self.set_stashing(msg, false);
+    }
+
+    /// Postpone message which is being handled, see `stash()`.
+    pub(crate) fn postpone<M>(&mut self, msg: M)
+    where
+        A: Actor<Context = crate::Context<A>> + Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        assert!(
+            self.handling,
+            "StateMachine::postpone() must be called from message handler"
+        );
+        self.set_stashing(msg, true);
+    }
+
+    fn set_stashing<M>(&mut self, msg: M, postponing: bool)
+    where
+        A: Actor<Context = crate::Context<A>> + Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.stash_response(&mut ());
+        self.postponing = postponing;
+        self.stashing = Some(Box::new(move |tx: &mut dyn Any| {
+            let tx = tx
+                .downcast_mut::<Option<Responder<M>>>()
+                .and_then(Option::take);
+            match tx {
+                Some(tx) => tx.pack(msg),
+                None => <crate::Context<A> as ToEnvelope<A, M>>::pack(msg, None),
+            }
+        }));
+    }
+
+    pub(crate) fn set_handling(&mut self, handling: bool) {
+        self.handling = handling;
+    }
+
+    /// Move stashed message to the stash, message takes response channel
+    /// if it is of the same type.
+    pub(crate) fn stash_response(&mut self, tx: &mut dyn Any) {
+        if let Some(stashing) = self.stashing.take() {
+            let env = stashing(tx);
+            if self.postponing {
+                self.postponed.push_back(env);
+            } else {
+                self.stash.push_back(env);
+            }
+        }
+    }
+
+    /// Re-enqueue stashed messages ahead of mailbox.
+    pub fn unstash_all(&mut self) {
+        self.stash_response(&mut ());
+        while let Some(env) = self.stash.pop_back() {
+            self.unstashed.push_front(env);
+        }
+    }
+
+    /// Re-enqueue postponed messages ahead of mailbox.
+    pub(crate) fn unpostpone_all(&mut self) {
+        self.stash_response(&mut ());
+        while let Some(env) = self.postponed.pop_back() {
+            self.unstashed.push_front(env);
+        }
+    }
+
+    #[inline]
+    pub fn stashed(&self) -> usize {
+        self.stash.len() + usize::from(self.stashing.is_some() && !self.postponing)
+    }
+
+    #[inline]
+    pub(crate) fn postponed(&self) -> usize {
+        self.postponed.len() + usize::from(self.stashing.is_some() && self.postponing)
+    }
+
     /// Restart context. Cleanup all futures, except address queue.
+    ///
+    /// Stashed, postponed and unstashed messages are dropped, their
+    /// requests fail with `MailboxError::Closed`.
     #[inline]
     pub(crate) fn restart(&mut self) {
         self.flags = ContextFlags::RUNNING;
         self.wait = SmallVec::new();
         self.items = SmallVec::new();
         self.handles[0] = SpawnHandle::default();
+        self.reason = None;
+        self.stash = VecDeque::new();
+        self.postponed = VecDeque::new();
+        self.unstashed = VecDeque::new();
+        self.stashing = None;
+        self.postponing = false;
     }
 
     #[inline]
@@ -206,6 +393,8 @@ where
     mailbox: Mailbox<A>,
     wait: SmallVec<[ActorWaitItem<A>; 2]>,
     items: SmallVec<[Item<A>; 3]>,
+    stop_fut: Option<(ResponseActFuture<A, ()>, Delay)>,
+    tracked: Option<Tracked>,
 }
 
 impl<A, C> fmt::Debug for ContextFut<A, C>
@@ -224,11 +413,16 @@ where
     A: Actor<Context = C>,
 {
     fn drop(&mut self) {
-        if self.alive() {
-            self.ctx.parts().stop();
+        if self.stop_fut.is_none() && self.alive() {
+            self.ctx.parts().stop_with(StopReason::Shutdown);
             let waker = futures_util::task::noop_waker();
             let mut cx = futures_util::task::Context::from_waker(&waker);
-            let _ = Pin::new(self).poll(&mut cx);
+            let _ = Pin::new(&mut *self).poll(&mut cx);
+        }
+
+        // stopping future did not complete
+        if self.stop_fut.take().is_some() {
+            self.stopped();
         }
     }
 }
@@ -239,12 +433,17 @@ where
     A: Actor<Context = C>,
 {
     pub fn new(ctx: C, act: A, mailbox: Mailbox<A>) -> Self {
+        let producer = mailbox.sender_producer();
+        let tracked =
+            Tracked::register(producer.id(), type_name::<A>(), move || producer.len());
         ContextFut {
             ctx,
             act,
             mailbox,
             wait: SmallVec::new(),
             items: SmallVec::new(),
+            stop_fut: None,
+            tracked,
         }
     }
 
@@ -275,9 +474,16 @@ where
                 || self.mailbox.connected()
                 || !self.items.is_empty()
                 || !self.wait.is_empty()
+                || !self.ctx.parts().unstashed.is_empty()
         }
     }
 
+    /// Are any senders connected
+    #[inline]
+    pub(crate) fn connected(&self) -> bool {
+        self.mailbox.connected()
+    }
+
     /// Restart context. Cleanup all futures, except address queue.
     #[inline]
     pub(crate) fn restart(&mut self) -> bool
@@ -289,6 +495,10 @@ where
             self.items = SmallVec::new();
             self.ctx.parts().restart();
             self.act.restarting(&mut self.ctx);
+            if metrics::enabled() {
+                let id = self.mailbox.id();
+                metrics::report(|sink| sink.restarted(id, type_name::<A>()));
+            }
             true
         } else {
             false
@@ -319,6 +529,58 @@ where
         modified
     }
 
+    /// Run `Actor::stopping_async()` future, actor gets stopped once the
+    /// future completes or stopping deadline elapses.
+    fn stop(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.stop_fut.is_none() {
+            if let Some(fut) = Actor::stopping_async(&mut self.act, &mut self.ctx) {
+                let parts = self.ctx.parts();
+                parts.flags = ContextFlags::STOPPING | ContextFlags::STARTED;
+                self.stop_fut = Some((fut, delay_for(parts.stopping_deadline)));
+            }
+        }
+
+        if let Some((ref mut fut, ref mut deadline)) = self.stop_fut {
+            // terminated actor does not wait for stopping future
+            let (act, ctx) = (&mut self.act, &mut self.ctx);
+            if !ctx.parts().flags.contains(ContextFlags::STOPPED) {
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    fut.as_mut().poll(act, ctx, cx)
+                }));
+                if caught(ctx, res).is_pending() {
+                    if Pin::new(deadline).poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    warn!(
+                        "{}: stopping future did not complete before deadline",
+                        type_name::<A>()
+                    );
+                }
+            }
+            self.stop_fut = None;
+        }
+
+        self.stopped();
+        Poll::Ready(())
+    }
+
+    fn stopped(&mut self) {
+        self.ctx.parts().flags = ContextFlags::STOPPED | ContextFlags::STARTED;
+        Actor::stopped(&mut self.act, &mut self.ctx);
+        self.record_stop_reason();
+    }
+
+    /// Pass the stop reason to the mailbox, it gets reported to watchers.
+    fn record_stop_reason(&mut self) {
+        let reason = self
+            .ctx
+            .parts()
+            .reason
+            .clone()
+            .unwrap_or(StopReason::Normal);
+        self.mailbox.set_stop_reason(reason);
+    }
+
     fn clean_cancled_handle(&mut self) {
         while self.ctx.parts().handles.len() > 2 {
             let handle = self.ctx.parts().handles.pop().unwrap();
@@ -344,10 +606,36 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
+        let tracked = match this.tracked.take() {
+            Some(tracked) => tracked,
+            None => return this.poll_actor(cx),
+        };
+        let res = tracked.poll(cx, |cx| this.poll_actor(cx));
+        tracked.set_state(this.ctx.state());
+        tracked.set_futures(this.wait.len(), this.items.len());
+        this.tracked = Some(tracked);
+        res
+    }
+}
+
+impl<A, C> ContextFut<A, C>
+where
+    C: AsyncContextParts<A> + Unpin,
+    A: Actor<Context = C>,
+{
+    fn poll_actor(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self;
+
+        if this.stop_fut.is_some() {
+            return this.stop(cx);
+        }
 
         if !this.ctx.parts().flags.contains(ContextFlags::STARTED) {
             this.ctx.parts().flags.insert(ContextFlags::STARTED);
             Actor::started(&mut this.act, &mut this.ctx);
+            if let Some(fut) = Actor::started_async(&mut this.act, &mut this.ctx) {
+                this.ctx.parts().wait(fut);
+            }
 
             // check cancelled handles, just in case
             if this.merge() {
@@ -361,31 +649,47 @@ where
             // and we always have to check most recent future
             while !this.wait.is_empty() && !this.stopping() {
                 let idx = this.wait.len() - 1;
-                if let Some(item) = this.wait.last_mut() {
-                    match Pin::new(item).poll(&mut this.act, &mut this.ctx, cx) {
-                        Poll::Ready(_) => (),
-                        Poll::Pending => return Poll::Pending,
-                    }
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    Pin::new(&mut this.wait[idx]).poll(&mut this.act, &mut this.ctx, cx)
+                }));
+                if caught(&mut this.ctx, res).is_pending() {
+                    return Poll::Pending;
                 }
                 this.wait.remove(idx);
                 this.merge();
             }
 
+            // unstashed messages are handled ahead of mailbox
+            while this.ctx.parts().wait.is_empty() && !this.stopping() {
+                match this.ctx.parts().unstashed.pop_front() {
+                    Some(mut env) => {
+                        this.mailbox.handle(&mut env, &mut this.act, &mut this.ctx)
+                    }
+                    None => break,
+                }
+            }
+
             // process mailbox
             this.mailbox.poll(&mut this.act, &mut this.ctx, cx);
             if !this.wait.is_empty() && !this.stopping() {
                 continue;
             }
+            if !this.ctx.parts().unstashed.is_empty() && !this.stopping() {
+                this.merge();
+                continue;
+            }
 
             // process items
             let mut idx = 0;
             while idx < this.items.len() && !this.stopping() {
                 this.ctx.parts().handles[1] = this.items[idx].0;
-                match Pin::new(&mut this.items[idx].1).poll(
-                    &mut this.act,
-                    &mut this.ctx,
-                    cx,
-                ) {
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    this.items[idx]
+                        .1
+                        .as_mut()
+                        .poll(&mut this.act, &mut this.ctx, cx)
+                }));
+                match caught(&mut this.ctx, res) {
                     Poll::Pending => {
                         // check cancelled handles
                         if this.ctx.parts().handles.len() > 2 {
@@ -435,30 +739,34 @@ where
                 continue;
             }
 
+            if metrics::enabled() {
+                let (id, count) = (this.mailbox.id(), this.items.len());
+                metrics::report(|sink| {
+                    sink.spawned_futures(id, type_name::<A>(), count)
+                });
+            }
+
             // check state
             if this.ctx.parts().flags.contains(ContextFlags::RUNNING) {
                 // possible stop condition
-                if !this.alive()
-                    && Actor::stopping(&mut this.act, &mut this.ctx) == Running::Stop
-                {
-                    this.ctx.parts().flags =
-                        ContextFlags::STOPPED | ContextFlags::STARTED;
-                    Actor::stopped(&mut this.act, &mut this.ctx);
-                    return Poll::Ready(());
+                if !this.alive() {
+                    this.ctx.parts().reason = Some(StopReason::MailboxClosed);
+                    if Actor::stopping(&mut this.act, &mut this.ctx) == Running::Stop {
+                        return this.stop(cx);
+                    }
+                    this.ctx.parts().reason = None;
                 }
             } else if this.ctx.parts().flags.contains(ContextFlags::STOPPING) {
                 if Actor::stopping(&mut this.act, &mut this.ctx) == Running::Stop {
-                    this.ctx.parts().flags =
-                        ContextFlags::STOPPED | ContextFlags::STARTED;
-                    Actor::stopped(&mut this.act, &mut this.ctx);
-                    return Poll::Ready(());
+                    return this.stop(cx);
                 } else {
                     this.ctx.parts().flags.remove(ContextFlags::STOPPING);
                     this.ctx.parts().flags.insert(ContextFlags::RUNNING);
+                    this.ctx.parts().reason = None;
                     continue;
                 }
             } else if this.ctx.parts().flags.contains(ContextFlags::STOPPED) {
-                Actor::stopped(&mut this.act, &mut this.ctx);
+                this.stopped();
                 return Poll::Ready(());
             }
 
@@ -466,3 +774,21 @@ where
         }
     }
 }
+
+/// Panic in wait future or spawned future terminates the actor, the
+/// future is treated as completed.
+fn caught<A, C>(ctx: &mut C, res: thread::Result<Poll<()>>) -> Poll<()>
+where
+    A: Actor<Context = C>,
+    C: AsyncContextParts<A>,
+{
+    match res {
+        Ok(res) => res,
+        Err(err) => {
+            let reason = StopReason::from_panic(&*err);
+            error!("Actor future panicked: {:?}", reason);
+            ctx.terminate_with(reason);
+            Poll::Ready(())
+        }
+    }
+}
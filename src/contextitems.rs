@@ -8,7 +8,7 @@ use futures_util::{ready, stream::Stream};
 use pin_project::pin_project;
 
 use crate::actor::{Actor, ActorContext, AsyncContext};
-use crate::clock::Delay;
+use crate::clock::{self, Delay};
 use crate::fut::ActorFuture;
 use crate::handler::{Handler, Message, MessageResponse};
 
@@ -48,6 +48,42 @@ where
     }
 }
 
+/// Future which runs within the span it was spawned in.
+#[cfg(feature = "tracing")]
+#[pin_project]
+pub(crate) struct ActorInstrumented<F> {
+    #[pin]
+    fut: F,
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F> ActorInstrumented<F> {
+    pub fn new(fut: F) -> Self {
+        ActorInstrumented {
+            fut,
+            span: tracing::Span::current(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<F: ActorFuture> ActorFuture for ActorInstrumented<F> {
+    type Output = F::Output;
+    type Actor = F::Actor;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        act: &mut F::Actor,
+        ctx: &mut <F::Actor as Actor>::Context,
+        task: &mut task::Context<'_>,
+    ) -> Poll<F::Output> {
+        let this = self.project();
+        let _enter = this.span.enter();
+        this.fut.poll(act, ctx, task)
+    }
+}
+
 pub(crate) struct ActorDelayedMessageItem<A, M>
 where
     A: Actor,
@@ -73,7 +109,7 @@ where
     pub fn new(msg: M, timeout: Duration) -> Self {
         Self {
             msg: Some(msg),
-            timeout: tokio::time::delay_for(timeout),
+            timeout: clock::delay_for(timeout),
             act: PhantomData,
             m: PhantomData,
         }
@@ -0,0 +1,257 @@
+//! Finite state machine actors
+//!
+//! [`StateMachine`](trait.StateMachine.html) actor keeps its state in
+//! [`Fsm`](struct.Fsm.html). Message handlers return
+//! [`Transition`](enum.Transition.html) to the next state, it is applied
+//! once the handler returns. Returned transition is the only way to change
+//! the state.
+//!
+//! Entering a state can arm a state timeout, it is cancelled once the
+//! machine leaves the state. Messages which can not be handled in the
+//! current state can be postponed, they are handled again after next
+//! transition.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use actix::prelude::*;
+//! use actix::fsm::{Fsm, StateMachine, Transition};
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Door {
+//!     Locked,
+//!     Open,
+//! }
+//!
+//! struct Lock {
+//!     fsm: Fsm<Door>,
+//! }
+//!
+//! impl Actor for Lock {
+//!     type Context = Context<Self>;
+//!
+//!     fn started(&mut self, ctx: &mut Context<Self>) {
+//!         self.init_state(ctx);
+//!     }
+//! }
+//!
+//! impl StateMachine for Lock {
+//!     type State = Door;
+//!
+//!     fn fsm(&mut self) -> &mut Fsm<Door> {
+//!         &mut self.fsm
+//!     }
+//!
+//!     fn state_entered(&mut self, _: &mut Context<Self>) -> Option<Duration> {
+//!         match self.fsm.state() {
+//!             // lock the door again after a while
+//!             Door::Open => Some(Duration::from_secs(10)),
+//!             Door::Locked => None,
+//!         }
+//!     }
+//!
+//!     fn state_timeout(&mut self, _: &mut Context<Self>) -> Transition<Door> {
+//!         Transition::Next(Door::Locked)
+//!     }
+//! }
+//!
+//! struct Code(u32);
+//!
+//! impl Message for Code {
+//!     type Result = ();
+//! }
+//!
+//! impl Handler<Code> for Lock {
+//!     type Result = Transition<Door>;
+//!
+//!     fn handle(&mut self, msg: Code, _: &mut Context<Self>) -> Transition<Door> {
+//!         match self.fsm.state() {
+//!             Door::Locked if msg.0 == 1234 => Transition::Next(Door::Open),
+//!             _ => Transition::Keep,
+//!         }
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+use std::any::type_name;
+use std::fmt;
+use std::time::Duration;
+
+use log::trace;
+
+use crate::actor::{Actor, ActorContext, AsyncContext, SpawnHandle};
+use crate::context::Context;
+use crate::fut::{self, ActorFuture};
+use crate::handler::{Handler, Message, MessageResponse, ResponseChannel};
+
+/// Result of handling an event in a state.
+///
+/// Message handler of a `StateMachine` returns the transition, it is
+/// applied before the next message is handled and the response is sent
+/// once it is applied. Moving to the next state cancels state timeout of
+/// the current state and re-enqueues postponed messages.
+#[derive(Debug, PartialEq)]
+pub enum Transition<S> {
+    /// Stay in the current state, state timeout stays armed.
+    Keep,
+    /// Move to the next state. The state is entered even if it is equal
+    /// to the current one.
+    Next(S),
+    /// Stop the actor.
+    Stop,
+}
+
+/// State of a `StateMachine` actor.
+pub struct Fsm<S> {
+    state: S,
+    timeout: Option<SpawnHandle>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Fsm<S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Fsm")
+            .field("state", &self.state)
+            .field("timeout", &self.timeout.is_some())
+            .finish()
+    }
+}
+
+impl<S> Fsm<S> {
+    /// Create state machine in `initial` state.
+    ///
+    /// The initial state is entered with `StateMachine::init_state()`.
+    pub fn new(initial: S) -> Self {
+        Fsm {
+            state: initial,
+            timeout: None,
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns `true` if state timeout is armed.
+    pub fn timeout_armed(&self) -> bool {
+        self.timeout.is_some()
+    }
+}
+
+/// Actor implemented as a finite state machine.
+#[allow(unused_variables)]
+pub trait StateMachine: Actor<Context = Context<Self>> {
+    /// Type of the machine's state, usually an enum.
+    type State: fmt::Debug + 'static;
+
+    /// Returns state of the machine.
+    fn fsm(&mut self) -> &mut Fsm<Self::State>;
+
+    /// Called once the machine enters new state.
+    ///
+    /// Returned duration arms state timeout, `state_timeout()` is called
+    /// unless the machine leaves the state before the timeout elapses.
+    fn state_entered(&mut self, ctx: &mut Context<Self>) -> Option<Duration> {
+        None
+    }
+
+    /// Called once state timeout elapses.
+    fn state_timeout(&mut self, ctx: &mut Context<Self>) -> Transition<Self::State> {
+        Transition::Keep
+    }
+
+    /// Enters the initial state, it is usually called from
+    /// `Actor::started()`.
+    fn init_state(&mut self, ctx: &mut Context<Self>) {
+        enter(self, ctx)
+    }
+
+    /// Postpones message until the machine moves to the next state.
+    ///
+    /// Must be called from the handler of `msg`. Response to the message is
+    /// sent once it is handled again. Postponed messages are kept apart
+    /// from messages stashed with `Context::stash()`, a transition does not
+    /// unstash them.
+    fn postpone<M>(&mut self, msg: M, ctx: &mut Context<Self>)
+    where
+        Self: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        ctx.postpone(msg)
+    }
+
+    /// Returns the number of postponed messages.
+    fn postponed(&self, ctx: &Context<Self>) -> usize {
+        ctx.postponed()
+    }
+}
+
+impl<A, M> MessageResponse<A, M> for Transition<A::State>
+where
+    A: StateMachine,
+    M: Message<Result = ()>,
+{
+    fn handle<R: ResponseChannel<M>>(self, ctx: &mut Context<A>, tx: Option<R>) {
+        match self {
+            Transition::Keep => {
+                if let Some(tx) = tx {
+                    tx.send(());
+                }
+            }
+            transition => {
+                ctx.wait(fut::ready(transition).map(move |transition, act, ctx| {
+                    apply(act, transition, ctx);
+                    if let Some(tx) = tx {
+                        tx.send(());
+                    }
+                }))
+            }
+        }
+    }
+}
+
+/// Apply transition to the next state.
+fn apply<A: StateMachine>(
+    act: &mut A,
+    transition: Transition<A::State>,
+    ctx: &mut Context<A>,
+) {
+    match transition {
+        Transition::Keep => (),
+        Transition::Next(state) => {
+            let fsm = act.fsm();
+            trace!(
+                "{}: transition {:?} -> {:?}",
+                type_name::<A>(),
+                fsm.state,
+                state
+            );
+            fsm.state = state;
+            if let Some(handle) = fsm.timeout.take() {
+                ctx.cancel_future(handle);
+            }
+            ctx.unpostpone_all();
+            enter(act, ctx);
+        }
+        Transition::Stop => {
+            if let Some(handle) = act.fsm().timeout.take() {
+                ctx.cancel_future(handle);
+            }
+            ctx.stop();
+        }
+    }
+}
+
+/// Run entry callback of the current state and arm its timeout.
+fn enter<A: StateMachine>(act: &mut A, ctx: &mut Context<A>) {
+    if let Some(timeout) = act.state_entered(ctx) {
+        let handle = ctx.run_later(timeout, |act, ctx| {
+            act.fsm().timeout = None;
+            let transition = act.state_timeout(ctx);
+            apply(act, transition, ctx);
+        });
+        act.fsm().timeout = Some(handle);
+    }
+}
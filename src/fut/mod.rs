@@ -13,6 +13,7 @@ mod helpers;
 mod map;
 mod ready_fut;
 mod result;
+mod scoped;
 mod stream_finish;
 mod stream_fold;
 mod stream_map;
@@ -26,6 +27,7 @@ pub use self::helpers::{Finish, FinishStream};
 pub use self::map::Map;
 pub use self::ready_fut::{ready, Ready};
 pub use self::result::{err, ok, result, FutureResult};
+pub use self::scoped::{scoped, ActorScope, ScopedFuture};
 pub use self::stream_finish::StreamFinish;
 pub use self::stream_fold::StreamFold;
 pub use self::stream_map::StreamMap;
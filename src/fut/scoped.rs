@@ -0,0 +1,163 @@
+//! `async` blocks with access to the actor
+use std::cell::Cell;
+use std::fmt;
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::task::{self, Poll};
+
+use futures_util::future::Future;
+use pin_project::pin_project;
+
+use crate::actor::Actor;
+use crate::fut::ActorFuture;
+
+type Slot<A> = Rc<Cell<Option<(NonNull<A>, NonNull<<A as Actor>::Context>)>>>;
+
+/// Access to the actor and its context from a future created with
+/// [`scoped()`](fn.scoped.html).
+pub struct ActorScope<A: Actor> {
+    slot: Slot<A>,
+}
+
+impl<A: Actor> Clone for ActorScope<A> {
+    fn clone(&self) -> Self {
+        ActorScope {
+            slot: Rc::clone(&self.slot),
+        }
+    }
+}
+
+impl<A: Actor> fmt::Debug for ActorScope<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "ActorScope {{ /* omitted */ }}")
+    }
+}
+
+impl<A: Actor> ActorScope<A> {
+    /// Calls `f` with mutable access to the actor and its context.
+    ///
+    /// The borrow can not be held across `.await` points, actor state may
+    /// be changed by other messages meanwhile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of the scoped future, e.g. from a spawned
+    /// future, or from within `f`.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut A, &mut A::Context) -> R,
+    {
+        let (mut act, mut ctx) = self
+            .slot
+            .take()
+            .expect("ActorScope::with() is called outside of the scoped future");
+        let _restore = Restore {
+            slot: &self.slot,
+            value: Some((act, ctx)),
+        };
+        // pointers are valid while the scoped future is polled, the slot is
+        // empty until `f` returns so there is no other reference
+        unsafe { f(act.as_mut(), ctx.as_mut()) }
+    }
+}
+
+/// Puts value back to the slot on drop.
+struct Restore<'a, T: Copy> {
+    slot: &'a Cell<Option<T>>,
+    value: Option<T>,
+}
+
+impl<'a, T: Copy> Drop for Restore<'a, T> {
+    fn drop(&mut self) {
+        self.slot.set(self.value.take());
+    }
+}
+
+/// Future for the [`scoped()`](fn.scoped.html) function.
+#[pin_project]
+#[must_use = "futures do nothing unless polled"]
+pub struct ScopedFuture<A: Actor, F> {
+    #[pin]
+    fut: F,
+    slot: Slot<A>,
+}
+
+/// Creates `ActorFuture` from an `async` block which accesses the actor
+/// between `.await` points.
+///
+/// `f` receives [`ActorScope`](struct.ActorScope.html), the actor and its
+/// context are available with `ActorScope::with()` while the future is
+/// polled by the actor.
+///
+/// # Examples
+///
+/// ```rust
+/// use actix::prelude::*;
+/// use actix::clock::delay_for;
+/// use std::time::Duration;
+///
+/// struct Tick;
+///
+/// impl Message for Tick {
+///     type Result = usize;
+/// }
+///
+/// struct Counter(usize);
+///
+/// impl Actor for Counter {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl Handler<Tick> for Counter {
+///     type Result = ResponseActFuture<Self, usize>;
+///
+///     fn handle(&mut self, _: Tick, _: &mut Context<Self>) -> Self::Result {
+///         Box::pin(fut::scoped(|scope: ActorScope<Self>| async move {
+///             delay_for(Duration::from_millis(10)).await;
+///             scope.with(|act, _| {
+///                 act.0 += 1;
+///                 act.0
+///             })
+///         }))
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub fn scoped<A, F, Fut>(f: F) -> ScopedFuture<A, Fut>
+where
+    A: Actor,
+    F: FnOnce(ActorScope<A>) -> Fut,
+    Fut: Future,
+{
+    let slot = Rc::new(Cell::new(None));
+    let fut = f(ActorScope {
+        slot: Rc::clone(&slot),
+    });
+    ScopedFuture { fut, slot }
+}
+
+impl<A, F> ActorFuture for ScopedFuture<A, F>
+where
+    A: Actor,
+    F: Future,
+{
+    type Output = F::Output;
+    type Actor = A;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        act: &mut A,
+        ctx: &mut A::Context,
+        task: &mut task::Context<'_>,
+    ) -> Poll<F::Output> {
+        let this = self.project();
+        this.slot
+            .set(Some((NonNull::from(act), NonNull::from(ctx))));
+        let _restore = Restore {
+            slot: &**this.slot,
+            value: None,
+        };
+        this.fut.poll(task)
+    }
+}
@@ -2,7 +2,7 @@ use std::mem;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use pin_project::{pin_project, project};
+use pin_project::pin_project;
 
 use crate::actor::Actor;
 use crate::fut::{ActorFuture, ActorStream, IntoActorFuture};
@@ -61,14 +61,13 @@ where
     type Output = T;
     type Actor = S::Actor;
 
-    #[project]
     fn poll(
         self: Pin<&mut Self>,
         act: &mut S::Actor,
         ctx: &mut <S::Actor as Actor>::Context,
         task: &mut Context<'_>,
     ) -> Poll<T> {
-        let mut this = self.get_mut();
+        let this = self.get_mut();
         loop {
             match mem::replace(&mut this.state, State::Empty) {
                 State::Empty => panic!("cannot poll Fold twice"),
@@ -1,7 +1,7 @@
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use pin_project::{pin_project, project};
+use pin_project::pin_project;
 
 use crate::actor::Actor;
 use crate::fut::{ActorFuture, ActorStream, IntoActorFuture};
@@ -24,10 +24,10 @@ where
     f: F,
 }
 
-pub fn new<S, F: 'static, U>(stream: S, f: F) -> StreamThen<S, F, U>
+pub fn new<S, F, U>(stream: S, f: F) -> StreamThen<S, F, U>
 where
     S: ActorStream,
-    F: FnMut(S::Item, &mut S::Actor, &mut <S::Actor as Actor>::Context) -> U,
+    F: 'static + FnMut(S::Item, &mut S::Actor, &mut <S::Actor as Actor>::Context) -> U,
     U: IntoActorFuture<Actor = S::Actor>,
 {
     StreamThen {
@@ -47,14 +47,13 @@ where
     type Item = U::Output;
     type Actor = S::Actor;
 
-    #[project]
     fn poll_next(
         self: Pin<&mut Self>,
         act: &mut S::Actor,
         ctx: &mut <S::Actor as Actor>::Context,
         task: &mut Context<'_>,
     ) -> Poll<Option<U::Output>> {
-        let mut this = self.get_mut();
+        let this = self.get_mut();
         if this.future.is_none() {
             let item = match Pin::new(&mut this.stream).poll_next(act, ctx, task) {
                 Poll::Pending => return Poll::Pending,
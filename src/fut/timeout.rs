@@ -51,7 +51,7 @@ where
     ) -> Poll<Self::Output> {
         let this = self.project();
 
-        if let Poll::Ready(_) = this.timeout.poll(task) {
+        if this.timeout.poll(task).is_ready() {
             return Poll::Ready(Err(()));
         }
 
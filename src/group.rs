@@ -0,0 +1,484 @@
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{self, Poll, Waker};
+use std::time::Duration;
+
+use actix_rt::Arbiter;
+use futures_util::future::Future;
+use futures_util::task::{waker, ArcWake};
+use parking_lot::Mutex;
+
+use crate::actor::{Actor, ActorContext, StopReason, Supervised};
+use crate::address::{channel, Addr};
+use crate::clock::{self, Delay};
+use crate::context::Context;
+use crate::contextimpl::{AsyncContextParts, ContextFut};
+use crate::mailbox::DEFAULT_CAPACITY;
+use crate::supervisor::{Restarts, SupervisorStrategy};
+
+/// Restart policy of a [`ChildGroup`](struct.ChildGroup.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestartPolicy {
+    /// Only failed child gets restarted.
+    OneForOne,
+    /// All children get restarted if any child fails.
+    OneForAll,
+    /// Failed child and all children added after it get restarted.
+    RestForOne,
+}
+
+/// Group of supervised actors which are restarted together.
+///
+/// Children of a group can be of different actor types, all of them
+/// run in the same arbiter. If a child fails, group restarts it and,
+/// depending on `RestartPolicy`, its siblings. Running siblings are
+/// terminated before restart. Children are restarted in the order they
+/// were added to the group, each one gets `Supervised::restarting`
+/// called.
+///
+/// Child addresses returned by `ChildGroup::child()` stay valid across
+/// restarts, messages sent during restart are queued in the child's
+/// mailbox. Child which addresses were all dropped stops normally and
+/// does not get restarted anymore. Group stops once all children are
+/// stopped.
+///
+/// Number of group restarts can be limited with
+/// [`SupervisorStrategy`](struct.SupervisorStrategy.html), every
+/// failure counts as single restart regardless of number of restarted
+/// children. Once restart budget is exhausted all children get
+/// terminated.
+///
+/// Groups can be nested with `ChildGroup::group()` to build a supervision
+/// tree. Nested group is a single child of its parent: it fails once its
+/// own restart budget is exhausted, and restarting it restarts all of its
+/// children with a fresh budget.
+///
+/// ## Example
+///
+/// ```rust
+/// # use actix::prelude::*;
+/// use actix::{ChildGroup, RestartPolicy};
+///
+/// struct Connection;
+///
+/// impl Actor for Connection {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl actix::Supervised for Connection {}
+///
+/// struct Parser;
+///
+/// impl Actor for Parser {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl actix::Supervised for Parser {}
+///
+/// fn main() {
+///     System::run(|| {
+///         let mut conns = ChildGroup::new(RestartPolicy::OneForAll);
+///         let conn = conns.child(|_| Connection);
+///         let parser = conns.child(|_| Parser);
+///
+///         let mut root = ChildGroup::new(RestartPolicy::OneForOne);
+///         root.group(conns);
+///         root.start();
+/// #       System::current().stop();
+///     });
+/// }
+/// ```
+pub struct ChildGroup {
+    policy: RestartPolicy,
+    strategy: SupervisorStrategy,
+    children: Vec<Box<dyn FnOnce() -> Box<dyn Child> + Send>>,
+}
+
+impl fmt::Debug for ChildGroup {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ChildGroup")
+            .field("policy", &self.policy)
+            .field("strategy", &self.strategy)
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+impl ChildGroup {
+    /// Create empty group with specified restart policy.
+    pub fn new(policy: RestartPolicy) -> Self {
+        ChildGroup {
+            policy,
+            strategy: SupervisorStrategy::default(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Set restart strategy of the group.
+    pub fn strategy(mut self, strategy: SupervisorStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Add child actor to the group.
+    ///
+    /// Actor is created once group starts. Returned address stays valid
+    /// across restarts.
+    pub fn child<A, F>(&mut self, f: F) -> Addr<A>
+    where
+        A: Supervised + Actor<Context = Context<A>>,
+        F: FnOnce(&mut Context<A>) -> A + Send + 'static,
+    {
+        let (tx, rx) = channel::channel(DEFAULT_CAPACITY);
+
+        self.children.push(Box::new(move || {
+            let mut ctx = Context::with_receiver(rx);
+            let act = f(&mut ctx);
+            let child: Box<dyn Child> = Box::new(ChildFut::new(ctx.into_future(act)));
+            child
+        }));
+
+        Addr::new(tx)
+    }
+
+    /// Add nested group to the group.
+    ///
+    /// Nested group runs in the same arbiter as its parent and gets
+    /// restarted according to parent's `RestartPolicy` once its restart
+    /// budget is exhausted.
+    pub fn group(&mut self, group: ChildGroup) {
+        self.children.push(Box::new(move || {
+            let child: Box<dyn Child> = Box::new(GroupFut::new(group));
+            child
+        }));
+    }
+
+    /// Start group in current arbiter.
+    pub fn start(self) {
+        actix_rt::spawn(GroupFut::new(self));
+    }
+
+    /// Start group in arbiter's thread.
+    pub fn start_in_arbiter(self, sys: &Arbiter) {
+        sys.exec_fn(move || actix_rt::spawn(GroupFut::new(self)));
+    }
+}
+
+/// Type erased supervised child.
+trait Child {
+    /// Poll running child, resolves once child is stopped.
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<()>;
+
+    /// Terminate running child.
+    fn terminate(&mut self);
+
+    /// The reason why child stopped.
+    fn stop_reason(&mut self) -> Option<StopReason>;
+
+    /// Restart stopped child, returns `false` if child's address
+    /// is not connected.
+    fn restart(&mut self) -> bool;
+
+    /// Did stopped child fail, child which stops while its address is
+    /// still connected is considered failed.
+    fn failed(&self) -> bool;
+}
+
+struct ChildFut<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fut: ContextFut<A, Context<A>>,
+}
+
+impl<A> ChildFut<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fn new(fut: ContextFut<A, Context<A>>) -> Self {
+        ChildFut { fut }
+    }
+}
+
+impl<A> Child for ChildFut<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.fut).poll(cx)
+    }
+
+    fn terminate(&mut self) {
+        self.fut.ctx().parts().terminate_with(StopReason::Shutdown);
+
+        // stopped context resolves immediately
+        let waker = futures_util::task::noop_waker();
+        let mut cx = task::Context::from_waker(&waker);
+        let _ = Pin::new(&mut self.fut).poll(&mut cx);
+    }
+
+    fn stop_reason(&mut self) -> Option<StopReason> {
+        self.fut.ctx().stop_reason().cloned()
+    }
+
+    fn restart(&mut self) -> bool {
+        self.fut.restart()
+    }
+
+    fn failed(&self) -> bool {
+        self.fut.connected()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ChildState {
+    Running,
+    Restarting,
+    Stopped,
+}
+
+/// Children woken since the group was polled last time.
+#[derive(Default)]
+struct Wakeups {
+    parent: Mutex<Option<Waker>>,
+    woken: Mutex<Vec<usize>>,
+}
+
+/// Waker of a single child, queues the child for polling and wakes
+/// the group.
+struct ChildWaker {
+    idx: usize,
+    queued: AtomicBool,
+    wakeups: Arc<Wakeups>,
+}
+
+impl ChildWaker {
+    /// Queue child for polling, returns `false` if it is queued already.
+    fn queue(&self) -> bool {
+        if self.queued.swap(true, Ordering::AcqRel) {
+            false
+        } else {
+            self.wakeups.woken.lock().push(self.idx);
+            true
+        }
+    }
+}
+
+impl ArcWake for ChildWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if arc_self.queue() {
+            if let Some(ref waker) = *arc_self.wakeups.parent.lock() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+struct Member {
+    state: ChildState,
+    child: Box<dyn Child>,
+    wake: Arc<ChildWaker>,
+    waker: Waker,
+}
+
+struct GroupFut {
+    policy: RestartPolicy,
+    restarts: Restarts,
+    delay: Option<Delay>,
+    children: Vec<Member>,
+    wakeups: Arc<Wakeups>,
+    /// Restart budget is exhausted, the reason of the last failure.
+    exhausted: Option<Option<StopReason>>,
+}
+
+impl GroupFut {
+    fn new(group: ChildGroup) -> Self {
+        let wakeups = Arc::new(Wakeups::default());
+        let children = group
+            .children
+            .into_iter()
+            .enumerate()
+            .map(|(idx, f)| {
+                let wake = Arc::new(ChildWaker {
+                    idx,
+                    queued: AtomicBool::new(false),
+                    wakeups: Arc::clone(&wakeups),
+                });
+                wake.queue();
+                Member {
+                    state: ChildState::Running,
+                    child: f(),
+                    waker: waker(Arc::clone(&wake)),
+                    wake,
+                }
+            })
+            .collect();
+
+        GroupFut {
+            policy: group.policy,
+            restarts: Restarts::new(group.strategy),
+            delay: None,
+            children,
+            wakeups,
+            exhausted: None,
+        }
+    }
+
+    /// Child `idx` failed, mark children for restart according to policy.
+    fn failed(&mut self, idx: usize) {
+        // failed child is already stopped
+        self.children[idx].state = ChildState::Restarting;
+
+        let restart = match self.policy {
+            RestartPolicy::OneForOne => idx..idx + 1,
+            RestartPolicy::OneForAll => 0..self.children.len(),
+            RestartPolicy::RestForOne => idx..self.children.len(),
+        };
+
+        for member in &mut self.children[restart] {
+            if member.state == ChildState::Running {
+                member.child.terminate();
+            }
+            if member.state != ChildState::Stopped {
+                member.state = ChildState::Restarting;
+            }
+        }
+    }
+
+    /// Restart all marked children.
+    fn restart_marked(&mut self) {
+        for member in &mut self.children {
+            if member.state == ChildState::Restarting {
+                member.state = if member.child.restart() {
+                    member.wake.queue();
+                    ChildState::Running
+                } else {
+                    ChildState::Stopped
+                };
+            }
+        }
+    }
+
+    /// Terminate all running children.
+    fn terminate_all(&mut self) {
+        self.delay = None;
+        for member in &mut self.children {
+            if member.state == ChildState::Running {
+                member.child.terminate();
+            }
+            member.state = ChildState::Stopped;
+        }
+    }
+}
+
+/// Nested group is a child of its parent group.
+impl Child for GroupFut {
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<()> {
+        Future::poll(Pin::new(self), cx)
+    }
+
+    fn terminate(&mut self) {
+        self.terminate_all();
+    }
+
+    fn stop_reason(&mut self) -> Option<StopReason> {
+        self.exhausted.clone().and_then(|reason| reason)
+    }
+
+    fn restart(&mut self) -> bool {
+        self.exhausted = None;
+        self.restarts.reset();
+        for member in &mut self.children {
+            member.state = ChildState::Restarting;
+        }
+        self.restart_marked();
+        self.children
+            .iter()
+            .any(|member| member.state == ChildState::Running)
+    }
+
+    fn failed(&self) -> bool {
+        self.exhausted.is_some()
+    }
+}
+
+impl Future for GroupFut {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        {
+            let mut parent = this.wakeups.parent.lock();
+            if !parent.as_ref().map_or(false, |w| w.will_wake(cx.waker())) {
+                *parent = Some(cx.waker().clone());
+            }
+        }
+
+        loop {
+            // restart marked children once the backoff elapses, running
+            // children are polled meanwhile
+            if let Some(ref mut delay) = this.delay {
+                if Pin::new(delay).poll(cx).is_ready() {
+                    this.delay = None;
+                    this.restart_marked();
+                }
+            }
+
+            // poll only children which were woken
+            let woken = mem::replace(&mut *this.wakeups.woken.lock(), Vec::new());
+            let mut failed = None;
+            for (pos, &idx) in woken.iter().enumerate() {
+                let member = &mut this.children[idx];
+                member.wake.queued.store(false, Ordering::Release);
+                if member.state != ChildState::Running {
+                    continue;
+                }
+                let mut child_cx = task::Context::from_waker(&member.waker);
+                if member.child.poll(&mut child_cx).is_ready() {
+                    if member.child.failed() {
+                        // children which were not polled stay queued
+                        this.wakeups.woken.lock().extend(&woken[pos + 1..]);
+                        failed = Some(idx);
+                        break;
+                    }
+                    member.state = ChildState::Stopped;
+                }
+            }
+
+            match failed {
+                Some(idx) => {
+                    let reason = this.children[idx].child.stop_reason();
+                    this.failed(idx);
+
+                    match this.restarts.failed(reason.clone()) {
+                        // restart budget is exhausted, stop group permanently
+                        None => {
+                            this.terminate_all();
+                            this.exhausted = Some(reason);
+                            return Poll::Ready(());
+                        }
+                        Some(delay) if delay == Duration::from_secs(0) => {
+                            this.restart_marked()
+                        }
+                        Some(delay) => this.delay = Some(clock::delay_for(delay)),
+                    }
+                }
+                None => {
+                    return if this
+                        .children
+                        .iter()
+                        .all(|member| member.state == ChildState::Stopped)
+                    {
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    };
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,17 @@
 use std::fmt;
 use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::task::Poll;
 
 use futures_channel::oneshot::Sender as SyncSender;
+use futures_util::future;
 
-use crate::actor::{Actor, AsyncContext};
-use crate::address::Addr;
+use crate::actor::{Actor, AsyncContext, StopReason};
+use crate::address::{Addr, MailboxError};
 use crate::context::Context;
-use crate::fut::ActorFuture;
+use crate::fut::{self, ActorFuture, ActorScope};
 
 /// Describes how to handle messages of a specific type.
 ///
@@ -37,6 +40,33 @@ pub trait Message {
     /// The type of value that this message will resolved with if it is
     /// successful.
     type Result: 'static;
+
+    /// Priority of the message in actor's mailbox.
+    ///
+    /// Can be overridden for individual sends with `Addr::send_with_priority()`.
+    const PRIORITY: Priority = Priority::Normal;
+}
+
+/// Priority of a message in actor's mailbox.
+///
+/// Messages with higher priority are handled first, messages with the
+/// same priority are handled in the order they were sent. Mailbox keeps
+/// a single queue until a message with other than `Normal` priority is
+/// sent to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Handled once there are no messages with higher priority.
+    Low,
+    /// Default priority of messages.
+    Normal,
+    /// Handled ahead of other messages.
+    High,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
 }
 
 /// Allow users to use `Arc<M>` as a message without having to re-impl `Message`
@@ -45,6 +75,8 @@ where
     M: Message<Result = R>,
 {
     type Result = R;
+
+    const PRIORITY: Priority = M::PRIORITY;
 }
 
 /// Allow users to use `Box<M>` as a message without having to re-impl `Message`
@@ -53,6 +85,8 @@ where
     M: Message<Result = R>,
 {
     type Result = R;
+
+    const PRIORITY: Priority = M::PRIORITY;
 }
 
 /// A helper type that implements the `MessageResponse` trait.
@@ -142,6 +176,18 @@ impl<A, T> AtomicResponse<A, T> {
     pub fn new(fut: ResponseActFuture<A, T>) -> Self {
         AtomicResponse(fut)
     }
+
+    /// Creates a response from an `async` block, see `fut::scoped()`.
+    ///
+    /// The mailbox is not polled until the block completes.
+    pub fn scoped<F, Fut>(f: F) -> Self
+    where
+        A: Actor,
+        F: FnOnce(ActorScope<A>) -> Fut,
+        Fut: Future<Output = T> + 'static,
+    {
+        AtomicResponse(Box::pin(fut::scoped(f)))
+    }
 }
 
 impl<A, M, T: 'static> MessageResponse<A, M> for AtomicResponse<A, T>
@@ -247,6 +293,14 @@ pub trait ResponseChannel<M: Message>: 'static {
     fn is_canceled(&self) -> bool;
 
     fn send(self, response: M::Result);
+
+    /// Fail the request with `err`, by default the channel is dropped.
+    #[doc(hidden)]
+    fn fail(self, _: MailboxError)
+    where
+        Self: Sized,
+    {
+    }
 }
 
 /// A trait which defines message responses.
@@ -430,15 +484,47 @@ where
     A::Context: AsyncContext<A>,
 {
     fn handle<R: ResponseChannel<M>>(self, _: &mut A::Context, tx: Option<R>) {
-        actix_rt::spawn(async move {
-            let res = self.await;
-            if let Some(tx) = tx {
-                tx.send(res)
-            }
-        });
+        spawn_response(self, tx);
     }
 }
 
+/// Run response future detached from the actor, its panic fails the
+/// request with `MailboxError::Panicked`.
+fn spawn_response<M, F, R>(mut fut: F, tx: Option<R>)
+where
+    M: Message,
+    F: Future<Output = M::Result> + Unpin + 'static,
+    R: ResponseChannel<M>,
+{
+    actix_rt::spawn(async move {
+        let res = future::poll_fn(|cx| {
+            match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut fut).poll(cx))) {
+                Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+                Ok(Poll::Pending) => Poll::Pending,
+                Err(err) => Poll::Ready(Err(err)),
+            }
+        })
+        .await;
+
+        match res {
+            Ok(res) => {
+                if let Some(tx) = tx {
+                    tx.send(res)
+                }
+            }
+            Err(err) => {
+                log::error!(
+                    "Response future panicked: {:?}",
+                    StopReason::from_panic(&*err)
+                );
+                if let Some(tx) = tx {
+                    tx.fail(MailboxError::Panicked)
+                }
+            }
+        }
+    });
+}
+
 enum ResponseTypeItem<I, E> {
     Result(Result<I, E>),
     Fut(Box<dyn Future<Output = Result<I, E>> + Unpin>),
@@ -487,13 +573,7 @@ where
 {
     fn handle<R: ResponseChannel<M>>(self, _: &mut A::Context, tx: Option<R>) {
         match self.item {
-            ResponseTypeItem::Fut(fut) => {
-                actix_rt::spawn(async move {
-                    if let Some(tx) = tx {
-                        tx.send(fut.await);
-                    }
-                });
-            }
+            ResponseTypeItem::Fut(fut) => spawn_response(fut, tx),
             ResponseTypeItem::Result(res) => {
                 if let Some(tx) = tx {
                     tx.send(res);
@@ -543,6 +623,18 @@ impl<A: Actor, I, E> ActorResponse<A, I, E> {
             item: ActorResponseTypeItem::Fut(Box::pin(fut)),
         }
     }
+
+    /// Creates an asynchronous response from an `async` block, see
+    /// `fut::scoped()`.
+    ///
+    /// Other messages are handled while the block runs.
+    pub fn scoped<F, Fut>(f: F) -> Self
+    where
+        F: FnOnce(ActorScope<A>) -> Fut,
+        Fut: Future<Output = Result<I, E>> + 'static,
+    {
+        Self::r#async(fut::scoped(f))
+    }
 }
 
 impl<A, M, I: 'static, E: 'static> MessageResponse<A, M> for ActorResponse<A, I, E>
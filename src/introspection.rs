@@ -0,0 +1,456 @@
+//! Registry of live actors
+//!
+//! Once [`enable()`](fn.enable.html) is called, every started actor,
+//! including sync actor workers, is tracked until its context is dropped.
+//! [`actors()`](fn.actors.html) returns state of tracked actors, it is
+//! useful for diagnosing stuck actors.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::introspection;
+//!
+//! struct MyActor;
+//!
+//! impl Actor for MyActor {
+//!     type Context = Context<Self>;
+//! }
+//!
+//! fn main() {
+//!     introspection::enable();
+//!
+//!     System::run(|| {
+//!         let addr = MyActor.start();
+//!
+//!         actix::spawn(async move {
+//!             let actors = introspection::actors();
+//!             assert!(actors.iter().any(|info| info.id == addr.id()));
+//!             println!("{}", introspection::to_json());
+//!
+//!             System::current().stop();
+//!         });
+//!     })
+//!     .unwrap();
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Waker};
+use std::thread;
+
+use actix_rt::System;
+use futures_util::task::{waker, ArcWake};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::actor::ActorState;
+use crate::address::ActorId;
+use crate::utils::escape;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Number of live `TrackingGuard`s, actors are tracked while there are any.
+static GUARDS: AtomicUsize = AtomicUsize::new(0);
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+static ACTORS: Lazy<Mutex<HashMap<usize, Arc<Entry>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static POOLS: Lazy<Mutex<HashMap<usize, Arc<Pool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Actor is not woken and does not run.
+const IDLE: u8 = 0;
+/// Actor is polled, or sync actor handles a message.
+const RUNNING: u8 = 1;
+/// Actor is woken and waits to be polled.
+const WOKEN: u8 = 2;
+
+/// Start tracking actors. Actors started before this call are not tracked.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Stop tracking newly started actors.
+///
+/// Already tracked actors are reported until they stop.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+#[inline]
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Acquire) || GUARDS.load(Ordering::Acquire) != 0
+}
+
+/// Tracks actors while it is alive, regardless of `enable()` and
+/// `disable()`.
+#[derive(Debug)]
+pub(crate) struct TrackingGuard(());
+
+impl TrackingGuard {
+    pub(crate) fn new() -> Self {
+        GUARDS.fetch_add(1, Ordering::AcqRel);
+        TrackingGuard(())
+    }
+}
+
+impl Drop for TrackingGuard {
+    fn drop(&mut self) {
+        GUARDS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// State of a live actor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActorInfo {
+    /// Identifier of the actor, shared by workers of a `SyncArbiter`.
+    pub id: ActorId,
+    /// Type name of the actor.
+    pub actor: &'static str,
+    /// Identifier of the `System` the actor was started in.
+    pub system: Option<usize>,
+    /// Name of the thread running the actor, or its id if the thread is
+    /// not named. Arbiter threads are named `actix-rt:worker:<id>`.
+    pub thread: String,
+    /// Execution state of the actor.
+    pub state: ActorState,
+    /// Number of messages in actor's mailbox.
+    pub mailbox: usize,
+    /// Number of futures started with `AsyncContext::wait()` which block
+    /// the mailbox.
+    pub wait_futures: usize,
+    /// Number of futures and streams spawned in actor's context.
+    pub spawned_futures: usize,
+    /// Actor is woken, it is being polled, or sync actor handles
+    /// a message or its elapsed timer.
+    pub busy: bool,
+    /// Number of times actor's context was polled, or number of messages
+    /// handled by sync actor.
+    pub polls: usize,
+}
+
+impl ActorInfo {
+    /// Render as JSON object.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        let _ = write!(
+            out,
+            "{{\"id\":{},\"actor\":\"{}\",\"system\":",
+            self.id.as_usize(),
+            escape(self.actor)
+        );
+        match self.system {
+            Some(system) => {
+                let _ = write!(out, "{}", system);
+            }
+            None => out.push_str("null"),
+        }
+        let _ = write!(
+            out,
+            ",\"thread\":\"{}\",\"state\":\"{:?}\",\"mailbox\":{},\
+             \"wait_futures\":{},\"spawned_futures\":{},\"busy\":{},\"polls\":{}}}",
+            escape(&self.thread),
+            self.state,
+            self.mailbox,
+            self.wait_futures,
+            self.spawned_futures,
+            self.busy,
+            self.polls
+        );
+    }
+}
+
+/// Returns state of all tracked actors, ordered by start time.
+pub fn actors() -> Vec<ActorInfo> {
+    let mut entries: Vec<_> = ACTORS
+        .lock()
+        .iter()
+        .map(|(key, entry)| (*key, entry.clone()))
+        .collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries.iter().map(|(_, entry)| entry.info()).collect()
+}
+
+/// Returns state of all tracked actors as JSON array.
+pub fn to_json() -> String {
+    let mut out = String::from("[");
+    for (idx, info) in actors().iter().enumerate() {
+        if idx != 0 {
+            out.push(',');
+        }
+        info.write_json(&mut out);
+    }
+    out.push(']');
+    out
+}
+
+struct Entry {
+    id: ActorId,
+    actor: &'static str,
+    system: Option<usize>,
+    thread: String,
+    state: AtomicU8,
+    wait_futures: AtomicUsize,
+    spawned_futures: AtomicUsize,
+    busy: AtomicU8,
+    polls: AtomicUsize,
+    mailbox: Box<dyn Fn() -> usize + Send + Sync>,
+}
+
+impl Entry {
+    fn info(&self) -> ActorInfo {
+        ActorInfo {
+            id: self.id,
+            actor: self.actor,
+            system: self.system,
+            thread: self.thread.clone(),
+            state: decode_state(self.state.load(Ordering::Relaxed)),
+            mailbox: (self.mailbox)(),
+            wait_futures: self.wait_futures.load(Ordering::Relaxed),
+            spawned_futures: self.spawned_futures.load(Ordering::Relaxed),
+            busy: self.busy.load(Ordering::SeqCst) != IDLE,
+            polls: self.polls.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Marks actor busy once its task is woken.
+struct BusyWaker {
+    entry: Arc<Entry>,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl ArcWake for BusyWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.entry.busy.store(WOKEN, Ordering::SeqCst);
+        if let Some(ref waker) = *arc_self.waker.lock() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// Registration of an actor, actor is removed from registry on drop.
+pub(crate) struct Tracked {
+    key: usize,
+    entry: Arc<Entry>,
+    busy: Arc<BusyWaker>,
+    waker: Waker,
+}
+
+/// Name of the current thread, or its id if the thread is not named.
+pub(crate) fn current_thread() -> String {
+    let current = thread::current();
+    match current.name() {
+        Some(name) => name.to_owned(),
+        None => format!("{:?}", current.id()),
+    }
+}
+
+impl Tracked {
+    /// Register actor if tracking is enabled. `mailbox` returns number of
+    /// messages in actor's mailbox.
+    pub(crate) fn register<F>(
+        id: ActorId,
+        actor: &'static str,
+        mailbox: F,
+    ) -> Option<Self>
+    where
+        F: Fn() -> usize + Send + Sync + 'static,
+    {
+        if !enabled() {
+            return None;
+        }
+
+        let thread = current_thread();
+        let system = if System::is_set() {
+            Some(System::current().id())
+        } else {
+            None
+        };
+        let entry = Arc::new(Entry {
+            id,
+            actor,
+            system,
+            thread,
+            state: AtomicU8::new(encode_state(ActorState::Started)),
+            wait_futures: AtomicUsize::new(0),
+            spawned_futures: AtomicUsize::new(0),
+            busy: AtomicU8::new(RUNNING),
+            polls: AtomicUsize::new(0),
+            mailbox: Box::new(mailbox),
+        });
+        let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        ACTORS.lock().insert(key, entry.clone());
+        let busy = Arc::new(BusyWaker {
+            entry: entry.clone(),
+            waker: Mutex::new(None),
+        });
+        let waker = waker(busy.clone());
+        Some(Tracked {
+            key,
+            entry,
+            busy,
+            waker,
+        })
+    }
+
+    /// Poll actor with a waker which marks the actor busy once woken.
+    /// Actor stays busy until the poll returns, or until the next poll if
+    /// it is woken meanwhile.
+    pub(crate) fn poll<F, R>(&self, cx: &mut Context<'_>, f: F) -> R
+    where
+        F: FnOnce(&mut Context<'_>) -> R,
+    {
+        {
+            let mut waker = self.busy.waker.lock();
+            match *waker {
+                Some(ref waker) if waker.will_wake(cx.waker()) => (),
+                _ => *waker = Some(cx.waker().clone()),
+            }
+        }
+        self.entry.busy.store(RUNNING, Ordering::SeqCst);
+        self.entry.polls.fetch_add(1, Ordering::SeqCst);
+        let res = f(&mut Context::from_waker(&self.waker));
+        let _ = self.entry.busy.compare_exchange(
+            RUNNING,
+            IDLE,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        res
+    }
+
+    /// Mark sync actor busy while it handles a message.
+    pub(crate) fn set_busy(&self, busy: bool) {
+        if busy {
+            self.entry.polls.fetch_add(1, Ordering::SeqCst);
+            self.entry.busy.store(RUNNING, Ordering::SeqCst);
+        } else {
+            self.entry.busy.store(IDLE, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn set_state(&self, state: ActorState) {
+        self.entry
+            .state
+            .store(encode_state(state), Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_futures(&self, wait: usize, spawned: usize) {
+        self.entry.wait_futures.store(wait, Ordering::Relaxed);
+        self.entry.spawned_futures.store(spawned, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        ACTORS.lock().remove(&self.key);
+    }
+}
+
+/// Messages taken by a `SyncArbiter` from its mailbox which are not
+/// handled by workers yet. They are not in the mailbox of any worker.
+struct Pool {
+    id: ActorId,
+    actor: &'static str,
+    system: Option<usize>,
+    thread: String,
+    pending: Box<dyn Fn() -> usize + Send + Sync>,
+}
+
+/// Registration of a `SyncArbiter`, it is removed from registry on drop.
+pub(crate) struct TrackedPool {
+    key: usize,
+}
+
+impl TrackedPool {
+    /// Register pool if tracking is enabled. `pending` returns number of
+    /// messages in pool's mailbox and messages which are not handled yet.
+    pub(crate) fn register<F>(
+        id: ActorId,
+        actor: &'static str,
+        pending: F,
+    ) -> Option<Self>
+    where
+        F: Fn() -> usize + Send + Sync + 'static,
+    {
+        if !enabled() {
+            return None;
+        }
+
+        let system = if System::is_set() {
+            Some(System::current().id())
+        } else {
+            None
+        };
+        let pool = Arc::new(Pool {
+            id,
+            actor,
+            system,
+            thread: current_thread(),
+            pending: Box::new(pending),
+        });
+        let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        POOLS.lock().insert(key, pool);
+        Some(TrackedPool { key })
+    }
+}
+
+impl Drop for TrackedPool {
+    fn drop(&mut self) {
+        POOLS.lock().remove(&self.key);
+    }
+}
+
+/// Sync actor pools of the `System` with messages which are not handled
+/// yet, messages are reported as pool's mailbox.
+pub(crate) fn busy_pools(system: usize) -> Vec<ActorInfo> {
+    let pools: Vec<_> = POOLS
+        .lock()
+        .values()
+        .filter(|pool| pool.system == Some(system))
+        .cloned()
+        .collect();
+    pools
+        .iter()
+        .filter_map(|pool| match (pool.pending)() {
+            0 => None,
+            pending => Some(ActorInfo {
+                id: pool.id,
+                actor: pool.actor,
+                system: pool.system,
+                thread: pool.thread.clone(),
+                state: ActorState::Running,
+                mailbox: pending,
+                wait_futures: 0,
+                spawned_futures: 0,
+                busy: true,
+                polls: 0,
+            }),
+        })
+        .collect()
+}
+
+fn encode_state(state: ActorState) -> u8 {
+    match state {
+        ActorState::Started => 0,
+        ActorState::Running => 1,
+        ActorState::Stopping => 2,
+        ActorState::Stopped => 3,
+    }
+}
+
+fn decode_state(state: u8) -> ActorState {
+    match state {
+        0 => ActorState::Started,
+        1 => ActorState::Running,
+        2 => ActorState::Stopping,
+        _ => ActorState::Stopped,
+    }
+}
@@ -28,6 +28,11 @@
 //! ## Package feature
 //!
 //! * `resolver` - enables dns resolver actor, `actix::actors::resolver`
+//! * `tracing` - handles messages within [`tracing`](https://docs.rs/tracing)
+//!   spans, span of the message handler is child of the sender's span,
+//!   requires Rust 1.42 or later
+//! * `test-util` - enables `actix::clock::pause()` and `actix::clock::advance()`
+//!   to control time in tests
 //!
 //! ## Tokio runtime
 //!
@@ -37,9 +42,9 @@
 //! While it provides minimum overhead, it has its own limits:
 //!
 //! - You cannot use tokio's async file I/O, as it relies on blocking calls that are not available
-//! in `current_thread`
+//!   in `current_thread`
 //! - `Stdin`, `Stderr` and `Stdout` from `tokio::io` are the same as file I/O in that regard and
-//! cannot be used in asynchronous manner in actix.
+//!   cannot be used in asynchronous manner in actix.
 // It's pain for this crate and has false positives.
 #![allow(clippy::needless_doctest_main)]
 #![deny(bare_trait_objects, nonstandard_style, rust_2018_idioms, unused)]
@@ -55,6 +60,7 @@ mod actor;
 mod context;
 mod contextimpl;
 mod contextitems;
+mod group;
 mod handler;
 mod stream;
 mod supervisor;
@@ -64,29 +70,42 @@ mod mailbox;
 
 pub mod actors;
 pub mod clock;
+pub mod fsm;
 pub mod fut;
+pub mod introspection;
 pub mod io;
+pub mod metrics;
 pub mod registry;
 pub mod sync;
+pub mod testing;
 pub mod utils;
 
 pub use actix_rt::{Arbiter, System, SystemRunner};
 
 pub use crate::actor::{
-    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, StopReason,
+    Supervised,
+};
+pub use crate::address::{
+    ActorId, Addr, MailboxError, Recipient, Terminated, WatchHandle, Watchable, WeakAddr,
 };
-pub use crate::address::{Addr, MailboxError, Recipient, WeakAddr};
 // pub use crate::arbiter::{Arbiter, ArbiterBuilder};
 pub use crate::context::Context;
-pub use crate::fut::{ActorFuture, ActorStream, FinishStream, WrapFuture, WrapStream};
+pub use crate::fut::{
+    ActorFuture, ActorScope, ActorStream, FinishStream, WrapFuture, WrapStream,
+};
+pub use crate::group::{ChildGroup, RestartPolicy};
 pub use crate::handler::{
-    ActorResponse, AtomicResponse, Handler, Message, MessageResult, Response,
+    ActorResponse, AtomicResponse, Handler, Message, MessageResult, Priority, Response,
     ResponseActFuture, ResponseFuture,
 };
+pub use crate::mailbox::OverflowPolicy;
 pub use crate::registry::{ArbiterService, Registry, SystemRegistry, SystemService};
 pub use crate::stream::StreamHandler;
-pub use crate::supervisor::Supervisor;
-pub use crate::sync::{SyncArbiter, SyncContext};
+pub use crate::supervisor::{Escalation, Supervisor, SupervisorStrategy};
+pub use crate::sync::{
+    Affinity, ShutdownPolicy, SyncArbiter, SyncArbiterBuilder, SyncContext,
+};
 
 #[doc(hidden)]
 pub use crate::context::ContextFutureSpawner;
@@ -107,17 +126,20 @@ pub mod prelude {
     pub use actix_rt::{Arbiter, System, SystemRunner};
 
     pub use crate::actor::{
-        Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+        Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, StopReason,
+        Supervised,
     };
     pub use crate::address::{
-        Addr, MailboxError, Recipient, RecipientRequest, Request, SendError,
+        ActorId, Addr, MailboxError, Recipient, RecipientRequest, Request, SendError,
+        Terminated, WatchHandle, Watchable,
     };
     pub use crate::context::{Context, ContextFutureSpawner};
-    pub use crate::fut::{ActorFuture, ActorStream, WrapFuture, WrapStream};
+    pub use crate::fut::{ActorFuture, ActorScope, ActorStream, WrapFuture, WrapStream};
     pub use crate::handler::{
-        ActorResponse, AtomicResponse, Handler, Message, MessageResult, Response,
-        ResponseActFuture, ResponseFuture,
+        ActorResponse, AtomicResponse, Handler, Message, MessageResult, Priority,
+        Response, ResponseActFuture, ResponseFuture,
     };
+    pub use crate::mailbox::OverflowPolicy;
     pub use crate::registry::{ArbiterService, SystemService};
     pub use crate::stream::StreamHandler;
     pub use crate::supervisor::Supervisor;
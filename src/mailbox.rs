@@ -1,16 +1,46 @@
+use std::any::type_name;
 use std::task::Poll;
 use std::{fmt, task};
 
 use futures_util::stream::StreamExt;
 
-use crate::actor::{Actor, AsyncContext};
-use crate::address::EnvelopeProxy;
+use crate::actor::{Actor, ActorContext, ActorState, AsyncContext, StopReason};
 use crate::address::{channel, Addr, AddressReceiver, AddressSenderProducer};
+use crate::address::{ActorId, Envelope, EnvelopeProxy};
+use crate::clock::{self, Instant};
+use crate::metrics;
 
 #[cfg(feature = "mailbox_assert")]
 /// Maximum number of consecutive polls in a loop
 const MAX_SYNC_POLLS: u16 = 256;
 
+/// Behavior of a bounded mailbox once it is full.
+///
+/// Messages which did not make it into the mailbox are counted, see
+/// `Context::dropped_messages()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Senders are parked until there is space in the mailbox. `do_send`
+    /// ignores mailbox capacity.
+    Backpressure,
+    /// Oldest message in the mailbox is dropped to make space for the new one.
+    /// Requests waiting for response of the dropped message fail with
+    /// `MailboxError::Closed`.
+    DropOldest,
+    /// New message is dropped, `try_send` and `do_send` report success.
+    /// Request fails with `MailboxError::Full`.
+    DropNewest,
+    /// New message is rejected, `try_send` and `do_send` return
+    /// `SendError::Full`. Request fails with `MailboxError::Full`.
+    Reject,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::Backpressure
+    }
+}
+
 /// Default address channel capacity
 pub const DEFAULT_CAPACITY: usize = 16;
 
@@ -64,6 +94,21 @@ where
         self.msgs.set_capacity(cap);
     }
 
+    /// Returns identifier of the actor.
+    pub fn id(&self) -> ActorId {
+        self.msgs.id()
+    }
+
+    /// Returns the number of messages in the mailbox.
+    pub fn len(&self) -> usize {
+        self.msgs.len()
+    }
+
+    /// Returns `true` if there are no messages in the mailbox.
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+
     #[inline]
     pub fn connected(&self) -> bool {
         self.msgs.connected()
@@ -77,6 +122,10 @@ where
         self.msgs.sender_producer()
     }
 
+    pub(crate) fn set_stop_reason(&mut self, reason: StopReason) {
+        self.msgs.set_stop_reason(reason)
+    }
+
     pub fn poll(
         &mut self,
         act: &mut A,
@@ -91,14 +140,15 @@ where
 
             // sync messages
             loop {
-                if ctx.waiting() {
+                // terminated actor does not handle messages anymore
+                if ctx.waiting() || ctx.state() == ActorState::Stopped {
                     return;
                 }
 
                 match self.msgs.poll_next_unpin(task) {
                     Poll::Ready(Some(mut msg)) => {
                         not_ready = false;
-                        msg.handle(act, ctx);
+                        self.handle(&mut msg, act, ctx);
                     }
                     Poll::Ready(None) | Poll::Pending => break,
                 }
@@ -115,4 +165,31 @@ where
             }
         }
     }
+
+    /// Handle message taken from the mailbox or replayed from the stash,
+    /// reports metrics of the handled message.
+    pub(crate) fn handle(
+        &self,
+        msg: &mut Envelope<A>,
+        act: &mut A,
+        ctx: &mut A::Context,
+    ) {
+        if metrics::enabled() {
+            let start = clock::now();
+            msg.handle(act, ctx);
+            self.report(msg, start);
+        } else {
+            msg.handle(act, ctx);
+        }
+    }
+
+    fn report(&self, msg: &Envelope<A>, start: Instant) {
+        let duration = clock::now().saturating_duration_since(start);
+        let id = self.msgs.id();
+        let actor = type_name::<A>();
+        metrics::report(|sink| {
+            sink.message_handled(id, actor, msg.message_type(), msg.elapsed(), duration);
+            sink.queue_length(id, actor, self.msgs.len());
+        });
+    }
 }
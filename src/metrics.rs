@@ -0,0 +1,433 @@
+//! Actor and mailbox metrics
+//!
+//! Metrics are reported to a [`MetricsSink`](trait.MetricsSink.html)
+//! installed with [`set_sink()`](fn.set_sink.html). Until a sink is
+//! installed metrics are not collected.
+//!
+//! [`InMemoryMetrics`](struct.InMemoryMetrics.html) aggregates metrics in
+//! memory and renders them in Prometheus text exposition format. Rendered
+//! series are aggregated by actor type, state of individual actors is
+//! available from [`introspection`](../introspection/index.html).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use actix::metrics::{self, InMemoryMetrics};
+//!
+//! let metrics = Arc::new(InMemoryMetrics::new());
+//! metrics::set_sink(metrics.clone());
+//!
+//! // ... run actors
+//!
+//! println!("{}", metrics.render_prometheus());
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+
+use crate::address::ActorId;
+use crate::utils::escape;
+
+/// Receiver of actor metrics.
+///
+/// `actor` is type name of the actor, `message` is type name of the
+/// message. All methods have empty default implementation.
+#[allow(unused_variables)]
+pub trait MetricsSink: Send + Sync {
+    /// Number of messages waiting in actor's mailbox.
+    fn queue_length(&self, id: ActorId, actor: &'static str, len: usize) {}
+
+    /// Message got handled.
+    ///
+    /// `latency` is time between the message was sent and its handler got
+    /// called, it is `None` for messages sent before the sink got installed.
+    /// `duration` is time spent in the handler.
+    fn message_handled(
+        &self,
+        id: ActorId,
+        actor: &'static str,
+        message: &'static str,
+        latency: Option<Duration>,
+        duration: Duration,
+    ) {
+    }
+
+    /// Number of futures and streams spawned in actor's context.
+    fn spawned_futures(&self, id: ActorId, actor: &'static str, count: usize) {}
+
+    /// Actor got restarted by its supervisor, or panicked sync actor
+    /// worker got restarted.
+    fn restarted(&self, id: ActorId, actor: &'static str) {}
+
+    /// Actor's mailbox got dropped, actor will not report metrics anymore.
+    fn stopped(&self, id: ActorId, actor: &'static str) {}
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SINK: Lazy<RwLock<Option<Arc<dyn MetricsSink>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+/// Install metrics sink, replaces previously installed sink.
+pub fn set_sink(sink: Arc<dyn MetricsSink>) {
+    *SINK.write() = Some(sink);
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Remove installed metrics sink, metrics are not collected anymore.
+pub fn clear_sink() {
+    ENABLED.store(false, Ordering::Release);
+    *SINK.write() = None;
+}
+
+/// Is metrics sink installed
+#[inline]
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Call `f` with installed sink.
+pub(crate) fn report<F: FnOnce(&dyn MetricsSink)>(f: F) {
+    if let Some(ref sink) = *SINK.read() {
+        f(sink.as_ref())
+    }
+}
+
+/// Metrics of a single message type handled by an actor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageMetrics {
+    /// Number of handled messages.
+    pub handled: u64,
+    /// Number of messages with known latency.
+    pub latency_count: u64,
+    /// Total time messages spent in mailbox.
+    pub latency_sum: Duration,
+    /// Total time spent in handler.
+    pub duration_sum: Duration,
+    /// Longest time spent in handler.
+    pub duration_max: Duration,
+}
+
+/// Metrics of a single actor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActorMetrics {
+    /// Type name of the actor.
+    pub actor: &'static str,
+    /// Last reported number of messages in mailbox.
+    pub queue_length: usize,
+    /// Last reported number of spawned futures.
+    pub spawned_futures: usize,
+    /// Number of restarts.
+    pub restarts: u64,
+    /// Metrics per message type.
+    pub messages: BTreeMap<&'static str, MessageMetrics>,
+}
+
+impl ActorMetrics {
+    fn new(actor: &'static str) -> Self {
+        ActorMetrics {
+            actor,
+            queue_length: 0,
+            spawned_futures: 0,
+            restarts: 0,
+            messages: BTreeMap::new(),
+        }
+    }
+
+    /// Add gauges and counters of `other` to this metrics.
+    fn merge(&mut self, other: &ActorMetrics) {
+        self.queue_length += other.queue_length;
+        self.spawned_futures += other.spawned_futures;
+        self.restarts += other.restarts;
+        for (msg, mm) in &other.messages {
+            let m = self.messages.entry(msg).or_default();
+            m.handled += mm.handled;
+            m.latency_count += mm.latency_count;
+            m.latency_sum += mm.latency_sum;
+            m.duration_sum += mm.duration_sum;
+            m.duration_max = m.duration_max.max(mm.duration_max);
+        }
+    }
+}
+
+/// Metrics sink which aggregates metrics in memory.
+///
+/// Metrics of an actor are removed once the actor's mailbox is dropped,
+/// its counters are kept in rendered metrics of the actor type.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    actors: Mutex<HashMap<ActorId, ActorMetrics>>,
+    stopped: Mutex<BTreeMap<&'static str, ActorMetrics>>,
+}
+
+impl InMemoryMetrics {
+    /// Create empty metrics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns copy of collected metrics.
+    pub fn snapshot(&self) -> BTreeMap<ActorId, ActorMetrics> {
+        self.actors
+            .lock()
+            .iter()
+            .map(|(id, m)| (*id, m.clone()))
+            .collect()
+    }
+
+    /// Returns copy of collected metrics of an actor.
+    pub fn actor(&self, id: ActorId) -> Option<ActorMetrics> {
+        self.actors.lock().get(&id).cloned()
+    }
+
+    /// Forget all collected metrics.
+    pub fn reset(&self) {
+        self.actors.lock().clear();
+        self.stopped.lock().clear();
+    }
+
+    /// Render collected metrics in Prometheus text exposition format.
+    ///
+    /// Counters include messages handled and restarts of stopped actors.
+    pub fn render_prometheus(&self) -> String {
+        let mut types = aggregate(&self.snapshot());
+        for (actor, m) in self.stopped.lock().iter() {
+            types
+                .entry(actor)
+                .or_insert_with(|| ActorMetrics::new(actor))
+                .merge(m);
+        }
+        render(&types)
+    }
+
+    fn with_actor<F: FnOnce(&mut ActorMetrics)>(
+        &self,
+        id: ActorId,
+        actor: &'static str,
+        f: F,
+    ) {
+        f(self
+            .actors
+            .lock()
+            .entry(id)
+            .or_insert_with(|| ActorMetrics::new(actor)))
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn queue_length(&self, id: ActorId, actor: &'static str, len: usize) {
+        self.with_actor(id, actor, |m| m.queue_length = len)
+    }
+
+    fn message_handled(
+        &self,
+        id: ActorId,
+        actor: &'static str,
+        message: &'static str,
+        latency: Option<Duration>,
+        duration: Duration,
+    ) {
+        self.with_actor(id, actor, |m| {
+            let msg = m.messages.entry(message).or_default();
+            msg.handled += 1;
+            if let Some(latency) = latency {
+                msg.latency_count += 1;
+                msg.latency_sum += latency;
+            }
+            msg.duration_sum += duration;
+            msg.duration_max = msg.duration_max.max(duration);
+        })
+    }
+
+    fn spawned_futures(&self, id: ActorId, actor: &'static str, count: usize) {
+        self.with_actor(id, actor, |m| m.spawned_futures = count)
+    }
+
+    fn restarted(&self, id: ActorId, actor: &'static str) {
+        self.with_actor(id, actor, |m| m.restarts += 1)
+    }
+
+    fn stopped(&self, id: ActorId, actor: &'static str) {
+        if let Some(mut m) = self.actors.lock().remove(&id) {
+            // gauges of stopped actor do not contribute to its type
+            m.queue_length = 0;
+            m.spawned_futures = 0;
+            self.stopped
+                .lock()
+                .entry(actor)
+                .or_insert_with(|| ActorMetrics::new(actor))
+                .merge(&m);
+        }
+    }
+}
+
+/// Render metrics in Prometheus text exposition format.
+///
+/// Metrics of actors of the same type are summed up, series are labeled
+/// with type name of the actor only.
+pub fn render_prometheus(metrics: &BTreeMap<ActorId, ActorMetrics>) -> String {
+    render(&aggregate(metrics))
+}
+
+/// Sum up metrics per actor type.
+fn aggregate(
+    metrics: &BTreeMap<ActorId, ActorMetrics>,
+) -> BTreeMap<&'static str, ActorMetrics> {
+    let mut types = BTreeMap::new();
+    for m in metrics.values() {
+        types
+            .entry(m.actor)
+            .or_insert_with(|| ActorMetrics::new(m.actor))
+            .merge(m);
+    }
+    types
+}
+
+fn render(metrics: &BTreeMap<&'static str, ActorMetrics>) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "actix_mailbox_queue_length",
+        "gauge",
+        "Number of messages in actor's mailbox.",
+    );
+    for m in metrics.values() {
+        let _ = writeln!(
+            out,
+            "actix_mailbox_queue_length{{{}}} {}",
+            labels(m.actor),
+            m.queue_length
+        );
+    }
+
+    header(
+        &mut out,
+        "actix_spawned_futures",
+        "gauge",
+        "Number of futures spawned in actor's context.",
+    );
+    for m in metrics.values() {
+        let _ = writeln!(
+            out,
+            "actix_spawned_futures{{{}}} {}",
+            labels(m.actor),
+            m.spawned_futures
+        );
+    }
+
+    header(
+        &mut out,
+        "actix_actor_restarts_total",
+        "counter",
+        "Number of actor restarts.",
+    );
+    for m in metrics.values() {
+        let _ = writeln!(
+            out,
+            "actix_actor_restarts_total{{{}}} {}",
+            labels(m.actor),
+            m.restarts
+        );
+    }
+
+    header(
+        &mut out,
+        "actix_messages_handled_total",
+        "counter",
+        "Number of handled messages.",
+    );
+    for m in metrics.values() {
+        for (msg, mm) in &m.messages {
+            let _ = writeln!(
+                out,
+                "actix_messages_handled_total{{{}}} {}",
+                message_labels(m.actor, msg),
+                mm.handled
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "actix_message_latency_seconds",
+        "summary",
+        "Time between message was sent and its handler got called.",
+    );
+    for m in metrics.values() {
+        for (msg, mm) in &m.messages {
+            let labels = message_labels(m.actor, msg);
+            let _ = writeln!(
+                out,
+                "actix_message_latency_seconds_sum{{{}}} {}",
+                labels,
+                mm.latency_sum.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "actix_message_latency_seconds_count{{{}}} {}",
+                labels, mm.latency_count
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "actix_handler_duration_seconds",
+        "summary",
+        "Time spent in message handler.",
+    );
+    for m in metrics.values() {
+        for (msg, mm) in &m.messages {
+            let labels = message_labels(m.actor, msg);
+            let _ = writeln!(
+                out,
+                "actix_handler_duration_seconds_sum{{{}}} {}",
+                labels,
+                mm.duration_sum.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "actix_handler_duration_seconds_count{{{}}} {}",
+                labels, mm.handled
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "actix_handler_duration_seconds_max",
+        "gauge",
+        "Longest time spent in message handler.",
+    );
+    for m in metrics.values() {
+        for (msg, mm) in &m.messages {
+            let _ = writeln!(
+                out,
+                "actix_handler_duration_seconds_max{{{}}} {}",
+                message_labels(m.actor, msg),
+                mm.duration_max.as_secs_f64()
+            );
+        }
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn message_labels(actor: &str, message: &str) -> String {
+    format!("{},message=\"{}\"", labels(actor), escape(message))
+}
+
+fn labels(actor: &str) -> String {
+    format!("actor=\"{}\"", escape(actor))
+}
@@ -1,14 +1,22 @@
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::pin::Pin;
 use std::task::{self, Poll};
+use std::time::Duration;
 
 use actix_rt::Arbiter;
 use futures_util::future::Future;
+use log::warn;
 use pin_project::pin_project;
 
-use crate::actor::{Actor, AsyncContext, Supervised};
-use crate::address::{channel, Addr};
+use crate::actor::{Actor, ActorContext, AsyncContext, StopReason, Supervised};
+use crate::address::{channel, Addr, Recipient};
+use crate::clock::{self, Delay, Instant};
 use crate::context::Context;
 use crate::contextimpl::ContextFut;
+use crate::handler::Message;
 use crate::mailbox::DEFAULT_CAPACITY;
 
 /// Actor supervisor
@@ -26,6 +34,11 @@ use crate::mailbox::DEFAULT_CAPACITY;
 /// message. If actor fails during message processing, this message can not be
 /// recovered. Sender would receive `Err(Cancelled)` error in this situation.
 ///
+/// By default actor gets restarted immediately and without limits. Use
+/// [`SupervisorStrategy`](struct.SupervisorStrategy.html) with
+/// `Supervisor::start_with()` to limit number of restarts and to delay
+/// restarts of a failing actor.
+///
 /// ## Example
 ///
 /// ```rust
@@ -72,6 +85,8 @@ where
 {
     #[pin]
     fut: ContextFut<A, Context<A>>,
+    restarts: Restarts,
+    delay: Option<Delay>,
 }
 
 impl<A> Supervisor<A>
@@ -101,6 +116,40 @@ where
     /// # });}
     /// ```
     pub fn start<F>(f: F) -> Addr<A>
+    where
+        F: FnOnce(&mut A::Context) -> A + 'static,
+        A: Actor<Context = Context<A>>,
+    {
+        Self::start_with(SupervisorStrategy::default(), f)
+    }
+
+    /// Start new supervised actor in current tokio runtime, restarting
+    /// it according to the given strategy.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use actix::prelude::*;
+    /// use actix::SupervisorStrategy;
+    ///
+    /// struct MyActor;
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// # impl actix::Supervised for MyActor {}
+    /// # fn main() {
+    /// #    System::run(|| {
+    /// let strategy = SupervisorStrategy::new()
+    ///     .max_restarts(3, Duration::from_secs(10))
+    ///     .backoff(Duration::from_millis(10), Duration::from_secs(1))
+    ///     .on_escalate(|esc| println!("gave up after {} restarts", esc.restarts));
+    ///
+    /// let addr = actix::Supervisor::start_with(strategy, |_| MyActor);
+    /// #         System::current().stop();
+    /// # });}
+    /// ```
+    pub fn start_with<F>(strategy: SupervisorStrategy, f: F) -> Addr<A>
     where
         F: FnOnce(&mut A::Context) -> A + 'static,
         A: Actor<Context = Context<A>>,
@@ -112,13 +161,27 @@ where
         let fut = ctx.into_future(act);
 
         // create supervisor
-        actix_rt::spawn(Self { fut });
+        actix_rt::spawn(Self::new(fut, strategy));
 
         addr
     }
 
     /// Start new supervised actor in arbiter's thread.
     pub fn start_in_arbiter<F>(sys: &Arbiter, f: F) -> Addr<A>
+    where
+        A: Actor<Context = Context<A>>,
+        F: FnOnce(&mut Context<A>) -> A + Send + 'static,
+    {
+        Self::start_in_arbiter_with(sys, SupervisorStrategy::default(), f)
+    }
+
+    /// Start new supervised actor in arbiter's thread, restarting it
+    /// according to the given strategy.
+    pub fn start_in_arbiter_with<F>(
+        sys: &Arbiter,
+        strategy: SupervisorStrategy,
+        f: F,
+    ) -> Addr<A>
     where
         A: Actor<Context = Context<A>>,
         F: FnOnce(&mut Context<A>) -> A + Send + 'static,
@@ -130,11 +193,19 @@ where
             let act = f(&mut ctx);
             let fut = ctx.into_future(act);
 
-            actix_rt::spawn(Self { fut });
+            actix_rt::spawn(Self::new(fut, strategy));
         });
 
         Addr::new(tx)
     }
+
+    fn new(fut: ContextFut<A, Context<A>>, strategy: SupervisorStrategy) -> Self {
+        Supervisor {
+            fut,
+            restarts: Restarts::new(strategy),
+            delay: None,
+        }
+    }
 }
 
 #[doc(hidden)]
@@ -147,15 +218,253 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let mut this = self.project();
         loop {
+            // wait for restart backoff
+            if let Some(ref mut delay) = this.delay {
+                match Pin::new(delay).poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(_) => {
+                        *this.delay = None;
+                        // stop if context's address is not connected
+                        if !this.fut.restart() {
+                            return Poll::Ready(());
+                        }
+                    }
+                }
+            }
+
             match this.fut.as_mut().poll(cx) {
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(_) => {
                     // stop if context's address is not connected
-                    if !this.fut.restart() {
+                    if !this.fut.connected() {
                         return Poll::Ready(());
                     }
+
+                    let reason = this.fut.ctx().stop_reason().cloned();
+                    match this.restarts.failed(reason) {
+                        // restart budget is exhausted, stop actor permanently
+                        None => return Poll::Ready(()),
+                        Some(delay) if delay == Duration::from_secs(0) => {
+                            if !this.fut.restart() {
+                                return Poll::Ready(());
+                            }
+                        }
+                        Some(delay) => *this.delay = Some(clock::delay_for(delay)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Notification sent by a `Supervisor` once it gives up restarting
+/// a failing actor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escalation {
+    /// Number of restarts performed within the strategy time window.
+    pub restarts: usize,
+    /// The reason of the last failure.
+    pub reason: StopReason,
+}
+
+impl Message for Escalation {
+    type Result = ();
+}
+
+/// Restart policy of a `Supervisor`.
+///
+/// Default strategy restarts actor immediately and without limits.
+///
+/// Strategy can limit number of restarts within a time window and
+/// delay each restart with an exponential backoff. Once restart budget
+/// is exhausted, supervisor stops the actor permanently and notifies
+/// escalation handler.
+pub struct SupervisorStrategy {
+    max_restarts: usize,
+    within: Option<Duration>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+    escalate: Option<Box<dyn FnOnce(Escalation) + Send>>,
+}
+
+impl fmt::Debug for SupervisorStrategy {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SupervisorStrategy")
+            .field("max_restarts", &self.max_restarts)
+            .field("within", &self.within)
+            .field("min_backoff", &self.min_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
+
+impl Default for SupervisorStrategy {
+    fn default() -> Self {
+        SupervisorStrategy {
+            max_restarts: !0,
+            within: None,
+            min_backoff: Duration::from_secs(0),
+            max_backoff: Duration::from_secs(0),
+            jitter: 0.0,
+            escalate: None,
+        }
+    }
+}
+
+impl SupervisorStrategy {
+    /// Create strategy that restarts actor immediately and without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `max` restarts within `within` time window.
+    ///
+    /// Supervisor stops the actor permanently on next failure.
+    pub fn max_restarts(mut self, max: usize, within: Duration) -> Self {
+        self.max_restarts = max;
+        self.within = Some(within);
+        self
+    }
+
+    /// Delay restarts with exponential backoff.
+    ///
+    /// First restart within time window is delayed by `min`, every
+    /// following restart doubles the delay, up to `max`. Without time
+    /// window, delay is reset to `min` once the restarted actor runs for
+    /// at least `max`.
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = if max < min { min } else { max };
+        self
+    }
+
+    /// Randomize backoff delay by adding up to `factor` of its value.
+    ///
+    /// Factor is clamped to `0.0..=1.0` range. By default jitter is not
+    /// applied.
+    pub fn jitter(mut self, factor: f64) -> Self {
+        // NaN is treated as zero
+        self.jitter = if factor > 1.0 {
+            1.0
+        } else if factor > 0.0 {
+            factor
+        } else {
+            0.0
+        };
+        self
+    }
+
+    /// Call `f` once supervisor gives up restarting the actor.
+    pub fn on_escalate<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Escalation) + Send + 'static,
+    {
+        self.escalate = Some(Box::new(f));
+        self
+    }
+
+    /// Send `Escalation` message to `rcp` once supervisor gives up
+    /// restarting the actor.
+    pub fn escalate_to(self, rcp: Recipient<Escalation>) -> Self {
+        self.on_escalate(move |esc| rcp.do_send(esc).unwrap_or(()))
+    }
+
+    /// Backoff delay for a restart, `n` is number of previous restarts
+    /// within time window.
+    fn delay(&self, n: usize) -> Duration {
+        if self.min_backoff == Duration::from_secs(0) {
+            return Duration::from_secs(0);
+        }
+
+        let delay = 1u32
+            .checked_shl(n as u32)
+            .and_then(|factor| self.min_backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff));
+
+        if self.jitter > 0.0 {
+            delay + delay.mul_f64(self.jitter * random_fraction())
+        } else {
+            delay
+        }
+    }
+
+    fn escalate(&mut self, restarts: usize, reason: StopReason) {
+        if let Some(f) = self.escalate.take() {
+            f(Escalation { restarts, reason })
+        }
+    }
+}
+
+/// Restart history of a supervised actor or group of actors.
+#[derive(Debug)]
+pub(crate) struct Restarts {
+    strategy: SupervisorStrategy,
+    history: VecDeque<Instant>,
+    /// Time of the last restart.
+    restarted: Option<Instant>,
+}
+
+impl Restarts {
+    pub(crate) fn new(strategy: SupervisorStrategy) -> Self {
+        Restarts {
+            strategy,
+            history: VecDeque::new(),
+            restarted: None,
+        }
+    }
+
+    /// Forget restart history, restart budget is full again.
+    pub(crate) fn reset(&mut self) {
+        self.history.clear();
+        self.restarted = None;
+    }
+
+    /// Register failure, returns delay before restart or `None` if
+    /// restart budget is exhausted. Escalation handler is called in
+    /// latter case.
+    pub(crate) fn failed(&mut self, reason: Option<StopReason>) -> Option<Duration> {
+        let now = Instant::now();
+        if let Some(within) = self.strategy.within {
+            while let Some(ts) = self.history.front() {
+                if now.duration_since(*ts) >= within {
+                    self.history.pop_front();
+                } else {
+                    break;
                 }
             }
+        } else if let Some(restarted) = self.restarted {
+            // reset backoff after a healthy run
+            if now.saturating_duration_since(restarted) >= self.strategy.max_backoff {
+                self.history.clear();
+            }
         }
+
+        if self.history.len() >= self.strategy.max_restarts {
+            warn!(
+                "Supervised actor failed {} times, stop restarting",
+                self.history.len() + 1
+            );
+            self.strategy
+                .escalate(self.history.len(), reason.unwrap_or(StopReason::Normal));
+            return None;
+        }
+
+        let delay = self.strategy.delay(self.history.len());
+        // without time window restarts are never expired,
+        // only track enough of them to saturate backoff
+        if self.strategy.within.is_some() || self.history.len() < 32 {
+            self.history.push_back(now);
+        }
+        self.restarted = Some(now + delay);
+        Some(delay)
     }
 }
+
+/// Random number in `0.0..1.0` range.
+fn random_fraction() -> f64 {
+    // every `RandomState` gets new random keys
+    let hasher = RandomState::new().build_hasher();
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -5,27 +5,49 @@
 //! a single Sync Actor type on a `SyncArbiter`. This means you can't have
 //! Actor type A and B, sharing the same thread pool. You need to create two
 //! SyncArbiters and have A and B spawn on unique `SyncArbiter`s respectively.
+//! Alternatively, SyncArbiters of A and B can share the blocking thread pool
+//! of the System, see `SyncArbiterBuilder::blocking_pool()`.
 //! For more information and examples, see `SyncArbiter`
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::Poll;
-use std::{task, thread};
+use std::time::Duration;
+use std::{fmt, task, thread};
+use std::{iter, mem};
 
 use actix_rt::System;
-use crossbeam_channel as cb_channel;
+use crossbeam_channel::{
+    self as cb_channel, RecvTimeoutError, SendTimeoutError, TrySendError,
+};
 use futures_channel::oneshot::Sender as SyncSender;
-use futures_util::{future::Future, stream::StreamExt};
-use log::warn;
-use pin_project::pin_project;
+use futures_util::future::{Future, FutureExt};
+use futures_util::stream::StreamExt;
+use futures_util::task::{noop_waker, AtomicWaker};
+use log::{error, warn};
+use once_cell::sync::OnceCell;
+use parking_lot::{Condvar, Mutex};
+use pin_project::{pin_project, pinned_drop};
+use tokio::runtime::Handle;
 
-use crate::actor::{Actor, ActorContext, ActorState, Running};
+use crate::actor::{Actor, ActorContext, ActorState, Running, SpawnHandle, StopReason};
 use crate::address::channel;
+use crate::address::envelope::{handle_request, Failure, Responder};
 use crate::address::{
     Addr, AddressReceiver, AddressSenderProducer, Envelope, EnvelopeProxy, ToEnvelope,
 };
+use crate::clock::{self, Instant};
 use crate::context::Context;
-use crate::handler::{Handler, Message, MessageResponse};
+use crate::handler::{Handler, Message};
+use crate::introspection::{Tracked, TrackedPool};
+use crate::metrics;
+use crate::supervisor::{Restarts, SupervisorStrategy};
 
 /// SyncArbiter provides the resources for a single Sync Actor to run on a dedicated
 /// thread or threads. This is generally used for CPU bound concurrent workloads. It's
@@ -36,6 +58,9 @@ use crate::handler::{Handler, Message, MessageResponse};
 /// Sync Actors have a different lifecycle compared to Actors on the System
 /// Arbiter. For more, see `SyncContext`.
 ///
+/// Once the System stops, the SyncArbiter stops its workers and waits for
+/// their threads to exit, see `SyncArbiterBuilder::shutdown_policy()`.
+///
 /// ## Example
 ///
 /// ```rust
@@ -94,15 +119,24 @@ use crate::handler::{Handler, Message, MessageResponse};
 ///     });
 /// }
 /// ```
-#[pin_project]
+#[pin_project(PinnedDrop)]
 pub struct SyncArbiter<A>
 where
     A: Actor<Context = SyncContext<A>>,
 {
-    queue: Option<cb_channel::Sender<Envelope<A>>>,
+    queue: Option<Queues<A>>,
     msgs: AddressReceiver<A>,
+    pending: Option<Envelope<A>>,
+    shared: Arc<Shared<A>>,
+    /// Runtime of the arbiter, it waits for workers on shutdown.
+    handle: Option<Handle>,
+    /// Blocking thread pool of the `System`, until its arbiter reports it.
+    lookup: Option<Lookup>,
+    tracked: Option<TrackedPool>,
 }
 
+type Lookup = Pin<Box<dyn Future<Output = Option<Handle>>>>;
+
 impl<A> SyncArbiter<A>
 where
     A: Actor<Context = SyncContext<A>>,
@@ -115,31 +149,1075 @@ where
     where
         F: Fn() -> A + Send + Sync + 'static,
     {
-        let factory = Arc::new(factory);
-        let (sender, receiver) = cb_channel::unbounded();
-        let (tx, rx) = channel::channel(0);
-
-        for _ in 0..threads {
-            let f = Arc::clone(&factory);
-            let sys = System::current();
-            let actor_queue = receiver.clone();
-            let inner_rx = rx.sender_producer();
-
-            thread::spawn(move || {
-                System::set_current(sys);
-                SyncContext::new(f, actor_queue, inner_rx).run();
-            });
+        Self::builder(factory).threads(threads).start()
+    }
+
+    /// Create builder of `SyncArbiter` with bounded queue and elastic
+    /// number of worker threads.
+    pub fn builder<F>(factory: F) -> SyncArbiterBuilder<A>
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        SyncArbiterBuilder {
+            factory: Arc::new(factory),
+            min_threads: 1,
+            max_threads: 1,
+            capacity: None,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            thread_name: None,
+            stack_size: None,
+            shutdown_policy: ShutdownPolicy::Abandon,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            affinity: HashMap::new(),
+            blocking: None,
+            strategy: SupervisorStrategy::new()
+                .backoff(DEFAULT_MIN_BACKOFF, DEFAULT_MAX_BACKOFF),
+        }
+    }
+
+    /// Start worker thread.
+    fn spawn_worker(shared: Arc<Shared<A>>, idx: usize) {
+        *shared.threads.lock() += 1;
+        let worker = Worker { shared, idx };
+
+        let mut builder = thread::Builder::new();
+        if let Some(ref name) = worker.shared.thread_name {
+            builder = builder.name(format!("{}-{}", name, idx));
+        }
+        if let Some(size) = worker.shared.stack_size {
+            builder = builder.stack_size(size);
+        }
+        builder
+            .spawn(move || worker.run())
+            .expect("Can not spawn sync actor worker thread");
+    }
+}
+
+/// Worker thread of the pool.
+struct Worker<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    shared: Arc<Shared<A>>,
+    idx: usize,
+}
+
+impl<A> Worker<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn run(self) {
+        System::set_current(self.shared.sys.clone());
+
+        #[cfg(feature = "tracing")]
+        let _enter =
+            tracing::debug_span!("sync_worker", actor = std::any::type_name::<A>())
+                .entered();
+
+        loop {
+            let mut ctx = None;
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                ctx.get_or_insert_with(|| {
+                    SyncContext::new(Arc::clone(&self.shared), self.idx)
+                })
+                .run()
+            }));
+
+            let err = match res {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+            // worker which already left the pool is not restarted
+            if ctx.as_ref().map_or(false, |ctx| ctx.retired) {
+                return;
+            }
+            if !self.restart(StopReason::from_panic(&*err)) {
+                self.shared.workers.fetch_sub(1, Ordering::SeqCst);
+                if self.idx < self.shared.slots.len() {
+                    self.shared.close_slot(self.idx);
+                }
+                return;
+            }
+        }
+    }
+
+    /// Handle a message on the blocking pool. The actor waits for the next
+    /// message parked, without a thread.
+    fn handle(self, env: Envelope<A>) {
+        System::set_current(self.shared.sys.clone());
+
+        #[cfg(feature = "tracing")]
+        let _enter =
+            tracing::debug_span!("sync_worker", actor = std::any::type_name::<A>())
+                .entered();
+
+        let mut ctx = None;
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            ctx.get_or_insert_with(|| SyncContext::resume(Arc::clone(&self.shared)))
+                .run_blocking(env)
+        }));
+
+        match (res, ctx) {
+            (Ok(()), Some(ctx)) if !ctx.retired => ctx.park(),
+            (Ok(()), _) => (),
+            (Err(err), ctx) => {
+                // the actor is lost, next message starts a new one
+                if !ctx.as_ref().map_or(false, |ctx| ctx.retired) {
+                    self.shared.workers.fetch_sub(1, Ordering::SeqCst);
+                }
+                if !self.restart(StopReason::from_panic(&*err)) {
+                    if let Some(ref blocking) = self.shared.blocking {
+                        blocking.lost.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for the backoff delay of the supervisor strategy. Returns
+    /// `false` if the worker is not restarted.
+    fn restart(&self, reason: StopReason) -> bool {
+        if self.shared.shutdown.load(Ordering::SeqCst) {
+            error!("Sync actor worker panicked during shutdown: {:?}", reason);
+            return false;
+        }
+
+        let delay = self.shared.restarts.lock().failed(Some(reason.clone()));
+        match delay {
+            Some(delay) => {
+                error!(
+                    "Sync actor worker panicked, restarting in {:?}: {:?}",
+                    delay, reason
+                );
+                thread::sleep(delay);
+                if self.shared.shutdown.load(Ordering::SeqCst) {
+                    return false;
+                }
+                if metrics::enabled() {
+                    let id = self.shared.address.id();
+                    let actor = std::any::type_name::<A>();
+                    metrics::report(|sink| sink.restarted(id, actor));
+                }
+                true
+            }
+            None => {
+                error!("Sync actor worker panicked, not restarting: {:?}", reason);
+                false
+            }
+        }
+    }
+}
+
+impl<A> Drop for Worker<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Worker exits, or it is dropped by the blocking pool which is shut
+    /// down.
+    fn drop(&mut self) {
+        *self.shared.threads.lock() -= 1;
+        self.shared.exited.notify_all();
+        self.shared.space.wake();
+    }
+}
+
+/// Default time after which idle worker thread above the minimum exits.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default time to wait for worker threads on shutdown.
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default backoff of restarts of panicked worker threads.
+const DEFAULT_MIN_BACKOFF: Duration = Duration::from_millis(10);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// What happens with queued messages once the `System` or arbiter running
+/// the `SyncArbiter` stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownPolicy {
+    /// Workers handle queued messages before they stop.
+    Drain,
+    /// Queued messages are dropped, workers stop once they finish the
+    /// message they are handling. Senders waiting for responses get
+    /// `MailboxError::Closed`.
+    Abandon,
+}
+
+/// Message which is routed to a worker by its key.
+///
+/// Once the message type is registered with
+/// `SyncArbiterBuilder::affinity()`, messages with equal keys are handled
+/// by the same worker in the order they are sent.
+pub trait Affinity: Message {
+    /// Type of the key.
+    type Key: Hash;
+
+    /// Returns key of the message.
+    fn affinity_key(&self) -> Self::Key;
+}
+
+/// Hash of the affinity key of a message.
+type KeyFn = Box<dyn Fn(&dyn Any) -> Option<u64> + Send + Sync>;
+
+/// Builder of `SyncArbiter`.
+///
+/// By default the pool has a single worker thread and an unbounded queue.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use actix::prelude::*;
+///
+/// struct DbExecutor;
+///
+/// impl Actor for DbExecutor {
+///     type Context = SyncContext<Self>;
+/// }
+///
+/// System::run(|| {
+///     let addr = SyncArbiter::builder(|| DbExecutor)
+///         .min_threads(2)
+///         .max_threads(8)
+///         .queue_capacity(64)
+///         .idle_timeout(Duration::from_secs(30))
+///         .thread_name("db")
+///         .start();
+/// #   System::current().stop();
+/// })
+/// .unwrap();
+/// ```
+pub struct SyncArbiterBuilder<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    factory: Arc<dyn Fn() -> A + Send + Sync>,
+    min_threads: usize,
+    max_threads: usize,
+    capacity: Option<usize>,
+    idle_timeout: Duration,
+    thread_name: Option<String>,
+    stack_size: Option<usize>,
+    shutdown_policy: ShutdownPolicy,
+    shutdown_timeout: Duration,
+    affinity: HashMap<TypeId, KeyFn>,
+    blocking: Option<ParkFn<A>>,
+    strategy: SupervisorStrategy,
+}
+
+impl<A> fmt::Debug for SyncArbiterBuilder<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SyncArbiterBuilder")
+            .field("min_threads", &self.min_threads)
+            .field("max_threads", &self.max_threads)
+            .field("capacity", &self.capacity)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("thread_name", &self.thread_name)
+            .field("stack_size", &self.stack_size)
+            .field("shutdown_policy", &self.shutdown_policy)
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("affinity", &self.affinity.len())
+            .field("blocking", &self.blocking.is_some())
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+impl<A> SyncArbiterBuilder<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Set fixed number of worker threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.min_threads = threads;
+        self.max_threads = threads;
+        self
+    }
+
+    /// Set number of worker threads which are started with the arbiter
+    /// and never exit because of idleness.
+    pub fn min_threads(mut self, threads: usize) -> Self {
+        self.min_threads = threads;
+        self.max_threads = self.max_threads.max(threads);
+        self
+    }
+
+    /// Set maximum number of worker threads. Worker threads are added
+    /// while there are more queued messages than idle workers.
+    pub fn max_threads(mut self, threads: usize) -> Self {
+        self.max_threads = threads;
+        self.min_threads = self.min_threads.min(threads);
+        self
+    }
+
+    /// Bound the queue of messages.
+    ///
+    /// Once the queue is full, messages are kept in the address mailbox of
+    /// the same capacity, so `Addr::send()` waits and `Addr::try_send()`
+    /// fails until workers catch up. `Addr::do_send()` ignores capacity.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Set time after which idle worker thread above the minimum exits,
+    /// default is 60 seconds. Worker with pending timers does not exit.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Set name prefix of worker threads, threads are named
+    /// `{name}-{index}`.
+    pub fn thread_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.thread_name = Some(name.into());
+        self
+    }
+
+    /// Set stack size of worker threads.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Set what happens with queued messages on shutdown, default is
+    /// `ShutdownPolicy::Abandon`.
+    ///
+    /// With `ShutdownPolicy::Drain` set `shutdown_timeout()` long enough
+    /// for the queued messages to be handled.
+    pub fn shutdown_policy(mut self, policy: ShutdownPolicy) -> Self {
+        self.shutdown_policy = policy;
+        self
+    }
+
+    /// Set time to wait for worker threads on shutdown, default is 1
+    /// second.
+    ///
+    /// Once the `System` or arbiter running the `SyncArbiter` stops, new
+    /// messages are rejected and shutdown of the arbiter's runtime waits
+    /// until all workers call `stopping` and `stopped` and exit, or until
+    /// the timeout elapses.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Route messages of type `M` to workers by their affinity key.
+    ///
+    /// Messages with equal keys are handled by the same worker in the
+    /// order they are sent, messages of other types are handled by any
+    /// worker. Once a message type is registered, the pool has fixed
+    /// number of `max_threads` worker threads. Affinity is not supported
+    /// on the blocking pool.
+    ///
+    /// If a worker panics and the supervisor strategy does not restart it,
+    /// requests routed to the worker fail with `MailboxError::Closed`.
+    pub fn affinity<M>(mut self) -> Self
+    where
+        A: Handler<M>,
+        M: Affinity + Send + 'static,
+        M::Result: Send,
+    {
+        let key: KeyFn = Box::new(|msg| {
+            let mut hasher = DefaultHasher::new();
+            msg.downcast_ref::<M>()?.affinity_key().hash(&mut hasher);
+            Some(hasher.finish())
+        });
+        self.affinity.insert(TypeId::of::<M>(), key);
+        self
+    }
+
+    /// Run workers on the blocking thread pool of the `System`'s arbiter
+    /// instead of dedicated threads.
+    ///
+    /// The pool is shared by sync actors of all types started with this
+    /// option. Each message is handled by a task of the pool, at most
+    /// `max_threads` of them run at once. Actors are created on demand and
+    /// wait for messages without holding a thread, idle actors above
+    /// `min_threads` are stopped after `idle_timeout`. Worker holds its
+    /// thread while the actor has pending timers.
+    ///
+    /// Actors move between threads of the pool, so they have to be `Send`.
+    /// Thread name and stack size are not used.
+    ///
+    /// # Panics
+    ///
+    /// `start()` panics if message affinity is used as well.
+    pub fn blocking_pool(mut self) -> Self
+    where
+        A: Send,
+    {
+        self.blocking = Some(park::<A>);
+        self
+    }
+
+    /// Set how worker threads are restarted once they panic outside of
+    /// message handler, e.g. in actor's factory or `started` method.
+    ///
+    /// By default restarts are not limited and delayed by backoff from
+    /// 10 milliseconds up to 1 second. Worker which is not restarted
+    /// leaves the pool, workers are never restarted during shutdown.
+    pub fn supervisor_strategy(mut self, strategy: SupervisorStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Start the `SyncArbiter` and return address of the pool.
+    pub fn start(mut self) -> Addr<A> {
+        if self.blocking.is_some() {
+            assert!(
+                self.affinity.is_empty(),
+                "Message affinity is not supported on the blocking pool"
+            );
+            if self.thread_name.is_some() || self.stack_size.is_some() {
+                warn!("Thread name and stack size are not used on the blocking pool");
+            }
         }
 
-        actix_rt::spawn(Self {
-            queue: Some(sender),
+        let (sender, receiver) = queue(self.capacity);
+        let (tx, rx) = channel::channel(self.capacity.unwrap_or(0));
+
+        // workers with own queues are never added or removed
+        let slots = if self.affinity.is_empty() {
+            0
+        } else {
+            self.min_threads = self.max_threads;
+            self.max_threads
+        };
+        let (slot_senders, slot_receivers) =
+            (0..slots).map(|_| queue(self.capacity)).unzip();
+
+        // actors on the blocking pool are created on demand
+        let blocking = self.blocking.map(|park| Blocking {
+            handle: OnceCell::new(),
+            parked: Mutex::new(VecDeque::new()),
+            park,
+            pruning: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            lost: AtomicUsize::new(0),
+        });
+        let workers = if blocking.is_some() {
+            0
+        } else {
+            self.min_threads
+        };
+
+        let shared = Arc::new(Shared {
+            factory: self.factory,
+            queue: receiver,
+            closed_slots: (0..slots).map(|_| AtomicBool::new(false)).collect(),
+            slots: slot_receivers,
+            affinity: self.affinity,
+            address: rx.sender_producer(),
+            sys: System::current(),
+            min_threads: self.min_threads,
+            max_threads: self.max_threads,
+            idle_timeout: self.idle_timeout,
+            thread_name: self.thread_name,
+            stack_size: self.stack_size,
+            blocking,
+            shutdown_policy: self.shutdown_policy,
+            shutdown_timeout: self.shutdown_timeout,
+            shutdown: AtomicBool::new(false),
+            restarts: Mutex::new(Restarts::new(self.strategy)),
+            threads: Mutex::new(0),
+            exited: Condvar::new(),
+            workers: AtomicUsize::new(workers),
+            idle: AtomicUsize::new(0),
+            spawned: AtomicUsize::new(workers),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            space: AtomicWaker::new(),
+        });
+
+        for idx in 0..workers {
+            SyncArbiter::spawn_worker(Arc::clone(&shared), idx);
+        }
+
+        let lookup = shared.blocking.as_ref().map(|_| -> Lookup {
+            let handle = System::current().arbiter().exec(Handle::current);
+            Box::pin(handle.map(Result::ok))
+        });
+
+        let pool = Arc::clone(&shared);
+        let tracked = TrackedPool::register(
+            shared.address.id(),
+            std::any::type_name::<A>(),
+            move || pool.address.len() + pool.in_flight.load(Ordering::SeqCst),
+        );
+
+        actix_rt::spawn(SyncArbiter {
+            queue: Some(Queues {
+                shared: sender,
+                slots: slot_senders,
+            }),
             msgs: rx,
+            pending: None,
+            shared,
+            handle: Handle::try_current().ok(),
+            lookup,
+            tracked,
         });
 
         Addr::new(tx)
     }
 }
 
+/// Moves actor to the parked actors of the blocking pool.
+type ParkFn<A> = fn(A) -> Box<dyn Any + Send>;
+
+fn park<A: Send + 'static>(act: A) -> Box<dyn Any + Send> {
+    Box::new(act)
+}
+
+fn queue<T>(
+    capacity: Option<usize>,
+) -> (cb_channel::Sender<T>, cb_channel::Receiver<T>) {
+    match capacity {
+        Some(cap) => cb_channel::bounded(cap),
+        None => cb_channel::unbounded(),
+    }
+}
+
+/// Senders of the pool's queue and of queues of individual workers.
+struct Queues<A: Actor> {
+    shared: cb_channel::Sender<Envelope<A>>,
+    slots: Vec<cb_channel::Sender<Envelope<A>>>,
+}
+
+impl<A> Queues<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Index of the worker which handles message with affinity key.
+    fn route(&self, shared: &Shared<A>, env: &Envelope<A>) -> Option<usize> {
+        let hash = env
+            .message()
+            .and_then(|msg| shared.affinity.get(&msg.type_id())?(msg))?;
+        Some(jump_hash(hash, self.slots.len()))
+    }
+
+    /// Queue of the worker `slot`, or the pool's queue.
+    fn queue(&self, slot: Option<usize>) -> &cb_channel::Sender<Envelope<A>> {
+        match slot {
+            Some(idx) => &self.slots[idx],
+            None => &self.shared,
+        }
+    }
+}
+
+/// Jump consistent hash of `key` over `buckets`.
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets as i64 {
+        b = j;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as usize
+}
+
+/// State of the pool shared by the arbiter and its workers.
+struct Shared<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    factory: Arc<dyn Fn() -> A + Send + Sync>,
+    queue: cb_channel::Receiver<Envelope<A>>,
+    /// Queues of individual workers, used for messages with affinity key.
+    slots: Vec<cb_channel::Receiver<Envelope<A>>>,
+    /// Set once worker of the slot is not restarted anymore.
+    closed_slots: Vec<AtomicBool>,
+    affinity: HashMap<TypeId, KeyFn>,
+    address: AddressSenderProducer<A>,
+    sys: System,
+    min_threads: usize,
+    max_threads: usize,
+    idle_timeout: Duration,
+    thread_name: Option<String>,
+    stack_size: Option<usize>,
+    /// Blocking thread pool which runs workers instead of dedicated threads.
+    blocking: Option<Blocking<A>>,
+    shutdown_policy: ShutdownPolicy,
+    shutdown_timeout: Duration,
+    /// Set once the arbiter shuts down.
+    shutdown: AtomicBool,
+    /// Restarts of panicked worker threads.
+    restarts: Mutex<Restarts>,
+    /// Number of running worker threads.
+    threads: Mutex<usize>,
+    /// Notified once worker thread exits.
+    exited: Condvar,
+    /// Number of running workers.
+    workers: AtomicUsize,
+    /// Number of workers waiting for a message.
+    idle: AtomicUsize,
+    /// Number of spawned workers, used as index of the next worker.
+    spawned: AtomicUsize,
+    /// Number of messages taken from the pool's mailbox which are not
+    /// handled yet, including the message being received.
+    in_flight: Arc<AtomicUsize>,
+    /// Arbiter waiting for space in the bounded queue or for workers to
+    /// exit.
+    space: AtomicWaker,
+}
+
+/// State of workers on the blocking thread pool.
+struct Blocking<A> {
+    /// Blocking thread pool of the `System`'s arbiter.
+    handle: OnceCell<Handle>,
+    /// Actors waiting for a message, the most recently parked last.
+    parked: Mutex<VecDeque<Parked>>,
+    park: ParkFn<A>,
+    /// Set while pruning of idle actors is scheduled.
+    pruning: AtomicBool,
+    /// Set once parked actors are stopped on shutdown.
+    closed: AtomicBool,
+    /// Number of workers which are not restarted after panic.
+    lost: AtomicUsize,
+}
+
+/// Counts the message as handled once dropped, even if the handler
+/// panics.
+struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Idle actor on the blocking pool.
+struct Parked {
+    act: Box<dyn Any + Send>,
+    tracked: Option<Tracked>,
+    since: Instant,
+}
+
+enum Recv<A: Actor> {
+    Envelope(Envelope<A>),
+    /// Deadline of worker's timer passed.
+    Timer,
+    Idle,
+    Closed,
+}
+
+impl<A> Shared<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Add worker if there are more queued messages than idle workers.
+    fn scale_up(self: &Arc<Self>) {
+        if self.queue.len() <= self.idle.load(Ordering::SeqCst) {
+            return;
+        }
+        let max = self.max_threads;
+        if self.update_workers(|n| if n < max { Some(n + 1) } else { None }) {
+            let idx = self.spawned.fetch_add(1, Ordering::SeqCst);
+            SyncArbiter::spawn_worker(Arc::clone(self), idx);
+        }
+    }
+
+    /// Message was sent to the queue `slot`.
+    fn sent(self: &Arc<Self>, slot: Option<usize>) {
+        match slot {
+            // worker of the slot exited while the message was sent
+            Some(idx) if self.slot_closed(slot) => self.close_slot(idx),
+            _ => self.scale_up(),
+        }
+    }
+
+    /// Whether worker of the slot is not restarted anymore.
+    fn slot_closed(&self, slot: Option<usize>) -> bool {
+        slot.map_or(false, |idx| self.closed_slots[idx].load(Ordering::SeqCst))
+    }
+
+    /// Drop messages of the slot whose worker is not restarted anymore,
+    /// their requests fail with `MailboxError::Closed`.
+    fn close_slot(&self, idx: usize) {
+        self.closed_slots[idx].store(true, Ordering::SeqCst);
+        while self.slots[idx].try_recv().is_ok() {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.space.wake();
+    }
+
+    /// Remove worker if there are more workers than the minimum.
+    fn scale_down(&self) -> bool {
+        let min = self.min_threads;
+        self.update_workers(|n| if n > min { Some(n - 1) } else { None })
+    }
+
+    /// Change number of workers while `f` allows it.
+    fn update_workers<F: Fn(usize) -> Option<usize>>(&self, f: F) -> bool {
+        let mut n = self.workers.load(Ordering::SeqCst);
+        while let Some(next) = f(n) {
+            match self.workers.compare_exchange(
+                n,
+                next,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(current) => n = current,
+            }
+        }
+        false
+    }
+
+    /// Handle message on the blocking pool once fewer than `max_threads`
+    /// workers are running.
+    fn dispatch(
+        self: &Arc<Self>,
+        env: Envelope<A>,
+        cx: &mut task::Context<'_>,
+    ) -> Result<(), Envelope<A>> {
+        if !self.acquire() {
+            // register before retry, so exited worker is not missed
+            self.space.register(cx.waker());
+            if !self.acquire() {
+                return Err(env);
+            }
+        }
+
+        let worker = Worker {
+            shared: Arc::clone(self),
+            idx: 0,
+        };
+        let handle = self
+            .blocking
+            .as_ref()
+            .and_then(|blocking| blocking.handle.get());
+        handle
+            .expect("Blocking pool is not known")
+            .spawn_blocking(move || worker.handle(env));
+        Ok(())
+    }
+
+    /// Count a worker on the blocking pool if there is room for it.
+    fn acquire(&self) -> bool {
+        let lost = self
+            .blocking
+            .as_ref()
+            .map_or(0, |blocking| blocking.lost.load(Ordering::SeqCst));
+        let mut threads = self.threads.lock();
+        if *threads + lost < self.max_threads {
+            *threads += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wait until the deadline passes. Returns `false` once the arbiter
+    /// shuts down.
+    fn sleep_until(&self, deadline: Instant) -> bool {
+        let mut threads = self.threads.lock();
+        while !self.shutdown.load(Ordering::SeqCst) {
+            if clock::now() >= deadline {
+                return true;
+            }
+            self.exited
+                .wait_for(&mut threads, clock::wait_time(deadline));
+        }
+        false
+    }
+
+    /// Schedule stop of the longest idle actor on the blocking pool once
+    /// its idle timeout passes.
+    fn schedule_prune(self: &Arc<Self>) {
+        let blocking = match self.blocking {
+            Some(ref blocking) => blocking,
+            None => return,
+        };
+        let since = match blocking.parked.lock().front() {
+            Some(parked) => parked.since,
+            None => return,
+        };
+        let handle = match blocking.handle.get() {
+            Some(handle) => handle.clone(),
+            None => return,
+        };
+        if self.workers.load(Ordering::SeqCst) <= self.min_threads
+            || self.shutdown.load(Ordering::SeqCst)
+            || blocking.pruning.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+
+        let delay = clock::wait_time(since + self.idle_timeout);
+        let shared = Arc::clone(self);
+        handle.clone().spawn(async move {
+            tokio::time::delay_for(delay).await;
+            handle.spawn_blocking(move || shared.prune());
+        });
+    }
+
+    /// Stop actors which are idle for `idle_timeout` while there are more
+    /// than `min_threads` of them.
+    fn prune(self: &Arc<Self>) {
+        let blocking = match self.blocking {
+            Some(ref blocking) => blocking,
+            None => return,
+        };
+        blocking.pruning.store(false, Ordering::SeqCst);
+
+        loop {
+            let parked = {
+                let mut parked = blocking.parked.lock();
+                let expired = parked.front().map_or(false, |parked| {
+                    clock::now().saturating_duration_since(parked.since)
+                        >= self.idle_timeout
+                });
+                if !expired || self.shutdown.load(Ordering::SeqCst) || !self.scale_down()
+                {
+                    break;
+                }
+                parked.pop_front().unwrap()
+            };
+            SyncContext::unparked(Arc::clone(self), parked)
+                .stop_idle(StopReason::Normal);
+        }
+        self.schedule_prune();
+    }
+
+    /// Whether workers stop without handling queued messages.
+    fn abandoned(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+            && self.shutdown_policy == ShutdownPolicy::Abandon
+    }
+
+    /// Hand queued messages to workers, then wait until all worker threads
+    /// exit or the deadline passes.
+    fn shut_down(
+        self: &Arc<Self>,
+        queues: Option<Queues<A>>,
+        queued: Vec<Envelope<A>>,
+        deadline: Instant,
+    ) {
+        {
+            // wake workers waiting for their timers
+            let _threads = self.threads.lock();
+            self.exited.notify_all();
+        }
+
+        if self.blocking.is_some() {
+            let reason = if queues.is_some() {
+                StopReason::Shutdown
+            } else {
+                StopReason::MailboxClosed
+            };
+            return self.shut_down_blocking(queued, reason, deadline);
+        }
+
+        match (&queues, self.shutdown_policy) {
+            (Some(queues), ShutdownPolicy::Drain) => {
+                'queued: for mut env in queued {
+                    let slot = queues.route(self, &env);
+                    if self.slot_closed(slot) {
+                        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                        continue;
+                    }
+                    let queue = queues.queue(slot);
+                    loop {
+                        match queue.send_timeout(env, clock::wait_time(deadline)) {
+                            Ok(()) => break,
+                            Err(SendTimeoutError::Timeout(e))
+                                if clock::now() < deadline =>
+                            {
+                                env = e
+                            }
+                            Err(_) => {
+                                warn!(
+                                    "Sync actor queue is not drained within shutdown timeout"
+                                );
+                                break 'queued;
+                            }
+                        }
+                    }
+                    self.sent(slot);
+                }
+            }
+            (_, ShutdownPolicy::Abandon) => {
+                for queue in iter::once(&self.queue).chain(&self.slots) {
+                    while queue.try_recv().is_ok() {}
+                }
+            }
+            (None, ShutdownPolicy::Drain) => (),
+        }
+
+        // workers stop once the queues are empty
+        drop(queues);
+        self.join(deadline);
+    }
+
+    /// Wait for running workers of the blocking pool, handle queued
+    /// messages by an idle actor, then stop idle actors.
+    fn shut_down_blocking(
+        self: &Arc<Self>,
+        queued: Vec<Envelope<A>>,
+        reason: StopReason,
+        deadline: Instant,
+    ) {
+        self.join(deadline);
+
+        if !queued.is_empty() {
+            let mut ctx = None;
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                for env in queued {
+                    if clock::now() >= deadline {
+                        warn!("Sync actor queue is not drained within shutdown timeout");
+                        break;
+                    }
+                    ctx.get_or_insert_with(|| SyncContext::resume(Arc::clone(self)))
+                        .run_blocking(env);
+                }
+            }));
+            match (res, ctx) {
+                (Ok(()), Some(ctx)) if !ctx.retired => ctx.park(),
+                (Ok(()), _) => (),
+                (Err(err), _) => error!(
+                    "Sync actor panicked during shutdown: {:?}",
+                    StopReason::from_panic(&*err)
+                ),
+            }
+        }
+
+        // workers which are still running stop their actors
+        let parked = match self.blocking {
+            Some(ref blocking) => {
+                let mut parked = blocking.parked.lock();
+                blocking.closed.store(true, Ordering::SeqCst);
+                mem::replace(&mut *parked, VecDeque::new())
+            }
+            None => return,
+        };
+        for parked in parked {
+            let ctx = SyncContext::unparked(Arc::clone(self), parked);
+            let res =
+                panic::catch_unwind(AssertUnwindSafe(|| ctx.stop_idle(reason.clone())));
+            if let Err(err) = res {
+                error!(
+                    "Sync actor panicked during shutdown: {:?}",
+                    StopReason::from_panic(&*err)
+                );
+            }
+        }
+    }
+
+    /// Wait until all worker threads exit or the deadline passes.
+    fn join(&self, deadline: Instant) {
+        let mut threads = self.threads.lock();
+        while *threads != 0 {
+            if clock::now() >= deadline {
+                warn!(
+                    "{} sync actor worker threads did not stop within shutdown timeout",
+                    *threads
+                );
+                return;
+            }
+            self.exited
+                .wait_for(&mut threads, clock::wait_time(deadline));
+        }
+    }
+
+    /// Receive next message from the pool's queue or the worker's own
+    /// queue, waiting no longer than the worker's timer `deadline`.
+    fn recv(
+        &self,
+        slot: Option<&cb_channel::Receiver<Envelope<A>>>,
+        deadline: Option<Instant>,
+    ) -> Recv<A> {
+        self.idle.fetch_add(1, Ordering::SeqCst);
+        let mut retire = None;
+        let res = loop {
+            // worker with pending timers does not retire
+            if retire.is_none()
+                && deadline.is_none()
+                && self.workers.load(Ordering::SeqCst) > self.min_threads
+            {
+                retire = Some(clock::now() + self.idle_timeout);
+            }
+            let until = match deadline.or(retire) {
+                Some(until) => until,
+                None => match self.select(slot, None) {
+                    Ok(env) => break Recv::Envelope(env),
+                    Err(_) => break Recv::Closed,
+                },
+            };
+
+            match self.select(slot, Some(clock::wait_time(until))) {
+                Ok(env) => break Recv::Envelope(env),
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = clock::now();
+                    if deadline.map_or(false, |deadline| deadline <= now) {
+                        break Recv::Timer;
+                    }
+                    if retire.map_or(false, |retire| retire <= now) {
+                        if self.scale_down() {
+                            break Recv::Idle;
+                        }
+                        // idle timeout starts again
+                        retire = None;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break Recv::Closed,
+            }
+        };
+        self.idle.fetch_sub(1, Ordering::SeqCst);
+
+        match res {
+            Recv::Envelope(_) => self.space.wake(),
+            Recv::Closed => {
+                self.workers.fetch_sub(1, Ordering::SeqCst);
+            }
+            Recv::Timer | Recv::Idle => (),
+        }
+        res
+    }
+
+    fn select(
+        &self,
+        slot: Option<&cb_channel::Receiver<Envelope<A>>>,
+        timeout: Option<Duration>,
+    ) -> Result<Envelope<A>, RecvTimeoutError> {
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                return match timeout {
+                    Some(timeout) => self.queue.recv_timeout(timeout),
+                    None => self
+                        .queue
+                        .recv()
+                        .map_err(|_| RecvTimeoutError::Disconnected),
+                }
+            }
+        };
+
+        let mut sel = cb_channel::Select::new();
+        sel.recv(&self.queue);
+        sel.recv(slot);
+        let oper = match timeout {
+            Some(timeout) => sel
+                .select_timeout(timeout)
+                .map_err(|_| RecvTimeoutError::Timeout)?,
+            None => sel.select(),
+        };
+        let (rx, other) = if oper.index() == 0 {
+            (&self.queue, slot)
+        } else {
+            (slot, &self.queue)
+        };
+        match oper.recv(rx) {
+            Ok(env) => Ok(env),
+            // queues are closed together, the other one may still have messages
+            Err(_) => other.try_recv().map_err(|_| RecvTimeoutError::Disconnected),
+        }
+    }
+}
+
 impl<A> Actor for SyncArbiter<A>
 where
     A: Actor<Context = SyncContext<A>>,
@@ -156,25 +1234,167 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
+        if this.handle.is_none() {
+            *this.handle = Some(Handle::current());
+        }
+
+        if let Some(lookup) = this.lookup.as_mut() {
+            let handle = match lookup.as_mut().poll(cx) {
+                Poll::Ready(handle) => handle,
+                Poll::Pending => return Poll::Pending,
+            };
+            *this.lookup = None;
+            // the `System`'s arbiter is gone, use own runtime
+            let handle = handle.or_else(|| this.handle.clone());
+            if let (Some(blocking), Some(handle)) = (&this.shared.blocking, handle) {
+                let _ = blocking.handle.set(handle);
+            }
+        }
+
         loop {
-            match this.msgs.poll_next_unpin(cx) {
-                Poll::Ready(Some(msg)) => {
-                    if let Some(ref queue) = this.queue {
-                        assert!(queue.send(msg).is_ok());
+            if let (Some(env), Some(queues)) = (this.pending.take(), this.queue.as_ref())
+            {
+                if this.shared.blocking.is_some() {
+                    if let Err(env) = this.shared.dispatch(env, cx) {
+                        *this.pending = Some(env);
+                        return Poll::Pending;
                     }
+                    continue;
+                }
+
+                let slot = queues.route(this.shared, &env);
+                if this.shared.slot_closed(slot) {
+                    // the request fails once the envelope is dropped
+                    this.shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let queue = queues.queue(slot);
+                match queue.try_send(env) {
+                    Ok(()) => this.shared.sent(slot),
+                    Err(TrySendError::Full(env)) => {
+                        // register before retry, so freed space is not missed
+                        this.shared.space.register(cx.waker());
+                        match queue.try_send(env) {
+                            Ok(()) => this.shared.sent(slot),
+                            Err(err) => {
+                                *this.pending = Some(err.into_inner());
+                                return Poll::Pending;
+                            }
+                        }
+                    }
+                    Err(TrySendError::Disconnected(_)) => unreachable!(),
+                }
+            }
+
+            // message is counted before it leaves the mailbox
+            this.shared.in_flight.fetch_add(1, Ordering::SeqCst);
+            match this.msgs.poll_next_unpin(cx) {
+                Poll::Ready(Some(msg)) => *this.pending = Some(msg),
+                Poll::Pending => {
+                    this.shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    break;
                 }
-                Poll::Pending => break,
                 Poll::Ready(None) => unreachable!(),
             }
         }
 
         // stop condition
         if this.msgs.connected() {
-            Poll::Pending
-        } else {
-            // stop sync arbiters
-            *this.queue = None;
+            return Poll::Pending;
+        }
+
+        // stop sync arbiters and wait until workers exit
+        *this.queue = None;
+        this.msgs.set_stop_reason(StopReason::MailboxClosed);
+        this.shared.space.register(cx.waker());
+        if *this.shared.threads.lock() == 0 {
             Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[pinned_drop]
+impl<A> PinnedDrop for SyncArbiter<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Shut down workers once the arbiter running the `SyncArbiter` stops.
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        let shared = Arc::clone(this.shared);
+        shared.shutdown.store(true, Ordering::SeqCst);
+        let deadline = clock::now() + shared.shutdown_timeout;
+
+        // reject new messages
+        this.msgs.close();
+
+        let mut queued = Vec::new();
+        match shared.shutdown_policy {
+            ShutdownPolicy::Drain => {
+                let waker = noop_waker();
+                let mut cx = task::Context::from_waker(&waker);
+                queued.extend(this.pending.take());
+                while let Poll::Ready(Some(env)) = this.msgs.poll_next_unpin(&mut cx) {
+                    shared.in_flight.fetch_add(1, Ordering::SeqCst);
+                    queued.push(env);
+                }
+            }
+            ShutdownPolicy::Abandon => {
+                this.pending.take();
+            }
+        }
+        let queues = this.queue.take();
+        let shut_down = ShutDown(Some(Box::new(move || {
+            shared.shut_down(queues, queued, deadline)
+        })));
+
+        match this.handle.take() {
+            // the runtime waits for blocking tasks once it stops, so the
+            // wait is a part of the arbiter's shutdown
+            Some(handle) => {
+                handle.spawn_blocking(move || shut_down.run());
+            }
+            // arbiter never ran, nothing waits for the workers
+            None => {
+                let res = thread::Builder::new()
+                    .name("actix-sync-shutdown".to_owned())
+                    .spawn(move || {
+                        let res =
+                            panic::catch_unwind(AssertUnwindSafe(|| shut_down.run()));
+                        if let Err(err) = res {
+                            error!(
+                                "Sync actor shutdown panicked: {:?}",
+                                StopReason::from_panic(&*err)
+                            );
+                        }
+                    });
+                if let Err(err) = res {
+                    // dropped closure shuts down on the current thread
+                    error!("Can not spawn sync actor shutdown thread: {}", err);
+                }
+            }
+        }
+    }
+}
+
+/// Shutdown of a `SyncArbiter`. It runs once dropped, if the blocking task
+/// which should run it is cancelled by the stopping runtime.
+struct ShutDown(Option<Box<dyn FnOnce() + Send>>);
+
+impl ShutDown {
+    fn run(mut self) {
+        if let Some(shut_down) = self.0.take() {
+            shut_down()
+        }
+    }
+}
+
+impl Drop for ShutDown {
+    fn drop(&mut self) {
+        if let Some(shut_down) = self.0.take() {
+            shut_down()
         }
     }
 }
@@ -198,6 +1418,12 @@ where
 /// the Actor. Similar, returning `false` from `fn stopping` can not prevent
 /// the restart or termination of the Actor.
 ///
+/// Sync Actor can send messages to itself with `notify` and schedule
+/// timers with `notify_later`, `run_later` and `run_interval`. These are
+/// handled by the same worker in turn with messages from the pool's queue,
+/// and are cancelled once the Actor is restarted. Timers use the clock of
+/// the `System`, see [`clock::now()`](../clock/fn.now.html).
+///
 /// ## Example
 ///
 /// ```rust
@@ -224,75 +1450,415 @@ where
     A: Actor<Context = SyncContext<A>>,
 {
     act: Option<A>,
-    queue: cb_channel::Receiver<Envelope<A>>,
+    shared: Arc<Shared<A>>,
+    /// Worker's own queue, used for messages with affinity key.
+    slot: Option<cb_channel::Receiver<Envelope<A>>>,
     stopping: bool,
     state: ActorState,
-    factory: Arc<dyn Fn() -> A>,
-    address: AddressSenderProducer<A>,
+    reason: Option<StopReason>,
+    tracked: Option<Tracked>,
+    timers: Timers<A>,
+    /// Set once the worker left the pool.
+    retired: bool,
+}
+
+type RunLater<A> = Box<dyn FnOnce(&mut A, &mut SyncContext<A>)>;
+type RunInterval<A> = Box<dyn FnMut(&mut A, &mut SyncContext<A>)>;
+
+enum TimerItem<A: Actor<Context = SyncContext<A>>> {
+    Envelope(Envelope<A>),
+    Later(RunLater<A>),
+    Interval(Duration, RunInterval<A>),
+}
+
+struct Timer<A: Actor<Context = SyncContext<A>>> {
+    handle: SpawnHandle,
+    deadline: Instant,
+    item: TimerItem<A>,
+}
+
+/// Timers and notifications of a single worker.
+struct Timers<A: Actor<Context = SyncContext<A>>> {
+    handle: SpawnHandle,
+    items: Vec<Timer<A>>,
+    /// Interval timer which is running.
+    running: Option<SpawnHandle>,
+}
+
+impl<A: Actor<Context = SyncContext<A>>> Timers<A> {
+    fn insert(&mut self, after: Duration, item: TimerItem<A>) -> SpawnHandle {
+        self.handle = self.handle.next();
+        self.items.push(Timer {
+            handle: self.handle,
+            deadline: clock::now() + after,
+            item,
+        });
+        self.handle
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.items.iter().map(|timer| timer.deadline).min()
+    }
+
+    fn elapsed(&self) -> bool {
+        self.deadline()
+            .map_or(false, |deadline| deadline <= clock::now())
+    }
+
+    /// Remove the earliest elapsed timer.
+    fn pop_elapsed(&mut self) -> Option<Timer<A>> {
+        let now = clock::now();
+        let (idx, _) = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .min_by_key(|(_, timer)| (timer.deadline, timer.handle.into_usize()))?;
+        Some(self.items.remove(idx))
+    }
+
+    fn cancel(&mut self, handle: SpawnHandle) -> bool {
+        if self.running == Some(handle) {
+            self.running = None;
+            return true;
+        }
+        match self.items.iter().position(|timer| timer.handle == handle) {
+            Some(idx) => {
+                self.items.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.running = None;
+    }
 }
 
 impl<A> SyncContext<A>
 where
     A: Actor<Context = Self>,
 {
-    fn new(
-        factory: Arc<dyn Fn() -> A>,
-        queue: cb_channel::Receiver<Envelope<A>>,
-        address: AddressSenderProducer<A>,
+    fn new(shared: Arc<Shared<A>>, idx: usize) -> Self {
+        let act = (shared.factory)();
+        let slot = shared.slots.get(idx).cloned();
+        let (probe, slot_probe) = (shared.queue.clone(), slot.clone());
+        let tracked = Tracked::register(
+            shared.address.id(),
+            std::any::type_name::<A>(),
+            move || probe.len() + slot_probe.as_ref().map_or(0, |slot| slot.len()),
+        );
+        Self::with_actor(shared, slot, act, tracked, ActorState::Started)
+    }
+
+    /// Context of the most recently parked actor of the blocking pool, or
+    /// of a new one.
+    fn resume(shared: Arc<Shared<A>>) -> Self {
+        let parked = shared
+            .blocking
+            .as_ref()
+            .and_then(|blocking| blocking.parked.lock().pop_back());
+        match parked {
+            Some(parked) => Self::unparked(shared, parked),
+            None => {
+                shared.workers.fetch_add(1, Ordering::SeqCst);
+                Self::new(shared, 0)
+            }
+        }
+    }
+
+    fn unparked(shared: Arc<Shared<A>>, parked: Parked) -> Self {
+        let act = parked.act.downcast().expect("Parked actor of other type");
+        Self::with_actor(shared, None, *act, parked.tracked, ActorState::Running)
+    }
+
+    fn with_actor(
+        shared: Arc<Shared<A>>,
+        slot: Option<cb_channel::Receiver<Envelope<A>>>,
+        act: A,
+        tracked: Option<Tracked>,
+        state: ActorState,
     ) -> Self {
-        let act = factory();
         Self {
-            queue,
-            factory,
+            shared,
+            slot,
             act: Some(act),
             stopping: false,
-            state: ActorState::Started,
-            address,
+            state,
+            reason: None,
+            tracked,
+            timers: Timers {
+                handle: SpawnHandle::default(),
+                items: Vec::new(),
+                running: None,
+            },
+            retired: false,
         }
     }
 
     fn run(&mut self) {
         let mut act = self.act.take().unwrap();
-
-        // started
-        A::started(&mut act, self);
-        self.state = ActorState::Running;
+        self.start(&mut act);
 
         loop {
-            match self.queue.recv() {
-                Ok(mut env) => {
-                    env.handle(&mut act, self);
+            // timers and messages from the queue are handled in turn
+            if let Some(timer) = self.timers.pop_elapsed() {
+                self.set_busy(true);
+                self.fire(&mut act, timer);
+                if !self.handled(&mut act) {
+                    return;
                 }
-                Err(_) => {
-                    self.state = ActorState::Stopping;
-                    if A::stopping(&mut act, self) != Running::Stop {
-                        warn!("stopping method is not supported for sync actors");
-                    }
-                    self.state = ActorState::Stopped;
-                    A::stopped(&mut act, self);
+            }
+
+            // worker stays busy while it has elapsed timers
+            if !self.timers.elapsed() {
+                self.set_busy(false);
+            }
+            match self.shared.recv(self.slot.as_ref(), self.timers.deadline()) {
+                Recv::Envelope(env) => self.handle_envelope(&mut act, env),
+                Recv::Timer => continue,
+                Recv::Idle => {
+                    self.retired = true;
+                    self.stop_actor(&mut act, StopReason::Normal);
+                    // message could be queued while the worker retired
+                    self.shared.scale_up();
+                    return;
+                }
+                Recv::Closed => {
+                    let reason = if self.shared.shutdown.load(Ordering::SeqCst) {
+                        StopReason::Shutdown
+                    } else {
+                        StopReason::MailboxClosed
+                    };
+                    self.retired = true;
+                    self.stop_actor(&mut act, reason);
+                    return;
+                }
+            }
+
+            if !self.handled(&mut act) {
+                return;
+            }
+        }
+    }
+
+    /// Handle a message on the blocking pool, then wait for pending timers
+    /// of the actor.
+    fn run_blocking(&mut self, env: Envelope<A>) {
+        let mut act = self.act.take().unwrap();
+        if self.state == ActorState::Started {
+            self.start(&mut act);
+        }
+
+        self.handle_envelope(&mut act, env);
+        if !self.handled(&mut act) {
+            return;
+        }
+
+        while let Some(deadline) = self.timers.deadline() {
+            if !self.timers.elapsed() {
+                self.set_busy(false);
+            }
+            if !self.shared.sleep_until(deadline) {
+                break;
+            }
+            if let Some(timer) = self.timers.pop_elapsed() {
+                self.set_busy(true);
+                self.fire(&mut act, timer);
+                if !self.handled(&mut act) {
                     return;
                 }
             }
+        }
+        self.set_busy(false);
+        self.act = Some(act);
+    }
+
+    fn start(&mut self, act: &mut A) {
+        A::started(act, self);
+        self.set_state(ActorState::Running);
+    }
+
+    /// Handle a message from the pool's queue. The worker stays busy until
+    /// it waits for the next message.
+    fn handle_envelope(&mut self, act: &mut A, mut env: Envelope<A>) {
+        self.set_busy(true);
+        let _handled = InFlight(Arc::clone(&self.shared.in_flight));
+        if metrics::enabled() {
+            let start = clock::now();
+            env.handle(act, self);
+            self.report(&env, start);
+        } else {
+            env.handle(act, self);
+        }
+    }
+
+    /// Park the actor until the next message, it does not hold a thread.
+    /// The actor is stopped if the pool is already shut down.
+    fn park(mut self) {
+        let mut act = self.act.take().unwrap();
+        let shared = Arc::clone(&self.shared);
+        if let Some(ref blocking) = shared.blocking {
+            let mut parked = blocking.parked.lock();
+            if blocking.closed.load(Ordering::SeqCst) {
+                drop(parked);
+                shared.workers.fetch_sub(1, Ordering::SeqCst);
+                return self.stop_actor(&mut act, StopReason::Shutdown);
+            }
+            parked.push_back(Parked {
+                act: (blocking.park)(act),
+                tracked: self.tracked.take(),
+                since: clock::now(),
+            });
+        }
+        shared.schedule_prune();
+    }
 
-            if self.stopping {
-                self.stopping = false;
+    /// Stop the actor which is not running.
+    fn stop_idle(mut self, reason: StopReason) {
+        if let Some(mut act) = self.act.take() {
+            self.stop_actor(&mut act, reason);
+        }
+    }
+
+    /// Restart the actor if it got stopped. Returns `false` if the worker
+    /// exits.
+    fn handled(&mut self, act: &mut A) -> bool {
+        if self.shared.abandoned() {
+            self.shared.workers.fetch_sub(1, Ordering::SeqCst);
+            self.retired = true;
+            self.stop_actor(act, StopReason::Shutdown);
+            return false;
+        }
 
-                // stop old actor
-                A::stopping(&mut act, self);
-                self.state = ActorState::Stopped;
-                A::stopped(&mut act, self);
+        if self.stopping {
+            self.stopping = false;
+
+            // stop old actor
+            A::stopping(act, self);
+            self.set_state(ActorState::Stopped);
+            A::stopped(act, self);
+
+            // start new actor, timers of the old one are cancelled
+            self.timers.clear();
+            self.set_state(ActorState::Started);
+            self.reason = None;
+            *act = (self.shared.factory)();
+            A::started(act, self);
+            self.set_state(ActorState::Running);
+        }
+        true
+    }
 
-                // start new actor
-                self.state = ActorState::Started;
-                act = (*self.factory)();
-                A::started(&mut act, self);
-                self.state = ActorState::Running;
+    fn fire(&mut self, act: &mut A, timer: Timer<A>) {
+        match timer.item {
+            TimerItem::Envelope(mut env) => env.handle(act, self),
+            TimerItem::Later(f) => f(act, self),
+            TimerItem::Interval(interval, mut f) => {
+                self.timers.running = Some(timer.handle);
+                f(act, self);
+                // interval is not cancelled by the callback
+                if self.timers.running.take().is_some() {
+                    self.timers.items.push(Timer {
+                        handle: timer.handle,
+                        deadline: timer.deadline + interval,
+                        item: TimerItem::Interval(interval, f),
+                    });
+                }
             }
         }
     }
 
+    fn stop_actor(&mut self, act: &mut A, reason: StopReason) {
+        self.set_state(ActorState::Stopping);
+        self.reason.get_or_insert(reason);
+        if A::stopping(act, self) != Running::Stop {
+            warn!("stopping method is not supported for sync actors");
+        }
+        self.set_state(ActorState::Stopped);
+        A::stopped(act, self);
+    }
+
+    fn set_state(&mut self, state: ActorState) {
+        self.state = state;
+        if let Some(ref tracked) = self.tracked {
+            tracked.set_state(state);
+        }
+    }
+
+    fn set_busy(&self, busy: bool) {
+        if let Some(ref tracked) = self.tracked {
+            tracked.set_busy(busy);
+        }
+    }
+
     pub fn address(&self) -> Addr<A> {
-        Addr::new(self.address.sender())
+        Addr::new(self.shared.address.sender())
+    }
+
+    /// Sends the message to this worker's actor.
+    ///
+    /// The message is not sent through the pool's queue, it is handled by
+    /// the same worker after the current message.
+    pub fn notify<M>(&mut self, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.notify_later(msg, Duration::from_secs(0));
+    }
+
+    /// Sends the message to this worker's actor after a specified period
+    /// of time.
+    ///
+    /// Returns handle of the timer, which can be used to cancel it with
+    /// `cancel_timer()`.
+    pub fn notify_later<M>(&mut self, msg: M, after: Duration) -> SpawnHandle
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        let env = <Self as ToEnvelope<A, M>>::pack(msg, None);
+        self.timers.insert(after, TimerItem::Envelope(env))
+    }
+
+    /// Executes a closure on this worker's actor after a specified period
+    /// of time.
+    pub fn run_later<F>(&mut self, dur: Duration, f: F) -> SpawnHandle
+    where
+        F: FnOnce(&mut A, &mut Self) + 'static,
+    {
+        self.timers.insert(dur, TimerItem::Later(Box::new(f)))
+    }
+
+    /// Executes a closure on this worker's actor periodically at the
+    /// specified fixed interval.
+    pub fn run_interval<F>(&mut self, dur: Duration, f: F) -> SpawnHandle
+    where
+        F: FnMut(&mut A, &mut Self) + 'static,
+    {
+        self.timers
+            .insert(dur, TimerItem::Interval(dur, Box::new(f)))
+    }
+
+    /// Cancels timer or notification, returns `false` if it is not found.
+    pub fn cancel_timer(&mut self, handle: SpawnHandle) -> bool {
+        self.timers.cancel(handle)
+    }
+
+    fn report(&self, env: &Envelope<A>, start: Instant) {
+        let duration = clock::now().saturating_duration_since(start);
+        let id = self.shared.address.id();
+        let actor = std::any::type_name::<A>();
+        metrics::report(|sink| {
+            sink.message_handled(id, actor, env.message_type(), env.elapsed(), duration);
+            let slot = self.slot.as_ref().map_or(0, |slot| slot.len());
+            sink.queue_length(id, actor, self.shared.queue.len() + slot);
+        });
     }
 }
 
@@ -303,21 +1869,33 @@ where
     /// Stop the current Actor. SyncContext will stop the existing Actor, and restart
     /// a new Actor of the same type to replace it.
     fn stop(&mut self) {
-        self.stopping = true;
-        self.state = ActorState::Stopping;
+        self.stop_with(StopReason::Normal)
     }
 
     /// Terminate the current Actor. SyncContext will terminate the existing Actor, and restart
     /// a new Actor of the same type to replace it.
     fn terminate(&mut self) {
-        self.stopping = true;
-        self.state = ActorState::Stopping;
+        self.terminate_with(StopReason::Normal)
     }
 
     /// Get the Actor execution state.
     fn state(&self) -> ActorState {
         self.state
     }
+
+    fn stop_with(&mut self, reason: StopReason) {
+        self.stopping = true;
+        self.set_state(ActorState::Stopping);
+        self.reason.get_or_insert(reason);
+    }
+
+    fn terminate_with(&mut self, reason: StopReason) {
+        self.stop_with(reason)
+    }
+
+    fn stop_reason(&self) -> Option<&StopReason> {
+        self.reason.as_ref()
+    }
 }
 
 pub(crate) struct SyncContextEnvelope<A, M>
@@ -327,6 +1905,7 @@ where
 {
     msg: Option<M>,
     tx: Option<SyncSender<M::Result>>,
+    failure: Option<Arc<Failure>>,
     actor: PhantomData<A>,
 }
 
@@ -346,6 +1925,7 @@ where
     pub fn new(msg: M, tx: Option<SyncSender<M::Result>>) -> Self {
         Self {
             tx,
+            failure: None,
             msg: Some(msg),
             actor: PhantomData,
         }
@@ -361,13 +1941,20 @@ where
     type Actor = A;
 
     fn handle(&mut self, act: &mut A, ctx: &mut A::Context) {
-        let tx = self.tx.take();
-        if tx.is_some() && tx.as_ref().unwrap().is_canceled() {
-            return;
-        }
+        let tx = Responder::new(self.tx.take(), self.failure.take());
+        handle_request(act, ctx, &mut self.msg, tx, &mut ())
+    }
 
-        if let Some(msg) = self.msg.take() {
-            <A as Handler<M>>::handle(act, msg, ctx).handle(ctx, tx)
-        }
+    fn message_type(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
+
+    fn message(&self) -> Option<&dyn Any> {
+        let msg: &M = self.msg.as_ref()?;
+        Some(msg)
+    }
+
+    fn set_failure(&mut self, failure: Arc<Failure>) {
+        self.failure = Some(failure);
     }
 }
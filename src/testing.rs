@@ -0,0 +1,216 @@
+//! Test harness which runs actors until they are idle
+//!
+//! [`TestSystem`](struct.TestSystem.html) runs a `System` until all actors
+//! are idle instead of guessing sleep durations before
+//! `System::current().stop()`. Actors are tracked by the
+//! [`introspection`](../introspection/index.html) registry while a
+//! `TestSystem` exists.
+//!
+//! Every check first lets the system's arbiter run all ready tasks and fire
+//! elapsed timers. Actor is idle once it is neither woken nor being polled,
+//! i.e. no message, spawned future or elapsed timer is ready to be handled,
+//! and its mailbox is empty. Timers which have not elapsed yet do not keep
+//! the actor busy. Sync actor is idle once its worker does not handle a
+//! message or an elapsed timer, and the `SyncArbiter` has no messages in its
+//! mailbox or handed to workers.
+//!
+//! Actors on other threads, i.e. on other arbiters and sync actors, run
+//! while they are checked. The system is idle once two consecutive checks
+//! find all actors idle and none of them was polled, or handled a message,
+//! in between. Every actor then stayed idle since the first of the checks,
+//! so all of them were idle at once.
+//!
+//! Only actors are checked. Tasks spawned with `actix::spawn()` get to run
+//! in every check, but a task which waits for a timer, I/O or another thread
+//! before it sends a message is not waited for. Neither are timers of sync
+//! actors which elapse while their worker waits for a message.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use actix::prelude::*;
+//! use actix::testing::TestSystem;
+//!
+//! struct Counter(usize);
+//!
+//! impl Actor for Counter {
+//!     type Context = Context<Self>;
+//! }
+//!
+//! struct Incr;
+//!
+//! impl Message for Incr {
+//!     type Result = ();
+//! }
+//!
+//! impl Handler<Incr> for Counter {
+//!     type Result = ();
+//!
+//!     fn handle(&mut self, _: Incr, ctx: &mut Context<Self>) {
+//!         self.0 += 1;
+//!         if self.0 < 10 {
+//!             ctx.notify(Incr);
+//!         }
+//!     }
+//! }
+//!
+//! struct Get;
+//!
+//! impl Message for Get {
+//!     type Result = usize;
+//! }
+//!
+//! impl Handler<Get> for Counter {
+//!     type Result = usize;
+//!
+//!     fn handle(&mut self, _: Get, _: &mut Context<Self>) -> usize {
+//!         self.0
+//!     }
+//! }
+//!
+//! let mut sys = TestSystem::new();
+//! let addr = sys.block_on(async { Counter(0).start() });
+//! addr.do_send(Incr);
+//!
+//! sys.run_until_idle().unwrap();
+//! assert_eq!(sys.block_on(addr.send(Get)).unwrap(), 10);
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use actix_rt::{System, SystemRunner};
+use tokio::task;
+
+use crate::clock;
+use crate::introspection::{self, ActorInfo, TrackingGuard};
+
+/// Default time to wait for actors to become idle.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `System` which runs until its actors are idle.
+pub struct TestSystem {
+    runner: SystemRunner,
+    id: usize,
+    idle_timeout: Duration,
+    _tracking: TrackingGuard,
+}
+
+impl fmt::Debug for TestSystem {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("TestSystem")
+            .field("id", &self.id)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
+}
+
+impl Default for TestSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestSystem {
+    /// Creates new system, actors are tracked until it is dropped.
+    pub fn new() -> Self {
+        let tracking = TrackingGuard::new();
+        let runner = System::new("test");
+        TestSystem {
+            runner,
+            id: System::current().id(),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            _tracking: tracking,
+        }
+    }
+
+    /// Sets time to wait for actors to become idle, default is 5 seconds.
+    ///
+    /// The time is measured by the system time, it elapses even if the
+    /// [`clock`](../clock/index.html) is paused.
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Runs the future to completion on the system's arbiter.
+    pub fn block_on<F: Future + 'static>(&mut self, fut: F) -> F::Output {
+        self.runner.block_on(fut)
+    }
+
+    /// Runs the system until all of its actors are idle.
+    ///
+    /// Returns actors which are still busy once the idle timeout elapses.
+    pub fn run_until_idle(&mut self) -> Result<(), Busy> {
+        let id = self.id;
+        let deadline = Instant::now() + self.idle_timeout;
+
+        self.block_on(async move {
+            // actors found idle by the previous check, with their polls
+            let mut idle = Vec::new();
+            loop {
+                // ready tasks run and elapsed timers fire, the due timer
+                // keeps paused clock from jumping to the next timer
+                let turn = clock::delay_for(Duration::from_millis(0));
+                let () = task::yield_now().await;
+                turn.await;
+
+                let mut busy = introspection::busy_pools(id);
+                let mut polled = Vec::new();
+                let mut checked = Vec::new();
+                for info in introspection::actors() {
+                    if info.system != Some(id) {
+                        continue;
+                    }
+                    let key = (info.id, info.thread.clone(), info.polls);
+                    if info.busy || info.mailbox != 0 {
+                        busy.push(info);
+                    } else if !idle.contains(&key) {
+                        polled.push(info);
+                    }
+                    checked.push(key);
+                }
+
+                if busy.is_empty() {
+                    // every actor stayed idle since the previous check
+                    if polled.is_empty() && checked.len() == idle.len() {
+                        return Ok(());
+                    }
+                    idle = checked;
+                } else {
+                    idle.clear();
+                }
+
+                busy.append(&mut polled);
+                if !busy.is_empty() && Instant::now() >= deadline {
+                    return Err(Busy { actors: busy });
+                }
+            }
+        })
+    }
+}
+
+/// Actors which did not become idle within the timeout.
+#[derive(Clone, Debug)]
+pub struct Busy {
+    /// State of busy actors.
+    pub actors: Vec<ActorInfo>,
+}
+
+impl fmt::Display for Busy {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "actors are busy:")?;
+        for info in &self.actors {
+            write!(
+                fmt,
+                " {}(id: {}, mailbox: {})",
+                info.actor,
+                info.id.as_usize(),
+                info.mailbox
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Busy {}
@@ -1,3 +1,4 @@
+use std::fmt::Write;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -6,7 +7,7 @@ use std::time::Duration;
 use futures_channel::oneshot;
 
 use crate::actor::Actor;
-use crate::clock::{interval_at, Delay, Instant, Interval};
+use crate::clock::{self, interval_at, Delay, Instant, Interval};
 use crate::fut::{ActorFuture, ActorStream};
 
 pub struct Condition<T>
@@ -78,7 +79,6 @@ where
 /// #    let addr = MyActor.start();
 /// #    sys.run();
 /// # }
-
 #[must_use = "future do nothing unless polled"]
 pub struct TimerFunc<A>
 where
@@ -99,7 +99,7 @@ where
     {
         TimerFunc {
             f: Some(Box::new(f)),
-            timeout: tokio::time::delay_for(timeout),
+            timeout: clock::delay_for(timeout),
         }
     }
 }
@@ -230,3 +230,36 @@ impl<A: Actor> ActorStream for IntervalFunc<A> {
         }
     }
 }
+
+/// Escape string for JSON string literal and Prometheus label value.
+///
+/// Control characters other than newline are escaped as `\uXXXX`, which is
+/// valid in JSON only; type names used as label values never contain them.
+pub(crate) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a\"b\\c\nd\te\u{1}"),
+            "a\\\"b\\\\c\\nd\\u0009e\\u0001"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use std::future::Future;
+use std::pin::Pin;
 use std::sync;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::task::Poll;
 
 use actix::prelude::*;
 use tokio::time::{delay_for, Duration, Instant};
@@ -12,6 +15,7 @@ impl Message for Num {
     type Result = ();
 }
 
+#[allow(dead_code)]
 struct MyActor(Arc<AtomicUsize>, Arc<AtomicBool>, Running);
 
 impl Actor for MyActor {
@@ -242,3 +246,48 @@ fn test_run_interval() {
     //We wait 10 intervals by ~100ms
     assert_eq!(result.elapsed().as_secs(), 1);
 }
+
+struct TerminateActor(Arc<AtomicUsize>);
+
+impl Actor for TerminateActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Context<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_terminate_does_not_start_again() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let s = Arc::clone(&started);
+
+    System::run(move || {
+        let (tx, rx) = actix::dev::channel::channel(16);
+        let mut fut = Context::with_receiver(rx).into_future(TerminateActor(s));
+        let mut polls = 0;
+
+        actix::spawn(futures_util::future::poll_fn(move |cx| {
+            // keep mailbox connected
+            let _ = &tx;
+            polls += 1;
+            if polls == 2 {
+                // terminate context from outside, between polls
+                fut.ctx().terminate();
+            }
+            match Pin::new(&mut fut).poll(cx) {
+                Poll::Ready(()) => {
+                    System::current().stop();
+                    Poll::Ready(())
+                }
+                Poll::Pending => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+        }));
+    })
+    .unwrap();
+
+    assert_eq!(started.load(Ordering::SeqCst), 1);
+}
@@ -7,6 +7,7 @@ use actix::prelude::*;
 use tokio::time::{delay_for, Duration};
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct Ping(usize);
 
 impl Message for Ping {
@@ -254,6 +255,7 @@ fn test_address_hash() {
         let addr0 = MyActor(count0).start();
         let addr01 = addr0.clone();
 
+        #[allow(clippy::mutable_key_type)]
         let mut addresses = HashSet::new();
         addresses.insert(addr0.clone());
         addresses.insert(addr01.clone());
@@ -339,3 +341,132 @@ fn test_recipient_hash() {
     })
     .unwrap();
 }
+
+struct Data(usize);
+
+impl Message for Data {
+    type Result = ();
+}
+
+struct Control(usize);
+
+impl Message for Control {
+    type Result = ();
+
+    const PRIORITY: Priority = Priority::High;
+}
+
+struct Order(Arc<std::sync::Mutex<Vec<usize>>>);
+
+impl Actor for Order {
+    type Context = Context<Self>;
+}
+
+impl Handler<Data> for Order {
+    type Result = ();
+
+    fn handle(&mut self, msg: Data, _: &mut Self::Context) {
+        self.0.lock().unwrap().push(msg.0);
+    }
+}
+
+impl Handler<Control> for Order {
+    type Result = ();
+
+    fn handle(&mut self, msg: Control, _: &mut Self::Context) {
+        self.0.lock().unwrap().push(msg.0);
+    }
+}
+
+#[test]
+fn test_message_priority() {
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let order2 = Arc::clone(&order);
+
+    System::run(move || {
+        let addr = Order(order2).start();
+        // actor is not started yet, all messages are queued
+        addr.do_send(Data(1));
+        addr.do_send(Data(2));
+        let low = addr.send_with_priority(Data(3), Priority::Low);
+        addr.do_send(Control(4));
+        let high = addr.send_with_priority(Data(5), Priority::High);
+        addr.do_send(Data(6));
+
+        actix::spawn(async move {
+            high.await.unwrap();
+            low.await.unwrap();
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(*order.lock().unwrap(), vec![4, 5, 1, 2, 6, 3]);
+}
+
+struct Bounded(Arc<std::sync::Mutex<(Vec<usize>, usize)>>);
+
+impl Actor for Bounded {
+    type Context = Context<Self>;
+}
+
+impl Handler<Data> for Bounded {
+    type Result = ();
+
+    fn handle(&mut self, msg: Data, ctx: &mut Self::Context) {
+        let mut state = self.0.lock().unwrap();
+        state.0.push(msg.0);
+        state.1 = ctx.dropped_messages();
+    }
+}
+
+fn overflow(policy: OverflowPolicy) -> (Vec<usize>, usize, Vec<bool>) {
+    let state = Arc::new(std::sync::Mutex::new((Vec::new(), 0)));
+    let state2 = Arc::clone(&state);
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sent2 = Arc::clone(&sent);
+
+    System::run(move || {
+        let addr = Bounded::create(move |ctx| {
+            ctx.set_mailbox_capacity(2);
+            ctx.set_mailbox_overflow(policy);
+            Bounded(state2)
+        });
+        // actor is not started yet, all messages are queued
+        let mut results = Vec::new();
+        for i in 1..5 {
+            results.push(addr.try_send(Data(i)).is_ok());
+        }
+        let req = addr.send(Data(5));
+
+        actix::spawn(async move {
+            results.push(req.await.is_ok());
+            *sent2.lock().unwrap() = results;
+            delay_for(Duration::from_millis(10)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let (handled, dropped) = state.lock().unwrap().clone();
+    let sent = sent.lock().unwrap().clone();
+    (handled, dropped, sent)
+}
+
+#[test]
+fn test_mailbox_overflow() {
+    let (handled, dropped, sent) = overflow(OverflowPolicy::DropOldest);
+    assert_eq!(handled, vec![4, 5]);
+    assert_eq!(dropped, 3);
+    assert_eq!(sent, vec![true, true, true, true, true]);
+
+    let (handled, dropped, sent) = overflow(OverflowPolicy::DropNewest);
+    assert_eq!(handled, vec![1, 2]);
+    assert_eq!(dropped, 3);
+    assert_eq!(sent, vec![true, true, true, true, false]);
+
+    let (handled, dropped, sent) = overflow(OverflowPolicy::Reject);
+    assert_eq!(handled, vec![1, 2]);
+    assert_eq!(dropped, 3);
+    assert_eq!(sent, vec![true, true, false, false, false]);
+}
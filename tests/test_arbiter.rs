@@ -10,6 +10,7 @@ impl Message for Panic {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct Ping(usize);
 
 impl Message for Ping {
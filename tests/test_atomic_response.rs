@@ -46,3 +46,80 @@ async fn test_atomic_response() {
     assert!(Instant::now().duration_since(start).as_millis() >= 28);
     assert_eq!(result, vec![7, 13, 18, 22, 25, 27, 28]);
 }
+
+#[derive(Message)]
+#[rtype(result = "usize")]
+struct Scoped(u64);
+
+#[derive(Message)]
+#[rtype(result = "Result<usize, ()>")]
+struct Concurrent(u64);
+
+impl Handler<Scoped> for MyActor {
+    type Result = AtomicResponse<Self, usize>;
+
+    fn handle(&mut self, msg: Scoped, _: &mut Self::Context) -> Self::Result {
+        AtomicResponse::scoped(move |scope: ActorScope<Self>| async move {
+            let before = scope.with(|this, _| this.0);
+            delay_for(Duration::from_millis(msg.0)).await;
+            scope.with(|this, _| {
+                // no other message is handled meanwhile
+                assert_eq!(this.0, before);
+                this.0 += msg.0 as usize;
+                this.0
+            })
+        })
+    }
+}
+
+impl Handler<Concurrent> for MyActor {
+    type Result = ActorResponse<Self, usize, ()>;
+
+    fn handle(&mut self, msg: Concurrent, _: &mut Self::Context) -> Self::Result {
+        ActorResponse::scoped(move |scope: ActorScope<Self>| async move {
+            delay_for(Duration::from_millis(msg.0)).await;
+            Ok(scope.with(|this, _| {
+                this.0 += msg.0 as usize;
+                this.0
+            }))
+        })
+    }
+}
+
+#[actix_rt::test]
+async fn test_scoped_atomic_response() {
+    let addr = MyActor(0).start();
+    let res = futures_util::future::join_all(vec![
+        addr.send(Scoped(20)),
+        addr.send(Scoped(10)),
+        addr.send(Scoped(5)),
+    ])
+    .await;
+    let res: Vec<_> = res.into_iter().map(Result::unwrap).collect();
+    assert_eq!(res, vec![20, 30, 35]);
+}
+
+#[actix_rt::test]
+async fn test_scoped_concurrent_response() {
+    let addr = MyActor(0).start();
+    let res = futures_util::future::join_all(vec![
+        addr.send(Concurrent(40)),
+        addr.send(Concurrent(20)),
+        addr.send(Concurrent(1)),
+    ])
+    .await;
+    let res: Vec<_> = res.into_iter().map(|res| res.unwrap().unwrap()).collect();
+    // handled concurrently, shortest delay completes first
+    assert_eq!(res, vec![61, 21, 1]);
+}
+
+#[test]
+#[should_panic(expected = "outside of the scoped future")]
+fn test_scope_outside_of_future() {
+    let mut scope = None;
+    let _ = fut::scoped(|s: ActorScope<MyActor>| {
+        scope = Some(s);
+        async {}
+    });
+    scope.unwrap().with(|_, _| ());
+}
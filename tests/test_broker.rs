@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex};
+
+use actix::actors::broker::{Broker, BrokerIssue, BrokerSubscribe, Publish, Subscribe};
+use actix::actors::probe::TestProbe;
+use actix::prelude::*;
+use actix::testing::TestSystem;
+use tokio::time::Duration;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Event(usize);
+
+impl Message for Event {
+    type Result = ();
+}
+
+#[actix_rt::test]
+async fn test_broker_topics() {
+    let broker = Broker::from_registry();
+    let p1 = TestProbe::new();
+    let p2 = TestProbe::new();
+    let room = TestProbe::new();
+
+    broker
+        .send(Subscribe::new(p1.recipient::<Event>()))
+        .await
+        .unwrap();
+    broker
+        .send(Subscribe::new(p2.recipient::<Event>()))
+        .await
+        .unwrap();
+    // subscribing twice delivers message once
+    broker
+        .send(Subscribe::new(p2.recipient::<Event>()))
+        .await
+        .unwrap();
+    broker
+        .send(Subscribe::topic("room", room.recipient::<Event>()))
+        .await
+        .unwrap();
+
+    Broker::issue_async(Event(1));
+    broker.send(Publish::topic("room", Event(2))).await.unwrap();
+    broker.send(Publish::topic("hall", Event(3))).await.unwrap();
+
+    assert_eq!(p1.expect_msg::<Event>().await, Event(1));
+    assert_eq!(p2.expect_msg::<Event>().await, Event(1));
+    assert_eq!(room.expect_msg::<Event>().await, Event(2));
+    p1.expect_no_msg(Duration::from_millis(10)).await;
+    p2.expect_no_msg(Duration::from_millis(10)).await;
+    room.expect_no_msg(Duration::from_millis(10)).await;
+}
+
+type Log = Arc<Mutex<Vec<(&'static str, usize)>>>;
+
+struct Subscriber {
+    name: &'static str,
+    quit: bool,
+    log: Log,
+}
+
+impl Actor for Subscriber {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.subscribe::<Event>(ctx);
+    }
+}
+
+impl Handler<Event> for Subscriber {
+    type Result = ();
+
+    fn handle(&mut self, msg: Event, ctx: &mut Context<Self>) {
+        self.log.lock().unwrap().push((self.name, msg.0));
+        if self.quit {
+            ctx.stop();
+        }
+    }
+}
+
+struct Publisher;
+
+impl Actor for Publisher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.issue_sync(Event(1), ctx);
+        self.issue_sync(Event(2), ctx);
+        self.issue_async(Event(3));
+    }
+}
+
+#[test]
+fn test_broker_subscribers() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let l = Arc::clone(&log);
+
+    let mut sys = TestSystem::new();
+    sys.block_on(async move {
+        Subscriber {
+            name: "stays",
+            quit: false,
+            log: Arc::clone(&l),
+        }
+        .start();
+        Subscriber {
+            name: "quits",
+            quit: true,
+            log: l,
+        }
+        .start();
+    });
+    // subscriptions reach the broker
+    sys.run_until_idle().unwrap();
+
+    sys.block_on(async {
+        Publisher.start();
+    });
+    sys.run_until_idle().unwrap();
+
+    let mut log = log.lock().unwrap().clone();
+    log.sort();
+    // stopped subscriber does not get messages after the first one
+    assert_eq!(
+        log,
+        vec![("quits", 1), ("stays", 1), ("stays", 2), ("stays", 3)]
+    );
+}
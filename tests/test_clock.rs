@@ -0,0 +1,244 @@
+#![cfg(feature = "test-util")]
+use std::sync::{Arc, Mutex};
+
+use actix::clock::{self, delay_for, Duration, Instant};
+use actix::prelude::*;
+use actix::utils::{IntervalFunc, TimerFunc};
+use futures_channel::mpsc::{unbounded, UnboundedSender};
+use futures_util::stream::StreamExt;
+
+type Events = Arc<Mutex<Vec<(&'static str, u64)>>>;
+
+struct Timers {
+    start: Instant,
+    events: Events,
+}
+
+impl Timers {
+    fn record(&self, name: &'static str) {
+        let secs = (Instant::now() - self.start).as_secs();
+        self.events.lock().unwrap().push((name, secs));
+    }
+}
+
+impl Actor for Timers {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_later(Duration::from_secs(60), |act, _| act.record("run_later"));
+        ctx.run_interval(Duration::from_secs(25), |act, _| act.record("run_interval"));
+        ctx.notify_later(Tick, Duration::from_secs(30));
+        TimerFunc::new(Duration::from_secs(40), |act: &mut Self, _| {
+            act.record("timer_func")
+        })
+        .spawn(ctx);
+        ctx.spawn(
+            IntervalFunc::new(Duration::from_secs(45), |act: &mut Self, _| {
+                act.record("interval_func")
+            })
+            .finish(),
+        );
+        ctx.spawn(
+            delay_for(Duration::from_secs(3600))
+                .into_actor(self)
+                .timeout(Duration::from_secs(55))
+                .map(|res, act, _| {
+                    assert!(res.is_err());
+                    act.record("timeout")
+                }),
+        );
+    }
+}
+
+struct Tick;
+
+impl Message for Tick {
+    type Result = ();
+}
+
+impl Handler<Tick> for Timers {
+    type Result = ();
+
+    fn handle(&mut self, _: Tick, _: &mut Context<Self>) {
+        self.record("notify_later")
+    }
+}
+
+struct Slow;
+
+impl Message for Slow {
+    type Result = ();
+}
+
+impl Handler<Slow> for Timers {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _: Slow, _: &mut Context<Self>) -> Self::Result {
+        Box::pin(delay_for(Duration::from_secs(3600)).into_actor(self))
+    }
+}
+
+#[test]
+fn test_paused_clock_timers() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+    let real = std::time::Instant::now();
+
+    System::run(move || {
+        clock::pause();
+        let addr = Timers {
+            start: Instant::now(),
+            events: ev,
+        }
+        .start();
+
+        actix::spawn(async move {
+            // clock jumps to the next timer while arbiter is idle
+            delay_for(Duration::from_secs(75)).await;
+            let res = addr.send(Slow).timeout(Duration::from_secs(10)).await;
+            match res {
+                Err(MailboxError::Timeout) => (),
+                _ => panic!("Should not happen"),
+            }
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(real.elapsed() < Duration::from_secs(5));
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ("run_interval", 25),
+            ("notify_later", 30),
+            ("timer_func", 40),
+            ("interval_func", 45),
+            ("run_interval", 50),
+            ("timeout", 55),
+            ("run_later", 60),
+            ("run_interval", 75),
+        ]
+    );
+}
+
+#[test]
+fn test_advance_clock() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        clock::pause();
+        let start = Instant::now();
+        let ev2 = Arc::clone(&ev);
+        let _addr = Timers { start, events: ev }.start();
+
+        actix::spawn(async move {
+            clock::advance(Duration::from_secs(35)).await;
+            // expired timers fire once the timer driver is turned
+            delay_for(Duration::from_secs(0)).await;
+            let elapsed = Instant::now() - start;
+            assert!(elapsed >= Duration::from_secs(35));
+            assert!(elapsed < Duration::from_secs(36));
+            assert_eq!(
+                *ev2.lock().unwrap(),
+                vec![("run_interval", 35), ("notify_later", 35)]
+            );
+
+            clock::resume();
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_probe_paused_clock() {
+    let real = std::time::Instant::now();
+
+    System::run(|| {
+        clock::pause();
+        let mut probe = actix::actors::probe::TestProbe::new();
+        probe.set_timeout(Duration::from_secs(120));
+        let recipient = probe.recipient::<Tick>();
+
+        actix::spawn(async move {
+            probe.expect_no_msg(Duration::from_secs(3600)).await;
+
+            actix::spawn(async move {
+                delay_for(Duration::from_secs(60)).await;
+                recipient.do_send(Tick).unwrap();
+            });
+            let start = Instant::now();
+            probe.expect_msg::<Tick>().await;
+            assert_eq!((Instant::now() - start).as_secs(), 60);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(real.elapsed() < Duration::from_secs(5));
+}
+
+struct SyncTimers {
+    start: Instant,
+    events: UnboundedSender<(&'static str, u64)>,
+}
+
+impl SyncTimers {
+    fn record(&self, name: &'static str) {
+        let secs = (clock::now() - self.start).as_secs();
+        let _ = self.events.unbounded_send((name, secs));
+    }
+}
+
+impl Actor for SyncTimers {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, ctx: &mut SyncContext<Self>) {
+        ctx.run_later(Duration::from_secs(30), |act, _| act.record("run_later"));
+        ctx.notify_later(Tick, Duration::from_secs(60));
+    }
+}
+
+impl Handler<Tick> for SyncTimers {
+    type Result = ();
+
+    fn handle(&mut self, _: Tick, _: &mut SyncContext<Self>) {
+        self.record("notify_later")
+    }
+}
+
+impl Handler<Slow> for SyncTimers {
+    type Result = ();
+
+    fn handle(&mut self, _: Slow, _: &mut SyncContext<Self>) {}
+}
+
+#[test]
+fn test_paused_clock_sync_timers() {
+    let real = std::time::Instant::now();
+
+    System::run(|| {
+        clock::pause();
+        let start = clock::now();
+        let (tx, mut rx) = unbounded();
+        let addr = SyncArbiter::start(1, move || SyncTimers {
+            start,
+            events: tx.clone(),
+        });
+
+        actix::spawn(async move {
+            // actor is started and its timers are armed
+            addr.send(Slow).await.unwrap();
+
+            clock::advance(Duration::from_secs(45)).await;
+            assert_eq!(rx.next().await, Some(("run_later", 45)));
+            clock::advance(Duration::from_secs(30)).await;
+            assert_eq!(rx.next().await, Some(("notify_later", 75)));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(real.elapsed() < Duration::from_secs(5));
+}
@@ -69,7 +69,7 @@ impl Handler<TimeoutMessage> for MyActor {
 
     fn handle(&mut self, _: TimeoutMessage, _: &mut Self::Context) {
         if self.op != Op::Timeout {
-            assert!(false, "should not happen {:?}", self.op);
+            panic!("should not happen {:?}", self.op);
         }
         System::current().stop();
     }
@@ -458,3 +458,189 @@ fn test_cancel_completed_with_no_context_item() {
     })
     .unwrap();
 }
+
+struct Query(usize);
+
+impl Message for Query {
+    type Result = usize;
+}
+
+struct Ready;
+
+impl Message for Ready {
+    type Result = usize;
+}
+
+struct Stashing {
+    ready: bool,
+    handled: Arc<std::sync::Mutex<Vec<usize>>>,
+}
+
+impl Actor for Stashing {
+    type Context = Context<Self>;
+}
+
+impl Handler<Query> for Stashing {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Query, ctx: &mut Self::Context) -> usize {
+        if !self.ready {
+            ctx.stash(msg);
+            return 0;
+        }
+        self.handled.lock().unwrap().push(msg.0);
+        msg.0 * 10
+    }
+}
+
+impl Handler<Ready> for Stashing {
+    type Result = usize;
+
+    fn handle(&mut self, _: Ready, ctx: &mut Self::Context) -> usize {
+        self.ready = true;
+        let stashed = ctx.stashed();
+        ctx.unstash_all();
+        stashed
+    }
+}
+
+#[test]
+fn test_stash() {
+    let handled = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let h = Arc::clone(&handled);
+
+    System::run(move || {
+        let addr = Stashing {
+            ready: false,
+            handled: h,
+        }
+        .start();
+
+        let req1 = addr.send(Query(1));
+        addr.do_send(Query(2));
+        let ready = addr.send(Ready);
+        let req3 = addr.send(Query(3));
+
+        actix::spawn(async move {
+            // responses are sent once stashed messages are handled
+            assert_eq!(req1.await.unwrap(), 10);
+            assert_eq!(ready.await.unwrap(), 2);
+            assert_eq!(req3.await.unwrap(), 30);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    // stashed messages are handled ahead of mailbox
+    assert_eq!(*handled.lock().unwrap(), vec![1, 2, 3]);
+}
+
+struct StashRestart;
+
+impl Actor for StashRestart {
+    type Context = Context<Self>;
+}
+
+impl Supervised for StashRestart {}
+
+impl Handler<Query> for StashRestart {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Query, ctx: &mut Self::Context) -> usize {
+        ctx.stash(msg);
+        0
+    }
+}
+
+impl Handler<Ready> for StashRestart {
+    type Result = usize;
+
+    fn handle(&mut self, _: Ready, ctx: &mut Self::Context) -> usize {
+        ctx.stashed()
+    }
+}
+
+struct Restart;
+
+impl Message for Restart {
+    type Result = ();
+}
+
+impl Handler<Restart> for StashRestart {
+    type Result = ();
+
+    fn handle(&mut self, _: Restart, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+#[test]
+fn test_stash_dropped_on_restart() {
+    let result = Arc::new(std::sync::Mutex::new(None));
+    let res = Arc::clone(&result);
+
+    System::run(move || {
+        let addr = Supervisor::start(|_| StashRestart);
+        let req = addr.send(Query(1)).timeout(Duration::from_millis(100));
+        addr.do_send(Restart);
+        let ready = addr.send(Ready);
+
+        actix::spawn(async move {
+            let stashed = ready.await;
+            *res.lock().unwrap() = Some((stashed, req.await));
+            drop(addr);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    // restarted actor does not replay messages stashed before restart
+    let (stashed, req) = result.lock().unwrap().take().unwrap();
+    assert_eq!(stashed.unwrap(), 0);
+    match req {
+        Err(MailboxError::Closed) => (),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+struct StashOutsideHandler {
+    reason: Arc<std::sync::Mutex<Option<StopReason>>>,
+}
+
+impl Actor for StashOutsideHandler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_later(Duration::from_millis(0), |_, ctx| ctx.stash(Query(1)));
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        *self.reason.lock().unwrap() = ctx.stop_reason().cloned();
+        System::current().stop();
+    }
+}
+
+impl Handler<Query> for StashOutsideHandler {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Query, _: &mut Self::Context) -> usize {
+        msg.0
+    }
+}
+
+#[test]
+fn test_stash_outside_handler() {
+    let reason = Arc::new(std::sync::Mutex::new(None));
+    let r = Arc::clone(&reason);
+
+    System::run(move || {
+        StashOutsideHandler { reason: r }.start();
+    })
+    .unwrap();
+
+    let reason = reason.lock().unwrap().take();
+    match reason {
+        Some(StopReason::Panic(msg)) => assert!(msg.contains("stash")),
+        reason => panic!("Unexpected stop reason: {:?}", reason),
+    }
+}
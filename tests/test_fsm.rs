@@ -0,0 +1,257 @@
+use std::sync::{Arc, Mutex};
+
+use actix::fsm::{Fsm, StateMachine, Transition};
+use actix::prelude::*;
+use tokio::time::{delay_for, Duration};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Door {
+    Locked,
+    Open,
+}
+
+struct Lock {
+    fsm: Fsm<Door>,
+    events: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Lock {
+    fn new(events: Arc<Mutex<Vec<&'static str>>>) -> Self {
+        Lock {
+            fsm: Fsm::new(Door::Locked),
+            events,
+        }
+    }
+}
+
+impl Actor for Lock {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.init_state(ctx);
+    }
+}
+
+impl StateMachine for Lock {
+    type State = Door;
+
+    fn fsm(&mut self) -> &mut Fsm<Door> {
+        &mut self.fsm
+    }
+
+    fn state_entered(&mut self, _: &mut Context<Self>) -> Option<Duration> {
+        match self.fsm.state() {
+            Door::Open => Some(Duration::from_millis(50)),
+            Door::Locked => None,
+        }
+    }
+
+    fn state_timeout(&mut self, _: &mut Context<Self>) -> Transition<Door> {
+        self.events.lock().unwrap().push("timeout");
+        Transition::Next(Door::Locked)
+    }
+}
+
+struct Code(u32);
+
+impl Message for Code {
+    type Result = ();
+}
+
+impl Handler<Code> for Lock {
+    type Result = Transition<Door>;
+
+    fn handle(&mut self, msg: Code, _: &mut Context<Self>) -> Transition<Door> {
+        match self.fsm.state() {
+            Door::Locked if msg.0 == 1234 => Transition::Next(Door::Open),
+            _ => Transition::Keep,
+        }
+    }
+}
+
+struct Close;
+
+impl Message for Close {
+    type Result = ();
+}
+
+impl Handler<Close> for Lock {
+    type Result = Transition<Door>;
+
+    fn handle(&mut self, _: Close, _: &mut Context<Self>) -> Transition<Door> {
+        Transition::Next(Door::Locked)
+    }
+}
+
+struct Break;
+
+impl Message for Break {
+    type Result = ();
+}
+
+impl Handler<Break> for Lock {
+    type Result = Transition<Door>;
+
+    fn handle(&mut self, _: Break, _: &mut Context<Self>) -> Transition<Door> {
+        self.events.lock().unwrap().push("break");
+        Transition::Stop
+    }
+}
+
+struct Push;
+
+impl Message for Push {
+    type Result = Door;
+}
+
+impl Handler<Push> for Lock {
+    type Result = MessageResult<Push>;
+
+    fn handle(&mut self, msg: Push, ctx: &mut Context<Self>) -> Self::Result {
+        if *self.fsm.state() == Door::Locked {
+            self.postpone(msg, ctx);
+        } else {
+            self.events.lock().unwrap().push("push");
+        }
+        MessageResult(*self.fsm.state())
+    }
+}
+
+struct GetState;
+
+impl Message for GetState {
+    type Result = (Door, bool);
+}
+
+impl Handler<GetState> for Lock {
+    type Result = MessageResult<GetState>;
+
+    fn handle(&mut self, _: GetState, _: &mut Context<Self>) -> Self::Result {
+        MessageResult((*self.fsm.state(), self.fsm.timeout_armed()))
+    }
+}
+
+struct Knock;
+
+impl Message for Knock {
+    type Result = ();
+}
+
+impl Handler<Knock> for Lock {
+    type Result = ();
+
+    fn handle(&mut self, msg: Knock, ctx: &mut Context<Self>) {
+        // stashed by the actor itself, not by the state machine
+        self.events.lock().unwrap().push("knock");
+        ctx.stash(msg);
+    }
+}
+
+struct Queued;
+
+impl Message for Queued {
+    type Result = (usize, usize);
+}
+
+impl Handler<Queued> for Lock {
+    type Result = MessageResult<Queued>;
+
+    fn handle(&mut self, _: Queued, ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult((ctx.stashed(), self.postponed(ctx)))
+    }
+}
+
+#[test]
+fn test_fsm_state_timeout() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = Lock::new(ev).start();
+
+        // push is postponed until the door is open
+        let push = addr.send(Push);
+        addr.do_send(Code(1));
+        addr.do_send(Code(1234));
+
+        actix::spawn(async move {
+            assert_eq!(push.await.unwrap(), Door::Open);
+            assert_eq!(addr.send(GetState).await.unwrap(), (Door::Open, true));
+
+            delay_for(Duration::from_millis(100)).await;
+            assert_eq!(addr.send(GetState).await.unwrap(), (Door::Locked, false));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec!["push", "timeout"]);
+}
+
+#[test]
+fn test_fsm_timeout_cancelled() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = Lock::new(ev).start();
+        addr.do_send(Code(1234));
+        addr.do_send(Close);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(100)).await;
+            assert_eq!(addr.send(GetState).await.unwrap(), (Door::Locked, false));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(events.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_fsm_postpone_keeps_stash() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = Lock::new(ev).start();
+        addr.do_send(Knock);
+        let push = addr.send(Push);
+
+        actix::spawn(async move {
+            assert_eq!(addr.send(Queued).await.unwrap(), (1, 1));
+
+            addr.do_send(Code(1234));
+            assert_eq!(push.await.unwrap(), Door::Open);
+            assert_eq!(addr.send(Queued).await.unwrap(), (1, 0));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec!["knock", "push"]);
+}
+
+#[test]
+fn test_fsm_stop() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = Lock::new(ev).start();
+        addr.do_send(Code(1234));
+        let stop = addr.send(Break);
+        let state = addr.send(GetState);
+
+        actix::spawn(async move {
+            // response is sent once the transition is applied
+            stop.await.unwrap();
+            assert!(state.await.is_err());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(*events.lock().unwrap(), vec!["break"]);
+}
@@ -51,8 +51,12 @@ impl Actor for MyStreamActor {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         let mut s = futures_util::stream::FuturesOrdered::new();
-        s.push(delay_for(Duration::new(0, 5_000_000)));
-        s.push(delay_for(Duration::new(0, 5_000_000)));
+        // `push_back` requires newer futures-util than the minimum supported Rust version
+        #[allow(deprecated)]
+        {
+            s.push(delay_for(Duration::new(0, 5_000_000)));
+            s.push(delay_for(Duration::new(0, 5_000_000)));
+        }
 
         s.into_actor(self)
             .timeout(Duration::new(0, 1000))
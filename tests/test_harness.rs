@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use actix::prelude::*;
+use actix::testing::TestSystem;
+use tokio::time::Duration;
+
+struct Step;
+
+impl Message for Step {
+    type Result = ();
+}
+
+struct Chain {
+    steps: Arc<AtomicUsize>,
+}
+
+impl Actor for Chain {
+    type Context = Context<Self>;
+}
+
+impl Handler<Step> for Chain {
+    type Result = ();
+
+    fn handle(&mut self, _: Step, ctx: &mut Context<Self>) {
+        if self.steps.fetch_add(1, Ordering::SeqCst) < 4 {
+            ctx.run_later(Duration::from_millis(0), |_, ctx| ctx.notify(Step));
+        }
+    }
+}
+
+struct Worker {
+    handled: Arc<AtomicUsize>,
+}
+
+impl Actor for Worker {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Step> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Step, _: &mut SyncContext<Self>) {
+        thread::sleep(Duration::from_millis(5));
+        self.handled.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct Forever;
+
+impl Actor for Forever {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(Duration::from_millis(1), |_, _| ());
+    }
+}
+
+#[test]
+fn test_run_until_idle() {
+    let steps = Arc::new(AtomicUsize::new(0));
+    let handled = Arc::new(AtomicUsize::new(0));
+
+    let mut sys = TestSystem::new();
+    let s = Arc::clone(&steps);
+    let h = Arc::clone(&handled);
+    let (chain, worker) = sys.block_on(async move {
+        let chain = Chain { steps: s }.start();
+        let worker = SyncArbiter::start(2, move || Worker {
+            handled: Arc::clone(&h),
+        });
+        (chain, worker)
+    });
+
+    chain.do_send(Step);
+    for _ in 0..6 {
+        worker.do_send(Step);
+    }
+
+    sys.run_until_idle().unwrap();
+    assert_eq!(steps.load(Ordering::SeqCst), 5);
+    assert_eq!(handled.load(Ordering::SeqCst), 6);
+
+    // idle system returns immediately
+    sys.run_until_idle().unwrap();
+}
+
+#[test]
+fn test_run_until_idle_blocking_pool() {
+    let handled = Arc::new(AtomicUsize::new(0));
+
+    let mut sys = TestSystem::new();
+    let h = Arc::clone(&handled);
+    let worker = sys.block_on(async move {
+        SyncArbiter::builder(move || Worker {
+            handled: Arc::clone(&h),
+        })
+        .max_threads(2)
+        .blocking_pool()
+        .start()
+    });
+
+    // messages handed to the blocking pool are waited for before any
+    // actor of the pool exists
+    for round in 1..=3 {
+        for _ in 0..4 {
+            worker.do_send(Step);
+        }
+        sys.run_until_idle().unwrap();
+        assert_eq!(handled.load(Ordering::SeqCst), round * 4);
+    }
+}
+
+#[test]
+fn test_run_until_idle_busy() {
+    let mut sys = TestSystem::new();
+    sys.set_idle_timeout(Duration::from_millis(50));
+    let addr = sys.block_on(async { Forever.start() });
+
+    let busy = sys.run_until_idle().unwrap_err();
+    assert_eq!(busy.actors.len(), 1);
+    assert_eq!(busy.actors[0].id, addr.id());
+    assert!(busy.to_string().contains(std::any::type_name::<Forever>()));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_run_until_idle_paused_clock() {
+    struct Later(Arc<AtomicUsize>);
+
+    impl Actor for Later {
+        type Context = Context<Self>;
+
+        fn started(&mut self, ctx: &mut Context<Self>) {
+            ctx.run_later(Duration::from_secs(60), |act, _| {
+                act.0.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    let fired = Arc::new(AtomicUsize::new(0));
+
+    let mut sys = TestSystem::new();
+    let f = Arc::clone(&fired);
+    let _addr = sys.block_on(async move {
+        actix::clock::pause();
+        Later(f).start()
+    });
+
+    // pending timer does not keep the actor busy, the clock does not jump to it
+    sys.run_until_idle().unwrap();
+    assert_eq!(fired.load(Ordering::SeqCst), 0);
+}
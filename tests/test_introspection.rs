@@ -0,0 +1,87 @@
+use actix::introspection;
+use actix::prelude::*;
+use tokio::time::{delay_for, Duration};
+
+struct Ping;
+
+impl Message for Ping {
+    type Result = ();
+}
+
+struct Stuck;
+
+impl Actor for Stuck {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.wait(delay_for(Duration::from_secs(60)).into_actor(self));
+        ctx.spawn(delay_for(Duration::from_secs(60)).into_actor(self));
+    }
+}
+
+impl Handler<Ping> for Stuck {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {}
+}
+
+struct Idle;
+
+impl Actor for Idle {
+    type Context = Context<Self>;
+}
+
+struct SyncActor;
+
+impl Actor for SyncActor {
+    type Context = SyncContext<Self>;
+}
+
+#[test]
+fn test_introspection() {
+    introspection::enable();
+
+    System::run(|| {
+        let stuck = Stuck.start();
+        stuck.do_send(Ping);
+        stuck.do_send(Ping);
+        let idle = Idle.start();
+        let sync = SyncArbiter::start(2, || SyncActor);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(20)).await;
+
+            let actors = introspection::actors();
+            let info = actors.iter().find(|info| info.id == stuck.id()).unwrap();
+            assert_eq!(info.actor, std::any::type_name::<Stuck>());
+            assert_eq!(info.system, Some(System::current().id()));
+            assert_eq!(info.state, ActorState::Running);
+            assert_eq!(info.mailbox, 2);
+            assert_eq!(info.wait_futures, 1);
+            assert_eq!(info.spawned_futures, 1);
+
+            let workers: Vec<_> =
+                actors.iter().filter(|info| info.id == sync.id()).collect();
+            assert_eq!(workers.len(), 2);
+            assert!(workers.iter().all(|info| info.state == ActorState::Running));
+            assert_ne!(workers[0].thread, workers[1].thread);
+
+            let json = introspection::to_json();
+            assert!(json.starts_with('['));
+            assert!(json.contains(&info.to_json()));
+            assert!(info
+                .to_json()
+                .contains(&format!("\"actor\":\"{}\"", std::any::type_name::<Stuck>())));
+            assert!(info.to_json().contains("\"state\":\"Running\""));
+
+            // stopped actors are removed from registry
+            let id = idle.id();
+            drop(idle);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(introspection::actors().iter().all(|info| info.id != id));
+
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
@@ -268,3 +268,175 @@ fn test_stop_restore_after_stopping() {
     assert!(stopping.load(Ordering::Relaxed), "Not stopping");
     assert!(!stopped.load(Ordering::Relaxed), "Stopped");
 }
+
+type Events = Arc<Mutex<Vec<&'static str>>>;
+
+struct AsyncActor {
+    events: Events,
+    stopping_delay: Duration,
+    deadline: Option<Duration>,
+}
+
+impl AsyncActor {
+    fn new(events: Events) -> Self {
+        AsyncActor {
+            events,
+            stopping_delay: Duration::from_millis(20),
+            deadline: None,
+        }
+    }
+}
+
+impl Actor for AsyncActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.events.lock().unwrap().push("started");
+        if let Some(deadline) = self.deadline {
+            ctx.set_stopping_deadline(deadline);
+        }
+    }
+
+    fn started_async(
+        &mut self,
+        _: &mut Self::Context,
+    ) -> Option<ResponseActFuture<Self, ()>> {
+        Some(Box::pin(
+            delay_for(Duration::from_millis(20))
+                .into_actor(self)
+                .map(|_, act, _| act.events.lock().unwrap().push("started_async")),
+        ))
+    }
+
+    fn stopping_async(
+        &mut self,
+        _: &mut Self::Context,
+    ) -> Option<ResponseActFuture<Self, ()>> {
+        Some(Box::pin(
+            delay_for(self.stopping_delay)
+                .into_actor(self)
+                .map(|_, act, ctx| {
+                    assert_eq!(ctx.state(), ActorState::Stopping);
+                    act.events.lock().unwrap().push("stopping_async");
+                }),
+        ))
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.events.lock().unwrap().push("stopped");
+    }
+}
+
+impl Supervised for AsyncActor {
+    fn restarting(&mut self, _: &mut Self::Context) {
+        self.events.lock().unwrap().push("restarting");
+    }
+}
+
+struct Event(&'static str);
+
+impl Message for Event {
+    type Result = ();
+}
+
+impl Handler<Event> for AsyncActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Event, ctx: &mut Self::Context) {
+        self.events.lock().unwrap().push(msg.0);
+        if msg.0 == "stop" {
+            ctx.stop();
+        }
+    }
+}
+
+#[test]
+fn test_async_lifecycle() {
+    let events = Events::default();
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = AsyncActor::new(ev).start();
+        addr.do_send(Event("message"));
+        addr.do_send(Event("stop"));
+        // mailbox is not polled while stopping future runs
+        addr.do_send(Event("ignored"));
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(100)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "started",
+            "started_async",
+            "message",
+            "stop",
+            "stopping_async",
+            "stopped"
+        ]
+    );
+}
+
+#[test]
+fn test_async_stopping_deadline() {
+    let events = Events::default();
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let mut act = AsyncActor::new(ev);
+        act.stopping_delay = Duration::from_secs(10);
+        act.deadline = Some(Duration::from_millis(20));
+        let addr = act.start();
+        addr.do_send(Event("stop"));
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(100)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec!["started", "started_async", "stop", "stopped"]
+    );
+}
+
+#[test]
+fn test_async_lifecycle_restart() {
+    let events = Events::default();
+    let ev = Arc::clone(&events);
+
+    System::run(move || {
+        let addr = Supervisor::start(move |_| AsyncActor::new(ev));
+        addr.do_send(Event("stop"));
+        addr.do_send(Event("message"));
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(150)).await;
+            drop(addr);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(
+        &events.lock().unwrap()[..8],
+        &[
+            "started",
+            "started_async",
+            "stop",
+            "stopping_async",
+            "stopped",
+            "restarting",
+            "started",
+            "started_async",
+        ]
+    );
+    assert_eq!(events.lock().unwrap()[8], "message");
+}
@@ -0,0 +1,251 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use actix::metrics::{self, InMemoryMetrics};
+use actix::prelude::*;
+use tokio::time::{delay_for, Duration};
+
+struct Ping;
+
+impl Message for Ping {
+    type Result = ();
+}
+
+struct Die;
+
+impl Message for Die {
+    type Result = ();
+}
+
+struct MyActor;
+
+impl Actor for MyActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.spawn(delay_for(Duration::from_secs(60)).into_actor(self));
+    }
+}
+
+impl Supervised for MyActor {}
+
+impl Handler<Ping> for MyActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+impl Handler<Die> for MyActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Die, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+struct Ready;
+
+impl Message for Ready {
+    type Result = ();
+}
+
+#[derive(Default)]
+struct StashActor {
+    ready: bool,
+}
+
+impl Actor for StashActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<Ping> for StashActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Ping, ctx: &mut Self::Context) {
+        if !self.ready {
+            ctx.stash(msg);
+        }
+    }
+}
+
+impl Handler<Ready> for StashActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ready, ctx: &mut Self::Context) {
+        self.ready = true;
+        ctx.unstash_all();
+    }
+}
+
+struct SyncActor(Arc<AtomicUsize>);
+
+impl Actor for SyncActor {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, _: &mut Self::Context) {
+        if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("start failure");
+        }
+    }
+}
+
+impl Handler<Ping> for SyncActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {}
+}
+
+fn supervised_metrics() {
+    let sink = Arc::new(InMemoryMetrics::new());
+    metrics::set_sink(sink.clone());
+
+    let sink2 = Arc::clone(&sink);
+
+    System::run(move || {
+        let addr = Supervisor::start(|_| MyActor);
+        for _ in 0..3 {
+            addr.do_send(Ping);
+        }
+        addr.do_send(Die);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(50)).await;
+            let m = sink2.actor(addr.id()).unwrap();
+            assert_eq!(m.restarts, 1);
+            assert_eq!(m.queue_length, 0);
+            assert_eq!(m.spawned_futures, 1);
+
+            drop(addr);
+            delay_for(Duration::from_millis(10)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    metrics::clear_sink();
+}
+
+fn sync_metrics(blocking: bool) {
+    let sink = Arc::new(InMemoryMetrics::new());
+    metrics::set_sink(sink.clone());
+
+    System::run(move || {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let mut builder = SyncArbiter::builder(move || SyncActor(Arc::clone(&starts)));
+        if blocking {
+            builder = builder.blocking_pool();
+        }
+        let addr = builder.start();
+
+        actix::spawn(async move {
+            // blocking pool actor is started by the first message
+            let _ = addr.send(Ping).await;
+            delay_for(Duration::from_millis(50)).await;
+            addr.send(Ping).await.unwrap();
+
+            let m = sink.actor(addr.id()).unwrap();
+            assert_eq!(m.restarts, 1);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    metrics::clear_sink();
+}
+
+fn message_metrics() {
+    let sink = Arc::new(InMemoryMetrics::new());
+
+    System::run(move || {
+        // sink is installed after messages were sent
+        let addr = MyActor.start();
+        addr.do_send(Ping);
+        metrics::set_sink(sink.clone());
+        addr.do_send(Ping);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(20)).await;
+            let m = sink.actor(addr.id()).unwrap();
+            assert_eq!(m.actor, std::any::type_name::<MyActor>());
+            let ping = &m.messages[std::any::type_name::<Ping>()];
+            assert_eq!(ping.handled, 2);
+            assert_eq!(ping.latency_count, 1);
+            assert!(ping.duration_sum >= Duration::from_millis(2));
+            assert!(ping.duration_max >= Duration::from_millis(1));
+
+            let text = sink.render_prometheus();
+            // series are aggregated by actor type
+            let labels = format!(
+                "actor=\"{}\",message=\"{}\"",
+                std::any::type_name::<MyActor>(),
+                std::any::type_name::<Ping>()
+            );
+            assert!(!text.contains("id=\""));
+            assert!(text.contains("# TYPE actix_messages_handled_total counter\n"));
+            assert!(text
+                .contains(&format!("actix_messages_handled_total{{{}}} 2\n", labels)));
+            assert!(text.contains(&format!(
+                "actix_message_latency_seconds_count{{{}}} 1\n",
+                labels
+            )));
+            assert!(text.contains("# TYPE actix_handler_duration_seconds_max gauge\n"));
+            let max = format!("actix_handler_duration_seconds_max{{{}}} ", labels);
+            let max = text
+                .lines()
+                .find(|line| line.starts_with(&max))
+                .map(|line| line[max.len()..].parse::<f64>().unwrap())
+                .unwrap();
+            assert!((max - ping.duration_max.as_secs_f64()).abs() < 1e-9);
+
+            // metrics are removed once actor's mailbox is dropped
+            addr.do_send(Die);
+            let id = addr.id();
+            drop(addr);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(sink.actor(id).is_none());
+            // counters of stopped actor are kept
+            let text = sink.render_prometheus();
+            assert!(text
+                .contains(&format!("actix_messages_handled_total{{{}}} 2\n", labels)));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    metrics::clear_sink();
+}
+
+fn unstash_metrics() {
+    let sink = Arc::new(InMemoryMetrics::new());
+    metrics::set_sink(sink.clone());
+
+    System::run(move || {
+        let addr = StashActor::default().start();
+        addr.do_send(Ping);
+        addr.do_send(Ready);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(20)).await;
+            let m = sink.actor(addr.id()).unwrap();
+            // stashed message is counted again once replayed by unstash
+            assert_eq!(m.messages[std::any::type_name::<Ping>()].handled, 2);
+            assert_eq!(m.messages[std::any::type_name::<Ready>()].handled, 1);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    metrics::clear_sink();
+}
+
+// sink is global, scenarios must not run concurrently
+#[test]
+fn test_metrics() {
+    supervised_metrics();
+    sync_metrics(false);
+    sync_metrics(true);
+    message_metrics();
+    unstash_metrics();
+}
@@ -0,0 +1,190 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use actix::actors::mocker::Mocker;
+use actix::prelude::*;
+
+#[derive(Default)]
+struct Database;
+
+impl Actor for Database {
+    type Context = Context<Self>;
+}
+
+impl Supervised for Database {}
+impl SystemService for Database {}
+
+#[derive(Default)]
+struct Cache;
+
+impl Actor for Cache {
+    type Context = Context<Self>;
+}
+
+impl Supervised for Cache {}
+impl ArbiterService for Cache {}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Get(u32);
+
+impl Message for Get {
+    type Result = Option<u32>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Put(u32);
+
+impl Message for Put {
+    type Result = ();
+}
+
+#[test]
+fn test_mocker_system_service() {
+    let mocker = Mocker::<Database>::new()
+        .on(|_: &Put| ())
+        .times(2)
+        .on(|msg: &Get| if msg.0 == 1 { Some(10) } else { None })
+        .times(2)
+        .in_order();
+    let calls = mocker.calls();
+    let calls2 = calls.clone();
+
+    System::run(move || {
+        mocker.install_system_service();
+
+        actix::spawn(async move {
+            let db = Mocker::<Database>::from_registry();
+            db.send(Put(1)).await.unwrap();
+            db.send(Put(2)).await.unwrap();
+            assert_eq!(db.send(Get(1)).await.unwrap(), Some(10));
+            assert_eq!(db.send(Get(2)).await.unwrap(), None);
+
+            assert_eq!(calls2.len(), 4);
+            assert_eq!(calls2.count::<Put>(), 2);
+            assert_eq!(calls2.messages::<Get>(), vec![Get(1), Get(2)]);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(
+        calls.types(),
+        vec![
+            std::any::type_name::<Put>(),
+            std::any::type_name::<Put>(),
+            std::any::type_name::<Get>(),
+            std::any::type_name::<Get>(),
+        ]
+    );
+}
+
+#[test]
+fn test_mocker_arbiter_service() {
+    System::run(|| {
+        Mocker::<Cache>::new()
+            .on(|msg: &Get| Some(msg.0 + 1))
+            .install_arbiter_service();
+
+        actix::spawn(async move {
+            let cache = Mocker::<Cache>::from_registry();
+            assert_eq!(cache.send(Get(1)).await.unwrap(), Some(2));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_mocker_unexpected_message() {
+    System::run(|| {
+        let db = Mocker::<Database>::new().on(|_: &Put| ()).start();
+
+        actix::spawn(async move {
+            let res = db.send(Get(1)).await;
+            match res {
+                Err(MailboxError::Panicked) => (),
+                _ => panic!("Should not happen"),
+            }
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_mocker_verify() {
+    let mocker = Mocker::<Database>::new().on(|_: &Put| ()).times(1);
+    let calls = mocker.calls();
+    assert!(calls.is_empty());
+
+    let res = catch_unwind(AssertUnwindSafe(|| calls.verify()));
+    assert!(res.is_err());
+
+    // expectations are verified once
+    drop(mocker);
+
+    let mocker = Mocker::<Database>::new()
+        .on(|_: &Put| ())
+        .times(1)
+        .on(|_: &Get| None)
+        .times(1)
+        .in_order();
+    let calls = mocker.calls();
+    let verified = Arc::new(Mutex::new(None));
+    let v = Arc::clone(&verified);
+    System::run(move || {
+        let db = mocker.start();
+        actix::spawn(async move {
+            db.send(Get(1)).await.unwrap();
+            db.send(Put(1)).await.unwrap();
+            let res = catch_unwind(AssertUnwindSafe(|| calls.verify()));
+            *v.lock().unwrap() = Some((res.is_err(), calls.len()));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+    assert_eq!(verified.lock().unwrap().take(), Some((true, 2)));
+}
+
+#[test]
+fn test_mocker_drop_unverified() {
+    let mocker = Mocker::<Database>::new().on(|_: &Put| ()).times(1);
+    let calls = mocker.calls();
+
+    let res = catch_unwind(AssertUnwindSafe(move || drop(mocker)));
+    assert!(res.is_err());
+    assert!(calls.is_empty());
+
+    // mocker dropped by a panicking thread does not panic again
+    let mocker = Mocker::<Database>::new().on(|_: &Put| ()).times(1);
+    let res = catch_unwind(AssertUnwindSafe(move || {
+        let _mocker = mocker;
+        panic!("test failed");
+    }));
+    assert!(res.is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_mocker_mock() {
+    let mocker = Mocker::<Database>::mock(Box::new(|msg, _| {
+        let msg = msg.downcast::<Get>().unwrap();
+        Box::new(Some(Some(msg.0 + 1)))
+    }))
+    .on(|_: &Put| ());
+    let calls = mocker.calls();
+
+    System::run(move || {
+        let db = mocker.start();
+        actix::spawn(async move {
+            // typed handlers take precedence over the mock closure
+            db.send(Put(1)).await.unwrap();
+            assert_eq!(db.send(Get(1)).await.unwrap(), Some(2));
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(calls.count::<Get>(), 1);
+    assert!(calls.messages::<Get>().is_empty());
+}
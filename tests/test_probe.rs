@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use actix::actors::probe::TestProbe;
+use actix::prelude::*;
+use tokio::time::Duration;
+
+#[derive(Debug, PartialEq)]
+struct Ping(usize);
+
+impl Message for Ping {
+    type Result = usize;
+}
+
+#[derive(Debug, PartialEq)]
+struct Event(&'static str);
+
+impl Message for Event {
+    type Result = ();
+}
+
+struct Forwarder {
+    recipient: Recipient<Event>,
+}
+
+impl Actor for Forwarder {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Self::Context) {
+        let _ = self.recipient.do_send(Event("started"));
+    }
+}
+
+impl Handler<Ping> for Forwarder {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Ping, _: &mut Self::Context) -> usize {
+        let _ = self.recipient.do_send(Event("ping"));
+        msg.0
+    }
+}
+
+#[actix_rt::test]
+async fn test_probe_expect_msg() {
+    let probe = TestProbe::new();
+    let addr = Forwarder {
+        recipient: probe.recipient(),
+    }
+    .start();
+
+    assert_eq!(addr.send(Ping(1)).await.unwrap(), 1);
+    assert_eq!(probe.expect_msg::<Event>().await, Event("started"));
+    assert_eq!(probe.expect_msg::<Event>().await, Event("ping"));
+    assert!(probe.is_empty());
+
+    addr.do_send(Ping(2));
+    addr.do_send(Ping(3));
+    assert_eq!(
+        probe.receive_n::<Event>(2).await,
+        vec![Event("ping"), Event("ping")]
+    );
+    probe.expect_no_msg(Duration::from_millis(10)).await;
+}
+
+#[actix_rt::test]
+async fn test_probe_reply() {
+    let probe = TestProbe::new();
+    let recipient = probe.recipient::<Ping>();
+
+    // no reply, response channel is dropped
+    match recipient.send(Ping(1)).await {
+        Err(MailboxError::Closed) => (),
+        _ => panic!("Should not happen"),
+    }
+
+    probe.reply(|msg: &Ping| msg.0 * 2);
+    assert_eq!(recipient.send(Ping(2)).await.unwrap(), 4);
+    assert_eq!(probe.receive_n::<Ping>(2).await, vec![Ping(1), Ping(2)]);
+}
+
+#[actix_rt::test]
+async fn test_probe_reply_inspects_probe() {
+    let probe = Rc::new(TestProbe::new());
+    let recipient = probe.recipient::<Ping>();
+
+    // reply may access the probe, received message is queued after reply
+    let weak = Rc::downgrade(&probe);
+    probe.reply(move |msg: &Ping| {
+        let probe = weak.upgrade().unwrap();
+        let res = msg.0 + probe.len();
+        if res > 10 {
+            probe.reply(|_: &Ping| 0);
+        }
+        res
+    });
+    assert_eq!(recipient.send(Ping(1)).await.unwrap(), 1);
+    assert_eq!(recipient.send(Ping(10)).await.unwrap(), 11);
+    // reply replaced by the reply itself
+    assert_eq!(recipient.send(Ping(10)).await.unwrap(), 0);
+    assert_eq!(probe.len(), 3);
+}
+
+#[actix_rt::test]
+#[should_panic(expected = "expected message")]
+async fn test_probe_unexpected_type() {
+    let probe = TestProbe::new();
+    probe.recipient().do_send(Event("event")).unwrap();
+    probe.expect_msg::<Ping>().await;
+}
+
+#[actix_rt::test]
+#[should_panic(expected = "received unexpected message")]
+async fn test_probe_expect_no_msg() {
+    let probe = TestProbe::new();
+    probe.recipient().do_send(Event("event")).unwrap();
+    probe.expect_no_msg(Duration::from_millis(10)).await;
+}
+
+#[actix_rt::test]
+#[should_panic(expected = "timeout")]
+async fn test_probe_timeout() {
+    let mut probe = TestProbe::new();
+    probe.set_timeout(Duration::from_millis(10));
+    probe.expect_msg::<Event>().await;
+}
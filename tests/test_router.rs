@@ -0,0 +1,272 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use actix::actors::router::{Resizer, Router, Routing};
+use actix::prelude::*;
+use futures_util::future::join;
+use tokio::time::{delay_for, Duration};
+
+struct Job;
+
+impl Message for Job {
+    type Result = usize;
+}
+
+struct Pin(u32);
+
+impl Message for Pin {
+    type Result = usize;
+}
+
+#[derive(Clone)]
+struct Reload;
+
+impl Message for Reload {
+    type Result = ();
+}
+
+struct Stop;
+
+impl Message for Stop {
+    type Result = ();
+}
+
+struct Panic;
+
+impl Message for Panic {
+    type Result = ();
+}
+
+struct ThreadId;
+
+impl Message for ThreadId {
+    type Result = thread::ThreadId;
+}
+
+#[derive(Clone, Default)]
+struct Counters {
+    started: Arc<AtomicUsize>,
+    stopped: Arc<AtomicUsize>,
+    reloaded: Arc<AtomicUsize>,
+}
+
+impl Counters {
+    fn worker(&self) -> Worker {
+        Worker {
+            id: self.started.fetch_add(1, Ordering::SeqCst),
+            counters: self.clone(),
+        }
+    }
+}
+
+struct Worker {
+    id: usize,
+    counters: Counters,
+}
+
+impl Actor for Worker {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _: &mut Context<Self>) {
+        self.counters.stopped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Handler<Job> for Worker {
+    type Result = usize;
+
+    fn handle(&mut self, _: Job, _: &mut Context<Self>) -> usize {
+        self.id
+    }
+}
+
+impl Handler<Pin> for Worker {
+    type Result = usize;
+
+    fn handle(&mut self, _: Pin, _: &mut Context<Self>) -> usize {
+        self.id
+    }
+}
+
+impl Handler<Reload> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Reload, _: &mut Context<Self>) {
+        self.counters.reloaded.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Handler<Stop> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+impl Handler<Panic> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Panic, _: &mut Context<Self>) {
+        panic!("routee panicked");
+    }
+}
+
+impl Handler<ThreadId> for Worker {
+    type Result = MessageResult<ThreadId>;
+
+    fn handle(&mut self, _: ThreadId, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(thread::current().id())
+    }
+}
+
+#[actix_rt::test]
+async fn test_round_robin() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(3, move || c.worker()).start();
+
+    let mut ids = Vec::new();
+    for _ in 0..6 {
+        ids.push(router.send(Job).await.unwrap());
+    }
+    assert_eq!(ids, vec![0, 1, 2, 0, 1, 2]);
+
+    // recipients of the router are routed too
+    let recipient = router.recipient::<Job>();
+    assert_eq!(recipient.send(Job).await.unwrap(), 0);
+}
+
+#[actix_rt::test]
+async fn test_random() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(3, move || c.worker())
+        .routing(Routing::Random)
+        .start();
+
+    for _ in 0..20 {
+        assert!(router.send(Job).await.unwrap() < 3);
+    }
+}
+
+#[actix_rt::test]
+async fn test_consistent_hash_and_smallest_mailbox() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(3, move || c.worker())
+        .routing(Routing::SmallestMailbox)
+        .consistent_hash(|msg: &Pin| msg.0)
+        .start();
+
+    let pinned = router.send(Pin(7)).await.unwrap();
+    for _ in 0..5 {
+        assert_eq!(router.send(Pin(7)).await.unwrap(), pinned);
+    }
+
+    // pinned routee has the largest mailbox
+    for _ in 0..3 {
+        router.do_send(Pin(7));
+    }
+    let (a, b) = join(router.send(Job), router.send(Job)).await;
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert_ne!(a, pinned);
+    assert_ne!(b, pinned);
+    assert_ne!(a, b);
+}
+
+#[actix_rt::test]
+async fn test_broadcast() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(3, move || c.worker())
+        .broadcast::<Reload>()
+        .start();
+
+    router.send(Reload).await.unwrap();
+    // routees handle messages in order
+    for _ in 0..3 {
+        router.send(Job).await.unwrap();
+    }
+    assert_eq!(counters.reloaded.load(Ordering::SeqCst), 3);
+}
+
+#[actix_rt::test]
+async fn test_replace_stopped_routee() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(2, move || c.worker()).start();
+
+    router.send(Stop).await.unwrap();
+    delay_for(Duration::from_millis(10)).await;
+    assert_eq!(counters.stopped.load(Ordering::SeqCst), 1);
+    // replaced without waiting for the next message
+    assert_eq!(counters.started.load(Ordering::SeqCst), 3);
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        ids.push(router.send(Job).await.unwrap());
+    }
+    ids.sort();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(counters.started.load(Ordering::SeqCst), 3);
+}
+
+#[actix_rt::test]
+async fn test_routee_error() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(1, move || c.worker()).start();
+
+    match router.send(Panic).await {
+        Err(MailboxError::Panicked) => (),
+        _ => panic!("routee panic is not forwarded"),
+    }
+
+    delay_for(Duration::from_millis(10)).await;
+    assert_eq!(router.send(Job).await.unwrap(), 1);
+}
+
+#[actix_rt::test]
+async fn test_resizer() {
+    let counters = Counters::default();
+    let c = counters.clone();
+    let router = Router::new(1, move || c.worker())
+        .resizer(Resizer::new(1, 3).interval(Duration::from_millis(5)))
+        .start();
+
+    for _ in 0..5 {
+        router.do_send(Job);
+    }
+    router.send(Job).await.unwrap();
+    assert_eq!(counters.started.load(Ordering::SeqCst), 3);
+
+    delay_for(Duration::from_millis(100)).await;
+    assert_eq!(counters.stopped.load(Ordering::SeqCst), 2);
+    // removed routees are not replaced
+    assert_eq!(counters.started.load(Ordering::SeqCst), 3);
+    assert_eq!(router.send(Job).await.unwrap(), 0);
+}
+
+#[test]
+fn test_arbiters() {
+    let counters = Counters::default();
+    let c = counters.clone();
+
+    let mut sys = System::new("test");
+    let ids = sys.block_on(async move {
+        let arbiters = vec![Arbiter::new(), Arbiter::new()];
+        let router = Router::new(2, move || c.worker())
+            .arbiters(arbiters)
+            .start();
+
+        let a = router.send(ThreadId).await.unwrap();
+        let b = router.send(ThreadId).await.unwrap();
+        (a, b)
+    });
+
+    assert_ne!(ids.0, ids.1);
+    assert_ne!(ids.0, thread::current().id());
+    assert_ne!(ids.1, thread::current().id());
+}
@@ -2,6 +2,9 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use actix::prelude::*;
+#[cfg(feature = "test-util")]
+use actix::{clock, SupervisorStrategy};
+use actix::{ChildGroup, RestartPolicy};
 use tokio::time::{delay_for, Duration};
 
 struct Die;
@@ -64,3 +67,475 @@ fn test_supervisor_restart() {
     assert_eq!(restarts.load(Ordering::Relaxed), 2);
     assert_eq!(messages.load(Ordering::Relaxed), 2);
 }
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_supervisor_max_restarts() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let messages = Arc::new(AtomicUsize::new(0));
+    let escalated = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+    let restarts2 = Arc::clone(&restarts);
+    let messages2 = Arc::clone(&messages);
+    let escalated2 = Arc::clone(&escalated);
+
+    System::run(move || {
+        clock::pause();
+        let strategy = SupervisorStrategy::new()
+            .max_restarts(2, Duration::from_secs(10))
+            .on_escalate(move |esc| {
+                escalated2.store(esc.restarts, Ordering::Relaxed);
+            });
+        let addr = actix::Supervisor::start_with(strategy, move |_| {
+            MyActor(starts2, restarts2, messages2)
+        });
+        for _ in 0..5 {
+            addr.do_send(Die);
+        }
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(100)).await;
+            assert!(!addr.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(starts.load(Ordering::Relaxed), 3);
+    assert_eq!(restarts.load(Ordering::Relaxed), 2);
+    assert_eq!(messages.load(Ordering::Relaxed), 3);
+    assert_eq!(escalated.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_supervisor_backoff() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let messages = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+    let restarts2 = Arc::clone(&restarts);
+    let messages2 = Arc::clone(&messages);
+    let starts3 = Arc::clone(&starts);
+
+    System::run(move || {
+        clock::pause();
+        let strategy = SupervisorStrategy::new()
+            .backoff(Duration::from_secs(10), Duration::from_secs(60));
+        let addr = actix::Supervisor::start_with(strategy, move |_| {
+            MyActor(starts2, restarts2, messages2)
+        });
+        addr.do_send(Die);
+
+        actix::spawn(async move {
+            // actor is not restarted before backoff delay elapses
+            delay_for(Duration::from_secs(9)).await;
+            assert_eq!(starts3.load(Ordering::Relaxed), 1);
+            delay_for(Duration::from_secs(2)).await;
+            assert_eq!(starts3.load(Ordering::Relaxed), 2);
+            assert!(addr.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(starts.load(Ordering::Relaxed), 2);
+    assert_eq!(restarts.load(Ordering::Relaxed), 1);
+    assert_eq!(messages.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_supervisor_backoff_reset() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let messages = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+    let restarts2 = Arc::clone(&restarts);
+    let messages2 = Arc::clone(&messages);
+    let starts3 = Arc::clone(&starts);
+
+    System::run(move || {
+        clock::pause();
+        let strategy = SupervisorStrategy::new()
+            .backoff(Duration::from_secs(10), Duration::from_secs(30));
+        let addr = actix::Supervisor::start_with(strategy, move |_| {
+            MyActor(starts2, restarts2, messages2)
+        });
+        addr.do_send(Die);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_secs(15)).await;
+            assert_eq!(starts3.load(Ordering::Relaxed), 2);
+
+            // after a healthy run restart is delayed by minimal backoff again
+            delay_for(Duration::from_secs(40)).await;
+            addr.do_send(Die);
+            delay_for(Duration::from_secs(9)).await;
+            assert_eq!(starts3.load(Ordering::Relaxed), 2);
+            delay_for(Duration::from_secs(2)).await;
+            assert_eq!(starts3.load(Ordering::Relaxed), 3);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(starts.load(Ordering::Relaxed), 3);
+    assert_eq!(restarts.load(Ordering::Relaxed), 2);
+}
+
+struct Child(&'static str, Arc<Mutex<Vec<String>>>);
+
+impl Actor for Child {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Context<Self>) {
+        self.1.lock().unwrap().push(format!("{} started", self.0));
+    }
+}
+
+impl actix::Supervised for Child {
+    fn restarting(&mut self, _: &mut Context<Self>) {
+        self.1
+            .lock()
+            .unwrap()
+            .push(format!("{} restarting", self.0));
+    }
+}
+
+impl actix::Handler<Die> for Child {
+    type Result = ();
+
+    fn handle(&mut self, _: Die, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+struct OtherChild(Arc<Mutex<Vec<String>>>);
+
+impl Actor for OtherChild {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Context<Self>) {
+        self.0.lock().unwrap().push("other started".to_owned());
+    }
+}
+
+impl actix::Supervised for OtherChild {
+    fn restarting(&mut self, _: &mut Context<Self>) {
+        self.0.lock().unwrap().push("other restarting".to_owned());
+    }
+}
+
+struct Ping;
+
+impl Message for Ping {
+    type Result = ();
+}
+
+impl actix::Handler<Ping> for OtherChild {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Context<Self>) {}
+}
+
+fn run_group(policy: RestartPolicy) -> Vec<String> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = Arc::clone(&events);
+
+    System::run(move || {
+        let mut group = ChildGroup::new(policy);
+        let ev = Arc::clone(&events2);
+        let first = group.child(move |_| Child("first", ev));
+        let ev = Arc::clone(&events2);
+        let second = group.child(move |_| Child("second", ev));
+        let ev = Arc::clone(&events2);
+        let other = group.child(move |_| OtherChild(ev));
+        group.start();
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            events2.lock().unwrap().clear();
+            second.do_send(Die);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(first.connected());
+            assert!(other.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let events = events.lock().unwrap();
+    events.clone()
+}
+
+#[test]
+fn test_group_one_for_one() {
+    assert_eq!(
+        run_group(RestartPolicy::OneForOne),
+        vec!["second restarting", "second started"]
+    );
+}
+
+#[test]
+fn test_group_one_for_all() {
+    assert_eq!(
+        run_group(RestartPolicy::OneForAll),
+        vec![
+            "first restarting",
+            "second restarting",
+            "other restarting",
+            "first started",
+            "second started",
+            "other started",
+        ]
+    );
+}
+
+#[test]
+fn test_group_rest_for_one() {
+    assert_eq!(
+        run_group(RestartPolicy::RestForOne),
+        vec![
+            "second restarting",
+            "other restarting",
+            "second started",
+            "other started",
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_nested_group() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = Arc::clone(&events);
+
+    System::run(move || {
+        clock::pause();
+        let mut nested = ChildGroup::new(RestartPolicy::OneForAll).strategy(
+            SupervisorStrategy::new().max_restarts(1, Duration::from_secs(10)),
+        );
+        let ev = Arc::clone(&events2);
+        let first = nested.child(move |_| Child("first", ev));
+        let ev = Arc::clone(&events2);
+        let second = nested.child(move |_| Child("second", ev));
+
+        let mut root = ChildGroup::new(RestartPolicy::OneForOne);
+        root.group(nested);
+        let ev = Arc::clone(&events2);
+        let other = root.child(move |_| OtherChild(ev));
+        root.start();
+
+        actix::spawn(async move {
+            delay_for(Duration::from_secs(1)).await;
+            first.do_send(Die);
+            delay_for(Duration::from_secs(1)).await;
+            assert_eq!(events2.lock().unwrap().len(), 7);
+
+            // restart budget of nested group is exhausted,
+            // root group restarts it with a fresh budget
+            second.do_send(Die);
+            delay_for(Duration::from_secs(1)).await;
+            first.do_send(Die);
+            delay_for(Duration::from_secs(1)).await;
+            assert!(first.connected());
+            assert!(second.connected());
+            assert!(other.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let restarted = [
+        "first restarting",
+        "second restarting",
+        "first started",
+        "second started",
+    ];
+    let events = events.lock().unwrap();
+    assert_eq!(
+        events[..3],
+        ["first started", "second started", "other started"]
+    );
+    assert_eq!(events[3..7], restarted);
+    assert_eq!(events[7..11], restarted);
+    assert_eq!(events[11..], restarted);
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_group_backoff_does_not_block_siblings() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = Arc::clone(&events);
+
+    System::run(move || {
+        clock::pause();
+        let mut group = ChildGroup::new(RestartPolicy::OneForOne).strategy(
+            SupervisorStrategy::new()
+                .backoff(Duration::from_secs(10), Duration::from_secs(60)),
+        );
+        let ev = Arc::clone(&events2);
+        let first = group.child(move |_| Child("first", ev));
+        let ev = Arc::clone(&events2);
+        let other = group.child(move |_| OtherChild(ev));
+        group.start();
+
+        actix::spawn(async move {
+            delay_for(Duration::from_secs(1)).await;
+            events2.lock().unwrap().clear();
+            first.do_send(Die);
+            delay_for(Duration::from_secs(1)).await;
+
+            // first child waits for its backoff, sibling keeps handling messages
+            other
+                .send(Ping)
+                .timeout(Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert!(events2.lock().unwrap().is_empty());
+
+            delay_for(Duration::from_secs(10)).await;
+            assert_eq!(
+                *events2.lock().unwrap(),
+                ["first restarting", "first started"]
+            );
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
+
+struct Panic;
+
+impl Message for Panic {
+    type Result = usize;
+}
+
+impl actix::Handler<Panic> for MyActor {
+    type Result = usize;
+
+    fn handle(&mut self, _: Panic, _: &mut actix::Context<MyActor>) -> usize {
+        if self.2.fetch_add(1, Ordering::Relaxed) == 0 {
+            panic!("handler failure");
+        }
+        self.1.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn test_supervisor_handler_panic() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let messages = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+    let restarts2 = Arc::clone(&restarts);
+    let messages2 = Arc::clone(&messages);
+
+    System::run(move || {
+        let addr =
+            actix::Supervisor::start(move |_| MyActor(starts2, restarts2, messages2));
+
+        actix::spawn(async move {
+            match addr.send(Panic).await {
+                Err(MailboxError::Panicked) => (),
+                _ => panic!("Request should fail"),
+            }
+            assert_eq!(addr.send(Panic).await.unwrap(), 1);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(starts.load(Ordering::Relaxed), 2);
+    assert_eq!(restarts.load(Ordering::Relaxed), 1);
+    assert_eq!(messages.load(Ordering::Relaxed), 2);
+}
+
+struct ActFuturePanic;
+
+impl Message for ActFuturePanic {
+    type Result = ();
+}
+
+struct FuturePanic;
+
+impl Message for FuturePanic {
+    type Result = ();
+}
+
+struct SpawnedPanic;
+
+impl Message for SpawnedPanic {
+    type Result = ();
+}
+
+struct FutureActor(Arc<AtomicUsize>);
+
+impl Actor for FutureActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Context<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl actix::Supervised for FutureActor {}
+
+impl actix::Handler<ActFuturePanic> for FutureActor {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _: ActFuturePanic, _: &mut Context<Self>) -> Self::Result {
+        Box::pin(actix::fut::ready(()).map(|_, _, _| panic!("act future failure")))
+    }
+}
+
+impl actix::Handler<FuturePanic> for FutureActor {
+    type Result = ResponseFuture<()>;
+
+    fn handle(&mut self, _: FuturePanic, _: &mut Context<Self>) -> Self::Result {
+        Box::pin(async { panic!("future failure") })
+    }
+}
+
+impl actix::Handler<SpawnedPanic> for FutureActor {
+    type Result = ();
+
+    fn handle(&mut self, _: SpawnedPanic, ctx: &mut Context<Self>) {
+        ctx.spawn(actix::fut::wrap_future(async { panic!("spawned failure") }));
+    }
+}
+
+#[test]
+fn test_supervisor_future_panic() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let results2 = Arc::clone(&results);
+
+    System::run(move || {
+        let addr = actix::Supervisor::start(move |_| FutureActor(starts2));
+
+        actix::spawn(async move {
+            let res = addr.send(ActFuturePanic).await;
+            results2.lock().unwrap().push(res.is_ok());
+            let res = addr.send(FuturePanic).await;
+            let panicked = match res {
+                Err(MailboxError::Panicked) => true,
+                _ => false,
+            };
+            results2.lock().unwrap().push(panicked);
+            let res = addr.send(SpawnedPanic).await;
+            results2.lock().unwrap().push(res.is_ok());
+            delay_for(Duration::from_millis(10)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    // arbiter survives, actor is restarted after failures in its context,
+    // response future is detached from the actor
+    assert_eq!(*results.lock().unwrap(), vec![false, true, true]);
+    assert_eq!(starts.load(Ordering::Relaxed), 3);
+}
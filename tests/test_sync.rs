@@ -1,7 +1,12 @@
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use actix::prelude::*;
+use actix::{Affinity, ShutdownPolicy, SupervisorStrategy, SyncArbiterBuilder};
+use futures_util::future::join_all;
 
 struct Fibonacci(pub u32);
 
@@ -96,3 +101,787 @@ fn test_sync() {
         "Wrong number of messages"
     );
 }
+
+struct Panic;
+
+impl Message for Panic {
+    type Result = usize;
+}
+
+struct PanicActor {
+    starts: Arc<AtomicUsize>,
+}
+
+impl Actor for PanicActor {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, _: &mut Self::Context) {
+        // first worker thread dies before handling any message
+        if self.starts.fetch_add(1, Ordering::Relaxed) == 0 {
+            panic!("started failure");
+        }
+    }
+}
+
+impl Handler<Panic> for PanicActor {
+    type Result = usize;
+
+    fn handle(&mut self, _: Panic, _: &mut Self::Context) -> usize {
+        if self.starts.load(Ordering::Relaxed) == 2 {
+            panic!("handler failure");
+        }
+        self.starts.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn test_sync_panic() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+
+    System::run(move || {
+        let addr = SyncArbiter::start(1, move || PanicActor {
+            starts: Arc::clone(&starts2),
+        });
+
+        actix::spawn(async move {
+            match addr.send(Panic).await {
+                Err(MailboxError::Panicked) => (),
+                _ => panic!("Request should fail"),
+            }
+            assert_eq!(addr.send(Panic).await.unwrap(), 3);
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(starts.load(Ordering::Relaxed), 3);
+}
+
+struct FactoryPanic;
+
+impl Actor for FactoryPanic {
+    type Context = SyncContext<Self>;
+}
+
+#[test]
+fn test_sync_panic_restart_limit() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let created2 = Arc::clone(&created);
+    let escalated = Arc::new(Mutex::new(None));
+    let escalated2 = Arc::clone(&escalated);
+
+    System::run(move || {
+        let strategy = SupervisorStrategy::new()
+            .max_restarts(2, Duration::from_secs(60))
+            .on_escalate(move |esc| *escalated2.lock().unwrap() = Some(esc.restarts));
+        SyncArbiter::builder(move || -> FactoryPanic {
+            created2.fetch_add(1, Ordering::SeqCst);
+            panic!("factory failure")
+        })
+        .supervisor_strategy(strategy)
+        .start();
+
+        actix::spawn(async {
+            tokio::time::delay_for(Duration::from_millis(200)).await;
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    // first start and two restarts
+    assert_eq!(created.load(Ordering::SeqCst), 3);
+    assert_eq!(*escalated.lock().unwrap(), Some(2));
+}
+
+struct Block;
+
+impl Message for Block {
+    type Result = ();
+}
+
+struct BlockActor {
+    gate: Arc<(Mutex<bool>, Condvar)>,
+    handled: Arc<AtomicUsize>,
+}
+
+impl Actor for BlockActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Block> for BlockActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Block, _: &mut Self::Context) {
+        let (ref lock, ref cond) = *self.gate;
+        let mut open = lock.lock().unwrap();
+        while !*open {
+            open = cond.wait(open).unwrap();
+        }
+        self.handled.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[actix_rt::test]
+#[allow(clippy::mutex_atomic)]
+async fn test_sync_bounded_queue() {
+    let gate = Arc::new((Mutex::new(false), Condvar::new()));
+    let handled = Arc::new(AtomicUsize::new(0));
+
+    let g = Arc::clone(&gate);
+    let h = Arc::clone(&handled);
+    let addr = SyncArbiter::builder(move || BlockActor {
+        gate: Arc::clone(&g),
+        handled: Arc::clone(&h),
+    })
+    .queue_capacity(2)
+    .start();
+
+    let mut accepted = 0;
+    let full = loop {
+        match addr.try_send(Block) {
+            Ok(()) => accepted += 1,
+            Err(SendError::Full(_)) => break true,
+            Err(SendError::Closed(_)) => break false,
+        }
+        if accepted == 20 {
+            break false;
+        }
+        tokio::time::delay_for(Duration::from_millis(1)).await;
+    };
+    assert!(full, "queue is not bounded");
+
+    *gate.0.lock().unwrap() = true;
+    gate.1.notify_all();
+
+    addr.send(Block).await.unwrap();
+    assert_eq!(handled.load(Ordering::SeqCst), accepted + 1);
+}
+
+struct Sleep;
+
+impl Message for Sleep {
+    type Result = ();
+}
+
+struct ElasticActor {
+    started: Arc<AtomicUsize>,
+    stopped: Arc<AtomicUsize>,
+    threads: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Actor for ElasticActor {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, _: &mut Self::Context) {
+        self.started.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.stopped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Handler<Sleep> for ElasticActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, _: &mut Self::Context) {
+        let name = thread::current().name().unwrap().to_owned();
+        self.threads.lock().unwrap().insert(name);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[actix_rt::test]
+async fn test_sync_elastic_pool() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+
+    let (s1, s2, t) = (
+        Arc::clone(&started),
+        Arc::clone(&stopped),
+        Arc::clone(&threads),
+    );
+    let addr = SyncArbiter::builder(move || ElasticActor {
+        started: Arc::clone(&s1),
+        stopped: Arc::clone(&s2),
+        threads: Arc::clone(&t),
+    })
+    .min_threads(1)
+    .max_threads(3)
+    .idle_timeout(Duration::from_millis(50))
+    .thread_name("elastic")
+    .stack_size(256 * 1024)
+    .start();
+
+    join_all((0..6).map(|_| addr.send(Sleep))).await;
+
+    let workers = started.load(Ordering::SeqCst);
+    assert!((2..=3).contains(&workers), "{} workers", workers);
+    for name in threads.lock().unwrap().iter() {
+        assert!(name.starts_with("elastic-"), "{}", name);
+    }
+
+    // workers above the minimum exit once idle
+    tokio::time::delay_for(Duration::from_millis(300)).await;
+    assert_eq!(stopped.load(Ordering::SeqCst), workers - 1);
+
+    addr.send(Sleep).await.unwrap();
+    assert_eq!(started.load(Ordering::SeqCst), workers);
+}
+
+#[derive(Clone, Default)]
+struct Shutdown {
+    handled: Arc<AtomicUsize>,
+    stopped: Arc<Mutex<Vec<Option<StopReason>>>>,
+}
+
+struct ShutdownActor {
+    delay: Duration,
+    state: Shutdown,
+}
+
+impl Actor for ShutdownActor {
+    type Context = SyncContext<Self>;
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        let reason = ctx.stop_reason().cloned();
+        self.state.stopped.lock().unwrap().push(reason);
+    }
+}
+
+impl Handler<Sleep> for ShutdownActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, _: &mut Self::Context) {
+        thread::sleep(self.delay);
+        self.state.handled.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn run_shutdown(builder: SyncArbiterBuilder<ShutdownActor>, msgs: usize) {
+    System::run(move || {
+        let addr = builder.start();
+        for _ in 0..msgs {
+            addr.do_send(Sleep);
+        }
+        actix::spawn(async move {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+            System::current().stop();
+            // address is connected until the system stops
+            drop(addr);
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_sync_shutdown_drain() {
+    let state = Shutdown::default();
+    let s = state.clone();
+    let builder = SyncArbiter::builder(move || ShutdownActor {
+        delay: Duration::from_millis(20),
+        state: s.clone(),
+    })
+    .threads(2)
+    .shutdown_policy(ShutdownPolicy::Drain);
+
+    run_shutdown(builder, 6);
+
+    // system waits for workers to handle queued messages
+    assert_eq!(state.handled.load(Ordering::SeqCst), 6);
+    assert_eq!(
+        *state.stopped.lock().unwrap(),
+        vec![Some(StopReason::Shutdown), Some(StopReason::Shutdown)]
+    );
+}
+
+#[test]
+fn test_sync_shutdown_drain_elastic() {
+    let state = Shutdown::default();
+    let s = state.clone();
+
+    System::run(move || {
+        let addr = SyncArbiter::builder(move || ShutdownActor {
+            delay: Duration::from_millis(1),
+            state: s.clone(),
+        })
+        .min_threads(0)
+        .max_threads(1)
+        .shutdown_policy(ShutdownPolicy::Drain)
+        .start();
+        for _ in 0..3 {
+            addr.do_send(Sleep);
+        }
+        System::current().stop();
+    })
+    .unwrap();
+
+    // pool without minimum of workers handles queued messages
+    assert_eq!(state.handled.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        *state.stopped.lock().unwrap(),
+        vec![Some(StopReason::Shutdown)]
+    );
+}
+
+#[test]
+fn test_sync_shutdown_abandon() {
+    let state = Shutdown::default();
+    let s = state.clone();
+    let builder = SyncArbiter::builder(move || ShutdownActor {
+        delay: Duration::from_millis(50),
+        state: s.clone(),
+    });
+
+    // queued messages are abandoned by default
+    run_shutdown(builder, 5);
+
+    assert!(state.handled.load(Ordering::SeqCst) < 5);
+    assert_eq!(
+        *state.stopped.lock().unwrap(),
+        vec![Some(StopReason::Shutdown)]
+    );
+}
+
+#[test]
+fn test_sync_shutdown_timeout() {
+    let state = Shutdown::default();
+    let s = state.clone();
+    let builder = SyncArbiter::builder(move || ShutdownActor {
+        delay: Duration::from_millis(500),
+        state: s.clone(),
+    })
+    .shutdown_timeout(Duration::from_millis(20));
+
+    let start = Instant::now();
+    run_shutdown(builder, 1);
+
+    assert!(start.elapsed() < Duration::from_millis(400));
+    assert_eq!(state.handled.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_sync_shutdown_default_timeout() {
+    let state = Shutdown::default();
+    let s = state.clone();
+    let builder = SyncArbiter::builder(move || ShutdownActor {
+        delay: Duration::from_secs(5),
+        state: s.clone(),
+    });
+
+    // stopping the system does not wait for a long handler
+    let start = Instant::now();
+    run_shutdown(builder, 1);
+
+    assert!(start.elapsed() < Duration::from_secs(3));
+    assert_eq!(state.handled.load(Ordering::SeqCst), 0);
+}
+
+struct Tick(&'static str);
+
+impl Message for Tick {
+    type Result = ();
+}
+
+type TickLog = Arc<Mutex<Vec<(thread::ThreadId, &'static str)>>>;
+
+struct TimerActor {
+    log: TickLog,
+}
+
+impl TimerActor {
+    fn record(&self, event: &'static str) {
+        self.log
+            .lock()
+            .unwrap()
+            .push((thread::current().id(), event));
+    }
+}
+
+impl Actor for TimerActor {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.notify(Tick("notify"));
+        ctx.run_later(Duration::from_millis(5), |act, _| act.record("later"));
+        ctx.run_interval(Duration::from_millis(5), |act, _| act.record("interval"));
+
+        let handle = ctx.notify_later(Tick("cancelled"), Duration::from_millis(5));
+        // second cancel does not find the timer
+        if !ctx.cancel_timer(handle) || ctx.cancel_timer(handle) {
+            self.record("not cancelled");
+        }
+    }
+}
+
+impl Handler<Tick> for TimerActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Tick, _: &mut Self::Context) {
+        self.record(msg.0);
+    }
+}
+
+#[actix_rt::test]
+async fn test_sync_timers() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let l = Arc::clone(&log);
+    let _addr = SyncArbiter::start(2, move || TimerActor {
+        log: Arc::clone(&l),
+    });
+
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+
+    let log = log.lock().unwrap().clone();
+    let threads: HashSet<_> = log.iter().map(|(id, _)| *id).collect();
+    assert_eq!(threads.len(), 2);
+    // every worker handles its own notifications and timers
+    for thread in threads {
+        let events: Vec<_> = log
+            .iter()
+            .filter(|(id, _)| *id == thread)
+            .map(|(_, event)| *event)
+            .collect();
+        assert_eq!(events[0], "notify");
+        assert_eq!(events.iter().filter(|e| **e == "later").count(), 1);
+        assert!(events.iter().filter(|e| **e == "interval").count() >= 2);
+        assert!(!events.contains(&"cancelled"));
+        assert!(!events.contains(&"not cancelled"));
+    }
+}
+
+struct Work {
+    key: usize,
+    seq: usize,
+}
+
+impl Message for Work {
+    type Result = ();
+}
+
+impl Affinity for Work {
+    type Key = usize;
+
+    fn affinity_key(&self) -> usize {
+        self.key
+    }
+}
+
+type WorkLog = Arc<Mutex<Vec<(thread::ThreadId, usize, usize)>>>;
+
+struct AffinityActor {
+    log: WorkLog,
+}
+
+impl Actor for AffinityActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Work> for AffinityActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Work, _: &mut Self::Context) {
+        thread::sleep(Duration::from_millis(1));
+        self.log
+            .lock()
+            .unwrap()
+            .push((thread::current().id(), msg.key, msg.seq));
+    }
+}
+
+impl Handler<Sleep> for AffinityActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, _: &mut Self::Context) {}
+}
+
+struct IdleTimer {
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Actor for IdleTimer {
+    type Context = SyncContext<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.log.lock().unwrap().push("stopped");
+    }
+}
+
+impl Handler<Sleep> for IdleTimer {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, ctx: &mut Self::Context) {
+        ctx.notify_later(Tick("late"), Duration::from_millis(150));
+    }
+}
+
+impl Handler<Tick> for IdleTimer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Tick, _: &mut Self::Context) {
+        self.log.lock().unwrap().push(msg.0);
+    }
+}
+
+#[actix_rt::test]
+async fn test_sync_idle_timeout_pending_timer() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let l = Arc::clone(&log);
+    let addr = SyncArbiter::builder(move || IdleTimer {
+        log: Arc::clone(&l),
+    })
+    .min_threads(0)
+    .max_threads(1)
+    .idle_timeout(Duration::from_millis(50))
+    .start();
+
+    addr.send(Sleep).await.unwrap();
+    tokio::time::delay_for(Duration::from_millis(400)).await;
+
+    // worker retires once its timer fired
+    assert_eq!(*log.lock().unwrap(), vec!["late", "stopped"]);
+}
+
+#[actix_rt::test]
+async fn test_sync_affinity() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let l = Arc::clone(&log);
+    let addr = SyncArbiter::builder(move || AffinityActor {
+        log: Arc::clone(&l),
+    })
+    .threads(4)
+    .affinity::<Work>()
+    .start();
+
+    for seq in 0..5 {
+        for key in 0..16 {
+            addr.do_send(Work { key, seq });
+        }
+    }
+    // messages without key are handled by any worker
+    join_all((0..8).map(|_| addr.send(Sleep))).await;
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log.len(), 80);
+    let mut threads = HashSet::new();
+    for key in 0..16 {
+        let handled: Vec<_> = log.iter().filter(|(_, k, _)| *k == key).collect();
+        let seqs: Vec<_> = handled.iter().map(|(_, _, seq)| *seq).collect();
+        assert_eq!(seqs, vec![0, 1, 2, 3, 4]);
+        let thread = handled[0].0;
+        assert!(handled.iter().all(|(id, _, _)| *id == thread));
+        threads.insert(thread);
+    }
+    assert!(threads.len() > 1);
+}
+
+struct Restart(usize);
+
+impl Message for Restart {
+    type Result = ();
+}
+
+impl Affinity for Restart {
+    type Key = usize;
+
+    fn affinity_key(&self) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Restart> for AffinityActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Restart, ctx: &mut Self::Context) {
+        thread::sleep(Duration::from_millis(20));
+        ctx.stop();
+    }
+}
+
+#[actix_rt::test]
+async fn test_sync_affinity_worker_not_restarted() {
+    let created = AtomicUsize::new(0);
+    // the replacement actor panics, and the worker is not restarted
+    let addr = SyncArbiter::builder(move || {
+        if created.fetch_add(1, Ordering::SeqCst) > 0 {
+            panic!("factory failure");
+        }
+        AffinityActor {
+            log: WorkLog::default(),
+        }
+    })
+    .threads(1)
+    .affinity::<Work>()
+    .affinity::<Restart>()
+    .supervisor_strategy(
+        SupervisorStrategy::new().max_restarts(0, Duration::from_secs(60)),
+    )
+    .start();
+
+    // queued behind the message which stops the actor
+    let restart = addr.send(Restart(1));
+    let queued = addr.send(Work { key: 1, seq: 0 });
+    restart.await.unwrap();
+    let res = queued.timeout(Duration::from_secs(1)).await;
+    assert!(is_closed(res));
+
+    // messages sent once the worker is gone fail as well
+    let res = addr
+        .send(Work { key: 1, seq: 1 })
+        .timeout(Duration::from_secs(1))
+        .await;
+    assert!(is_closed(res));
+}
+
+fn is_closed(res: Result<(), MailboxError>) -> bool {
+    match res {
+        Err(MailboxError::Closed) => true,
+        _ => false,
+    }
+}
+
+#[derive(Clone, Default)]
+struct Blocking {
+    running: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+    handled: Arc<AtomicUsize>,
+    threads: Arc<Mutex<HashSet<thread::ThreadId>>>,
+}
+
+struct BlockingActor(Blocking);
+
+impl Actor for BlockingActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Sleep> for BlockingActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, _: &mut Self::Context) {
+        let state = &self.0;
+        let running = state.running.fetch_add(1, Ordering::SeqCst) + 1;
+        {
+            // peak is updated under the lock
+            let mut threads = state.threads.lock().unwrap();
+            threads.insert(thread::current().id());
+            if running > state.peak.load(Ordering::SeqCst) {
+                state.peak.store(running, Ordering::SeqCst);
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+        state.running.fetch_sub(1, Ordering::SeqCst);
+        state.handled.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct OtherBlockingActor(Blocking);
+
+impl Actor for OtherBlockingActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Sleep> for OtherBlockingActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Sleep, _: &mut Self::Context) {
+        self.0
+            .threads
+            .lock()
+            .unwrap()
+            .insert(thread::current().id());
+    }
+}
+
+#[actix_rt::test]
+async fn test_sync_blocking_pool() {
+    let state = Blocking::default();
+    let s = state.clone();
+    let addr = SyncArbiter::builder(move || BlockingActor(s.clone()))
+        .blocking_pool()
+        .min_threads(0)
+        .max_threads(2)
+        .idle_timeout(Duration::from_millis(20))
+        .start();
+
+    join_all((0..6).map(|_| addr.send(Sleep))).await;
+    assert_eq!(state.handled.load(Ordering::SeqCst), 6);
+    assert_eq!(state.peak.load(Ordering::SeqCst), 2);
+    let threads = state.threads.lock().unwrap().clone();
+    assert!(!threads.contains(&thread::current().id()));
+
+    // idle workers return their threads to the pool, which is shared with
+    // other actor types
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+    let other = Blocking::default();
+    let o = other.clone();
+    let other_addr = SyncArbiter::builder(move || OtherBlockingActor(o.clone()))
+        .blocking_pool()
+        .min_threads(0)
+        .start();
+    other_addr.send(Sleep).await.unwrap();
+    let used = other.threads.lock().unwrap().clone();
+    assert!(used.is_subset(&threads));
+
+    addr.send(Sleep).await.unwrap();
+    assert_eq!(state.handled.load(Ordering::SeqCst), 7);
+}
+
+#[actix_rt::test]
+async fn test_sync_blocking_pool_idle() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+
+    let (s1, s2, t) = (
+        Arc::clone(&started),
+        Arc::clone(&stopped),
+        Arc::clone(&threads),
+    );
+    let addr = SyncArbiter::builder(move || ElasticActor {
+        started: Arc::clone(&s1),
+        stopped: Arc::clone(&s2),
+        threads: Arc::clone(&t),
+    })
+    .blocking_pool()
+    .min_threads(1)
+    .max_threads(3)
+    .idle_timeout(Duration::from_millis(50))
+    .start();
+
+    join_all((0..6).map(|_| addr.send(Sleep))).await;
+    let actors = started.load(Ordering::SeqCst);
+    assert!((2..=3).contains(&actors), "{} actors", actors);
+
+    // idle actors above the minimum are stopped
+    tokio::time::delay_for(Duration::from_millis(300)).await;
+    assert_eq!(stopped.load(Ordering::SeqCst), actors - 1);
+
+    // the remaining actor is reused
+    addr.send(Sleep).await.unwrap();
+    assert_eq!(started.load(Ordering::SeqCst), actors);
+}
+
+#[test]
+fn test_sync_blocking_pool_shutdown() {
+    let state = Shutdown::default();
+    let s = state.clone();
+    let builder = SyncArbiter::builder(move || ShutdownActor {
+        delay: Duration::from_millis(20),
+        state: s.clone(),
+    })
+    .blocking_pool()
+    .threads(2)
+    .shutdown_policy(ShutdownPolicy::Drain);
+
+    run_shutdown(builder, 6);
+
+    // queued messages are handled by the actors which are already running
+    assert_eq!(state.handled.load(Ordering::SeqCst), 6);
+    assert_eq!(
+        *state.stopped.lock().unwrap(),
+        vec![Some(StopReason::Shutdown), Some(StopReason::Shutdown)]
+    );
+}
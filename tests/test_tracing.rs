@@ -0,0 +1,237 @@
+#![cfg(feature = "tracing")]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use actix::prelude::*;
+use tokio::time::{delay_for, Duration};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+struct SpanData {
+    meta: &'static Metadata<'static>,
+    fields: HashMap<String, String>,
+    parent: Option<u64>,
+}
+
+#[derive(Default)]
+struct Spans {
+    next: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    events: Mutex<Vec<(String, Option<u64>)>>,
+}
+
+impl Spans {
+    fn event(&self, message: &str) -> u64 {
+        let events = self.events.lock().unwrap();
+        let (_, span) = events.iter().find(|(msg, _)| msg == message).unwrap();
+        span.unwrap()
+    }
+
+    fn name(&self, id: u64) -> &'static str {
+        self.spans.lock().unwrap()[&id].meta.name()
+    }
+
+    fn parent(&self, id: u64) -> Option<u64> {
+        self.spans.lock().unwrap()[&id].parent
+    }
+
+    fn field(&self, id: u64, name: &str) -> Option<String> {
+        self.spans.lock().unwrap()[&id].fields.get(name).cloned()
+    }
+
+    fn find(&self, name: &str) -> Option<u64> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, span)| span.meta.name() == name)
+            .map(|(id, _)| *id)
+    }
+}
+
+thread_local! {
+    static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+fn current() -> Option<u64> {
+    STACK.with(|stack| stack.borrow().last().cloned())
+}
+
+struct Fields<'a>(&'a mut HashMap<String, String>);
+
+impl<'a> Visit for Fields<'a> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{:?}", value));
+    }
+}
+
+struct Recorder(Arc<Spans>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = self.0.next.fetch_add(1, Ordering::SeqCst) + 1;
+        let parent = if attrs.is_root() {
+            None
+        } else if let Some(parent) = attrs.parent() {
+            Some(parent.into_u64())
+        } else {
+            current()
+        };
+        let mut fields = HashMap::new();
+        attrs.record(&mut Fields(&mut fields));
+        self.0.spans.lock().unwrap().insert(
+            id,
+            SpanData {
+                meta: attrs.metadata(),
+                fields,
+                parent,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.0.spans.lock().unwrap();
+        let span = spans.get_mut(&span.into_u64()).unwrap();
+        values.record(&mut Fields(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = HashMap::new();
+        event.record(&mut Fields(&mut fields));
+        self.0
+            .events
+            .lock()
+            .unwrap()
+            .push((fields.remove("message").unwrap_or_default(), current()));
+    }
+
+    fn enter(&self, span: &Id) {
+        STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, _: &Id) {
+        STACK.with(|stack| stack.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        match current() {
+            Some(id) => {
+                let meta = self.0.spans.lock().unwrap()[&id].meta;
+                Current::new(Id::from_u64(id), meta)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+struct Ping;
+
+impl Message for Ping {
+    type Result = ();
+}
+
+struct MyActor;
+
+impl Actor for MyActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<Ping> for MyActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, ctx: &mut Self::Context) {
+        tracing::info!("handler");
+        ctx.spawn(
+            async {}
+                .into_actor(self)
+                .map(|_, _, _| tracing::info!("future")),
+        );
+    }
+}
+
+struct SyncActor;
+
+impl Actor for SyncActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Ping> for SyncActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {
+        tracing::info!("sync handler");
+    }
+}
+
+#[test]
+fn test_tracing_spans() {
+    let spans = Arc::new(Spans::default());
+    tracing::subscriber::set_global_default(Recorder(spans.clone())).unwrap();
+
+    System::run(move || {
+        let addr = MyActor.start();
+        let sync_addr = SyncArbiter::start(1, || SyncActor);
+
+        let request = tracing::info_span!("request");
+        let _enter = request.enter();
+        addr.do_send(Ping);
+        sync_addr.do_send(Ping);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(50)).await;
+            let request = spans.find("request").unwrap();
+
+            // handler runs in a span of the message, child of sender's span
+            let handle = spans.event("handler");
+            assert_eq!(spans.name(handle), "handle");
+            assert_eq!(spans.parent(handle), Some(request));
+            assert_eq!(
+                spans.field(handle, "actor").unwrap(),
+                std::any::type_name::<MyActor>()
+            );
+            assert_eq!(
+                spans.field(handle, "message").unwrap(),
+                std::any::type_name::<Ping>()
+            );
+            assert!(spans.field(handle, "handle").is_some());
+
+            // futures spawned by handler run in the same span
+            assert_eq!(spans.event("future"), handle);
+
+            let handle = spans.event("sync handler");
+            assert_eq!(spans.name(handle), "handle");
+            assert_eq!(spans.parent(handle), Some(request));
+            assert_eq!(
+                spans.field(handle, "actor").unwrap(),
+                std::any::type_name::<SyncActor>()
+            );
+
+            let worker = spans.find("sync_worker").unwrap();
+            assert_eq!(
+                spans.field(worker, "actor").unwrap(),
+                std::any::type_name::<SyncActor>()
+            );
+
+            System::current().stop();
+        });
+    })
+    .unwrap();
+}
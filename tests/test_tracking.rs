@@ -0,0 +1,45 @@
+//! Tracking of actors by `TestSystem`, runs in its own process since
+//! tracking is global.
+use std::thread;
+
+use actix::introspection;
+use actix::prelude::*;
+use actix::testing::TestSystem;
+
+struct Idle;
+
+impl Actor for Idle {
+    type Context = Context<Self>;
+}
+
+fn listed<A: Actor>(addr: &Addr<A>) -> bool {
+    introspection::actors()
+        .iter()
+        .any(|info| info.id == addr.id())
+}
+
+#[test]
+fn test_actors_tracked_while_test_system_exists() {
+    let mut sys = TestSystem::new();
+    let first = sys.block_on(async { Idle.start() });
+    assert!(listed(&first));
+
+    // nested test system on another thread
+    thread::spawn(|| {
+        let mut sys = TestSystem::new();
+        let addr = sys.block_on(async { Idle.start() });
+        assert!(listed(&addr));
+    })
+    .join()
+    .unwrap();
+
+    // outer system keeps tracking after the nested one is dropped
+    let second = sys.block_on(async { Idle.start() });
+    assert!(listed(&second));
+    drop(sys);
+
+    System::new("untracked").block_on(async {
+        let addr = Idle.start();
+        assert!(!listed(&addr));
+    });
+}
@@ -0,0 +1,322 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use actix::prelude::*;
+use tokio::time::{delay_for, Duration};
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Stop;
+
+struct Worker(Arc<AtomicBool>);
+
+impl Actor for Worker {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Handler<Stop> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+struct Watcher {
+    worker: Addr<Worker>,
+    terminated: Arc<Mutex<Vec<ActorId>>>,
+}
+
+impl Actor for Watcher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.watch(&self.worker);
+    }
+}
+
+impl Handler<Terminated> for Watcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Terminated, _: &mut Self::Context) {
+        self.terminated.lock().unwrap().push(msg.id);
+    }
+}
+
+#[test]
+fn test_watch() {
+    let terminated = Arc::new(Mutex::new(Vec::new()));
+    let terminated2 = Arc::clone(&terminated);
+    let id = Arc::new(Mutex::new(None));
+    let id2 = Arc::clone(&id);
+
+    System::run(move || {
+        let worker = Worker(Arc::new(AtomicBool::new(false))).start();
+        *id2.lock().unwrap() = Some(worker.id());
+        let watcher = Watcher {
+            worker: worker.clone(),
+            terminated: terminated2,
+        }
+        .start();
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            worker.do_send(Stop);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(watcher.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let id = id.lock().unwrap().unwrap();
+    assert_eq!(*terminated.lock().unwrap(), vec![id]);
+}
+
+#[test]
+fn test_watch_stopped_actor() {
+    let terminated = Arc::new(Mutex::new(Vec::new()));
+    let terminated2 = Arc::clone(&terminated);
+
+    System::run(move || {
+        let worker = Worker(Arc::new(AtomicBool::new(false))).start();
+        let recipient = worker.clone().recipient::<Stop>();
+        worker.do_send(Stop);
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            assert!(!recipient.connected());
+            let watcher = Watcher {
+                worker,
+                terminated: terminated2,
+            }
+            .start();
+            delay_for(Duration::from_millis(10)).await;
+            assert!(watcher.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert_eq!(terminated.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_unwatch() {
+    let notified = Arc::new(AtomicBool::new(false));
+    let notified2 = Arc::clone(&notified);
+
+    System::run(move || {
+        let worker = Worker(Arc::new(AtomicBool::new(false))).start();
+        let handle = worker.on_terminated(
+            Box::new(move |_| notified2.store(true, Ordering::Relaxed)),
+            Box::new(|| true),
+        );
+        handle.unwatch();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        worker.on_terminated(
+            Box::new(move |_| {
+                let _ = tx.send(());
+            }),
+            Box::new(|| true),
+        );
+        worker.do_send(Stop);
+
+        actix::spawn(async move {
+            rx.await.unwrap();
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(!notified.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_prune_dead_watchers() {
+    System::run(|| {
+        let token = Arc::new(());
+        let token2 = Arc::clone(&token);
+        let worker = Worker(Arc::new(AtomicBool::new(false))).start();
+        worker.on_terminated(Box::new(move |_| drop(token2)), Box::new(|| false));
+        for _ in 0..7 {
+            worker.on_terminated(Box::new(|_| ()), Box::new(|| true));
+        }
+        assert_eq!(Arc::strong_count(&token), 2);
+
+        // dead watcher is dropped without being called
+        worker.on_terminated(Box::new(|_| ()), Box::new(|| true));
+        assert_eq!(Arc::strong_count(&token), 1);
+        System::current().stop();
+    })
+    .unwrap();
+}
+
+struct Linked(Arc<AtomicBool>);
+
+impl Actor for Linked {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_link() {
+    let worker_stopped = Arc::new(AtomicBool::new(false));
+    let linked_stopped = Arc::new(AtomicBool::new(false));
+    let worker_stopped2 = Arc::clone(&worker_stopped);
+    let linked_stopped2 = Arc::clone(&linked_stopped);
+
+    System::run(move || {
+        let worker = Worker(worker_stopped2).start();
+        let worker2 = worker.clone();
+        let linked = Linked::create(move |ctx| {
+            ctx.link(&worker2.recipient::<Stop>());
+            Linked(linked_stopped2)
+        });
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            worker.do_send(Stop);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(!linked.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    assert!(worker_stopped.load(Ordering::Relaxed));
+    assert!(linked_stopped.load(Ordering::Relaxed));
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct StopWith(StopReason);
+
+struct Reasons(Arc<Mutex<Option<StopReason>>>);
+
+impl Actor for Reasons {
+    type Context = Context<Self>;
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        *self.0.lock().unwrap() = ctx.stop_reason().cloned();
+    }
+}
+
+impl Handler<StopWith> for Reasons {
+    type Result = ();
+
+    fn handle(&mut self, msg: StopWith, ctx: &mut Self::Context) {
+        ctx.stop_with(msg.0);
+        // first reason wins
+        ctx.stop_with(StopReason::Normal);
+    }
+}
+
+struct ReasonWatcher {
+    addrs: Vec<Addr<Reasons>>,
+    terminated: Arc<Mutex<Vec<Terminated>>>,
+}
+
+impl Actor for ReasonWatcher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        for addr in self.addrs.drain(..) {
+            ctx.watch(&addr);
+        }
+    }
+}
+
+impl Handler<Terminated> for ReasonWatcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Terminated, _: &mut Self::Context) {
+        self.terminated.lock().unwrap().push(msg);
+    }
+}
+
+#[test]
+fn test_stop_reason() {
+    let terminated = Arc::new(Mutex::new(Vec::new()));
+    let terminated2 = Arc::clone(&terminated);
+    let observed = Arc::new(Mutex::new(None));
+    let observed2 = Arc::clone(&observed);
+    let ids = Arc::new(Mutex::new(Vec::new()));
+    let ids2 = Arc::clone(&ids);
+
+    System::run(move || {
+        let failing = Reasons(observed2).start();
+        let closed = Reasons(Arc::new(Mutex::new(None))).start();
+        *ids2.lock().unwrap() = vec![failing.id(), closed.id()];
+
+        let watcher = ReasonWatcher {
+            addrs: vec![failing.clone(), closed],
+            terminated: terminated2,
+        }
+        .start();
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            failing.do_send(StopWith(StopReason::Error("boom".to_owned())));
+            delay_for(Duration::from_millis(10)).await;
+            assert!(watcher.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let reason = StopReason::Error("boom".to_owned());
+    assert_eq!(*observed.lock().unwrap(), Some(reason.clone()));
+
+    let ids = ids.lock().unwrap();
+    let mut terminated = terminated.lock().unwrap().clone();
+    terminated.sort_by_key(|t| t.id);
+    assert_eq!(
+        terminated,
+        vec![
+            Terminated { id: ids[0], reason },
+            Terminated {
+                id: ids[1],
+                reason: StopReason::MailboxClosed,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_link_stop_reason() {
+    let observed = Arc::new(Mutex::new(None));
+    let observed2 = Arc::clone(&observed);
+    let id = Arc::new(Mutex::new(None));
+    let id2 = Arc::clone(&id);
+
+    System::run(move || {
+        let worker = Worker(Arc::new(AtomicBool::new(false))).start();
+        *id2.lock().unwrap() = Some(worker.id());
+        let worker2 = worker.clone();
+        let linked = Reasons::create(move |ctx| {
+            ctx.link(&worker2);
+            Reasons(observed2)
+        });
+
+        actix::spawn(async move {
+            delay_for(Duration::from_millis(10)).await;
+            worker.do_send(Stop);
+            delay_for(Duration::from_millis(10)).await;
+            assert!(!linked.connected());
+            System::current().stop();
+        });
+    })
+    .unwrap();
+
+    let id = id.lock().unwrap().unwrap();
+    assert_eq!(*observed.lock().unwrap(), Some(StopReason::Linked(id)));
+}